@@ -0,0 +1,77 @@
+// Compares `parser::parse` with `arena::parse`.
+//
+//     cargo bench --bench arena_ast
+//
+// Reports the time per MB of source and the memory that the resulting program
+// holds on to per MB of source.  Memory is measured by counting allocations
+// made through the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use ast_store::{arena, parser};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let statement = "console.log(\"hello, \", foo.bar(baz), qux);\ndebugger;\n";
+    let code = statement.repeat(1_000_000 / statement.len());
+    let mb = code.len() as f64 / 1_000_000.0;
+
+    let (time, retained) = measure(&code, |code| parser::parse(code).unwrap());
+    report("ast", mb, time, retained);
+
+    let (time, retained) = measure(&code, |code| arena::parse(code).unwrap());
+    report("arena", mb, time, retained);
+}
+
+// Returns the average time to parse `code` and the number of bytes the result
+// holds on to.
+fn measure<T>(code: &str, parse: impl Fn(String) -> T) -> (Duration, usize) {
+    let mut total = Duration::ZERO;
+    let mut retained = 0;
+
+    for _ in 0..ITERATIONS {
+        // The source is moved into the parser and may be kept by the result,
+        // so its allocation is counted as well.
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let code = code.to_string();
+
+        let start = Instant::now();
+        let program = parse(code);
+        total += start.elapsed();
+
+        retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+        drop(program);
+    }
+
+    (total / ITERATIONS, retained)
+}
+
+fn report(name: &str, mb: f64, time: Duration, retained: usize) {
+    println!(
+        "{name:>6}  {:>10.2?} per MB  {:>8.2} MB retained per MB",
+        time.div_f64(mb),
+        retained as f64 / 1_000_000.0 / mb
+    );
+}
@@ -0,0 +1,88 @@
+// Compares exposing a program to JS with `serde_v8` against `lazy_ast`.
+//
+//     cargo bench --bench lazy_ast
+//
+// Both the time it takes and the amount of V8 heap that's in use afterwards
+// are reported, once for a script which walks the whole tree and once for a
+// script which only looks at the root.
+
+use std::time::{Duration, Instant};
+
+use ast_store::{lazy_ast, parser};
+
+const ITERATIONS: usize = 20;
+
+const TRAVERSE: &str = r#"
+    (function visit(node) {
+        let count = 1;
+        for (const key of Object.keys(node)) {
+            const value = node[key];
+            if (Array.isArray(value)) {
+                for (const child of value) {
+                    if (child && typeof child.type === "string") count += visit(child);
+                }
+            } else if (value && typeof value.type === "string") {
+                count += visit(value);
+            }
+        }
+        return count;
+    })(ast)
+"#;
+
+const ROOT_ONLY: &str = "ast.type";
+
+fn main() {
+    let platform = v8::new_default_platform(0, false).make_shared();
+    v8::V8::initialize_platform(platform);
+    v8::V8::initialize();
+
+    let code = "console.log(\"hello, \", foo.bar(baz));\ndebugger;\n".repeat(5_000);
+
+    for (name, script) in [("traverse", TRAVERSE), ("root only", ROOT_ONLY)] {
+        let eager = run(&code, script, |scope, program| {
+            serde_v8::to_v8(scope, &program).unwrap()
+        });
+        let lazy = run(&code, script, lazy_ast::to_v8);
+
+        println!("{name}:");
+        println!("  serde_v8  {:>10.2?}  {:>8} KiB", eager.0, eager.1 / 1024);
+        println!("  lazy_ast  {:>10.2?}  {:>8} KiB", lazy.0, lazy.1 / 1024);
+    }
+}
+
+// Returns the average time and the heap used after the last iteration.
+fn run(
+    code: &str,
+    script: &str,
+    to_v8: impl for<'s> Fn(
+        &mut v8::HandleScope<'s>,
+        ast_store::ast::Program,
+    ) -> v8::Local<'s, v8::Value>,
+) -> (Duration, usize) {
+    let mut total = Duration::ZERO;
+    let mut used_heap_size = 0;
+
+    for _ in 0..ITERATIONS {
+        let program = parser::parse(code.to_string()).unwrap();
+
+        let isolate = &mut v8::Isolate::new(Default::default());
+        let scope = &mut v8::HandleScope::new(isolate);
+        let context = v8::Context::new(scope);
+        let scope = &mut v8::ContextScope::new(scope, context);
+
+        let start = Instant::now();
+        let ast = to_v8(scope, program);
+        let key = v8::String::new(scope, "ast").unwrap();
+        context.global(scope).set(scope, key.into(), ast);
+        let source = v8::String::new(scope, script).unwrap();
+        let script = v8::Script::compile(scope, source, None).unwrap();
+        script.run(scope).unwrap();
+        total += start.elapsed();
+
+        let mut stats = v8::HeapStatistics::default();
+        scope.get_heap_statistics(&mut stats);
+        used_heap_size = stats.used_heap_size();
+    }
+
+    (total / ITERATIONS as u32, used_heap_size)
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+use ast_store::{invariants, parser};
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo +nightly fuzz run parse` from the repo root.
+fuzz_target!(|code: &str| {
+    if let Ok(program) = parser::parse(code.to_string()) {
+        if let Err(message) = invariants::check(&program) {
+            panic!("{message}\n\ncode: {code:?}");
+        }
+    }
+});
@@ -0,0 +1,281 @@
+// Decodes programs encoded by `binary::encode` in src/binary.rs into ESTree
+// objects.  This only relies on the language itself so that it can run in the
+// embedded V8 context, which doesn't have `TextDecoder`.
+
+const MAGIC = "ESTB";
+const VERSION = 1;
+
+const OPTIONAL = 1;
+const COMPUTED = 2;
+
+const SOURCE_TYPES = ["script", "module"];
+const TOKEN_TYPES = ["Identifier", "Keyword", "String", "Punctuator"];
+const COMMENT_TYPES = ["Line", "Block"];
+
+export function decode(bytes) {
+  const decoder = new Decoder(bytes);
+
+  for (let i = 0; i < MAGIC.length; i++) {
+    if (decoder.byte() !== MAGIC.charCodeAt(i)) {
+      throw new Error("not a binary AST");
+    }
+  }
+  const version = decoder.varint();
+  if (version !== VERSION) {
+    throw new Error(
+      `unsupported binary AST version ${version}, expected ${VERSION}`
+    );
+  }
+
+  let count = decoder.varint();
+  for (let i = 0; i < count; i++) {
+    decoder.strings.push(decoder.utf8(decoder.varint()));
+  }
+
+  count = decoder.varint();
+  let start = 0;
+  for (let i = 0; i < count; i++) {
+    start += decoder.varint();
+    decoder.lineStarts.push(start);
+  }
+
+  return decoder.program();
+}
+
+class Decoder {
+  constructor(bytes) {
+    this.bytes = bytes;
+    this.pos = 0;
+    this.strings = [];
+    this.lineStarts = [];
+  }
+
+  program() {
+    const sourceType = this.tag("source type", SOURCE_TYPES);
+    const range = this.range();
+
+    const body = this.list(() => this.node());
+    const tokens = this.list(() => {
+      const type = this.tag("token", TOKEN_TYPES);
+      return { type, ...this.tokenValue() };
+    });
+    const comments = this.list(() => {
+      const type = this.tag("comment", COMMENT_TYPES);
+      return { type, ...this.tokenValue() };
+    });
+
+    return {
+      type: "Program",
+      loc: this.loc(range),
+      range,
+      sourceType,
+      body,
+      tokens,
+      comments,
+    };
+  }
+
+  // Fields are added in the same order as the serde representation so that
+  // the result is indistinguishable from `serde_v8::to_v8`'s.
+  node() {
+    const tag = this.byte();
+    const range = this.range();
+    const loc = this.loc(range);
+
+    // Tags match the `tag` module in src/binary.rs.
+    switch (tag) {
+      case 0:
+        return { type: "DebuggerStatement", loc, range };
+      case 1:
+        return {
+          type: "ExpressionStatement",
+          loc,
+          range,
+          expression: this.node(),
+        };
+      case 2:
+        return { type: "Identifier", loc, range, name: this.string() };
+      case 3: {
+        const kind = this.byte();
+        let value;
+        if (kind === 0) {
+          value = this.string();
+        } else if (kind === 1) {
+          value = this.float64();
+        } else {
+          throw new Error(`invalid value tag ${kind}`);
+        }
+        const raw = this.varint();
+        return {
+          type: "Literal",
+          loc,
+          range,
+          value,
+          raw: raw === 0 ? null : this.stringAt(raw - 1),
+        };
+      }
+      case 4: {
+        const flags = this.byte();
+        return {
+          type: "CallExpression",
+          loc,
+          range,
+          callee: this.node(),
+          arguments: this.list(() => this.node()),
+          optional: (flags & OPTIONAL) !== 0,
+        };
+      }
+      case 5:
+        return { type: "SpreadElement", loc, range, argument: this.node() };
+      case 6: {
+        const flags = this.byte();
+        return {
+          type: "MemberExpression",
+          loc,
+          range,
+          object: this.node(),
+          property: this.node(),
+          computed: (flags & COMPUTED) !== 0,
+          optional: (flags & OPTIONAL) !== 0,
+        };
+      }
+      default:
+        throw new Error(`invalid node tag ${tag}`);
+    }
+  }
+
+  tokenValue() {
+    const range = this.range();
+    return { loc: this.loc(range), range, value: this.string() };
+  }
+
+  list(item) {
+    const count = this.varint();
+    const result = [];
+    for (let i = 0; i < count; i++) {
+      result.push(item());
+    }
+    return result;
+  }
+
+  range() {
+    const start = this.varint();
+    return [start, start + this.varint()];
+  }
+
+  loc(range) {
+    return { start: this.position(range[0]), end: this.position(range[1]) };
+  }
+
+  // Binary search for the last line that starts at or before `offset`.
+  position(offset) {
+    let low = 0;
+    let high = this.lineStarts.length;
+    while (low < high) {
+      const mid = (low + high) >>> 1;
+      if (this.lineStarts[mid] <= offset) {
+        low = mid + 1;
+      } else {
+        high = mid;
+      }
+    }
+    const line = Math.max(low, 1);
+    return { line, column: offset - (this.lineStarts[line - 1] || 0) };
+  }
+
+  // Reads a byte and returns the name it stands for in `names`.
+  tag(kind, names) {
+    const tag = this.byte();
+    if (tag >= names.length) {
+      throw new Error(`invalid ${kind} tag ${tag}`);
+    }
+    return names[tag];
+  }
+
+  string() {
+    return this.stringAt(this.varint());
+  }
+
+  stringAt(index) {
+    if (index >= this.strings.length) {
+      throw new Error(`invalid string index ${index}`);
+    }
+    return this.strings[index];
+  }
+
+  byte() {
+    if (this.pos >= this.bytes.length) {
+      throw new Error("unexpected end of binary AST");
+    }
+    return this.bytes[this.pos++];
+  }
+
+  varint() {
+    let value = 0;
+    for (let shift = 0; shift < 35; shift += 7) {
+      const byte = this.byte();
+      value += (byte & 0x7f) * 2 ** shift;
+      if ((byte & 0x80) === 0) {
+        return value;
+      }
+    }
+    throw new Error("invalid varint");
+  }
+
+  float64() {
+    if (this.pos + 8 > this.bytes.length) {
+      throw new Error("unexpected end of binary AST");
+    }
+    const view = new DataView(
+      this.bytes.buffer,
+      this.bytes.byteOffset + this.pos,
+      8
+    );
+    this.pos += 8;
+    return view.getFloat64(0, true);
+  }
+
+  // Strict UTF-8 like Rust's `str::from_utf8`, so that both decoders reject
+  // the same strings.
+  utf8(length) {
+    const end = this.pos + length;
+    if (end > this.bytes.length) {
+      throw new Error("unexpected end of binary AST");
+    }
+    let result = "";
+    while (this.pos < end) {
+      const byte = this.bytes[this.pos++];
+      let codePoint;
+      let continuations;
+      let min;
+      if (byte < 0x80) {
+        result += String.fromCharCode(byte);
+        continue;
+      } else if (byte >= 0xc2 && byte < 0xe0) {
+        [codePoint, continuations, min] = [byte & 0x1f, 1, 0x80];
+      } else if (byte >= 0xe0 && byte < 0xf0) {
+        [codePoint, continuations, min] = [byte & 0x0f, 2, 0x800];
+      } else if (byte >= 0xf0 && byte < 0xf5) {
+        [codePoint, continuations, min] = [byte & 0x07, 3, 0x10000];
+      } else {
+        throw new Error("invalid UTF-8 in string table");
+      }
+      for (let i = 0; i < continuations; i++) {
+        const next = this.pos < end ? this.bytes[this.pos++] : 0;
+        if ((next & 0xc0) !== 0x80) {
+          throw new Error("invalid UTF-8 in string table");
+        }
+        codePoint = (codePoint << 6) | (next & 0x3f);
+      }
+      if (
+        codePoint < min ||
+        codePoint > 0x10ffff ||
+        (codePoint >= 0xd800 && codePoint < 0xe000)
+      ) {
+        throw new Error("invalid UTF-8 in string table");
+      }
+      result += String.fromCodePoint(codePoint);
+    }
+    return result;
+  }
+}
@@ -0,0 +1,154 @@
+// Rebuilds ESLint's `CodePath` and `CodePathSegment` objects from the data
+// produced by `code_path::analyze` in Rust and groups its events by the node
+// they're emitted for.
+
+const { collectNodes } = require("./scope_manager.js");
+
+class CodePathSegment {
+  constructor(data) {
+    this.id = data.id;
+    this.nextSegments = [];
+    this.prevSegments = [];
+    this.allNextSegments = [];
+    this.allPrevSegments = [];
+    this.reachable = data.reachable;
+  }
+}
+
+class CodePath {
+  constructor(data) {
+    this.id = data.id;
+    this.origin = data.origin;
+    this.upper = null;
+    this.childCodePaths = [];
+    this.initialSegment = null;
+    this.finalSegments = [];
+    this.returnedSegments = [];
+    this.thrownSegments = [];
+  }
+
+  // Same as ESLint's, calls `callback(segment, controller)` for each segment
+  // after all of its previous segments.
+  traverseSegments(options, callback) {
+    if (typeof options === "function") {
+      callback = options;
+      options = {};
+    }
+    const startSegment = options.first || this.initialSegment;
+    const lastSegment = options.last;
+    const visited = new Set();
+    const stack = [[startSegment, 0]];
+    let skippedSegment = null;
+    let broken = false;
+    const controller = {
+      skip() {
+        skippedSegment = stack.length <= 1 ? null : stack[stack.length - 2][0];
+      },
+      break() {
+        broken = true;
+      },
+    };
+
+    while (stack.length > 0) {
+      const item = stack[stack.length - 1];
+      const [segment, index] = item;
+
+      if (index === 0) {
+        if (visited.has(segment)) {
+          stack.pop();
+          continue;
+        }
+        if (
+          segment !== startSegment &&
+          segment.prevSegments.some((prev) => !visited.has(prev))
+        ) {
+          stack.pop();
+          continue;
+        }
+        if (skippedSegment && segment.prevSegments.includes(skippedSegment)) {
+          skippedSegment = null;
+        }
+        visited.add(segment);
+        if (!skippedSegment) {
+          callback.call(this, segment, controller);
+          if (segment === lastSegment) {
+            controller.skip();
+          }
+          if (broken) {
+            break;
+          }
+        }
+      }
+
+      if (index < segment.nextSegments.length) {
+        item[1] += 1;
+        stack.push([segment.nextSegments[index], 0]);
+      } else {
+        if (skippedSegment === segment) {
+          skippedSegment = null;
+        }
+        stack.pop();
+      }
+    }
+  }
+}
+
+const createCodePaths = (ast, data, visitorKeys) => {
+  const nodes = collectNodes(ast, visitorKeys);
+
+  const segments = data.segments.map((segment) => new CodePathSegment(segment));
+  const codePaths = data.codePaths.map((codePath) => new CodePath(codePath));
+
+  data.segments.forEach((s, i) => {
+    const segment = segments[i];
+    segment.nextSegments = s.nextSegments.map((id) => segments[id]);
+    segment.prevSegments = s.prevSegments.map((id) => segments[id]);
+    // Unreachable segments are only left out of `nextSegments` and
+    // `prevSegments` by ESLint.
+    segment.allNextSegments = segment.nextSegments;
+    segment.allPrevSegments = segment.prevSegments;
+    segment.nextSegments = segment.nextSegments.filter((next) => next.reachable);
+    segment.prevSegments = segment.prevSegments.filter((prev) => prev.reachable);
+  });
+
+  data.codePaths.forEach((c, i) => {
+    const codePath = codePaths[i];
+    codePath.upper = c.upper === null ? null : codePaths[c.upper];
+    codePath.childCodePaths = c.childCodePaths.map((id) => codePaths[id]);
+    codePath.initialSegment = segments[c.initialSegment];
+    codePath.finalSegments = c.finalSegments.map((id) => segments[id]);
+    codePath.returnedSegments = c.returnedSegments.map((id) => segments[id]);
+    codePath.thrownSegments = c.thrownSegments.map((id) => segments[id]);
+  });
+
+  // node -> { enter, leave }, each a list of [handler name, ...arguments].
+  const events = new Map();
+  for (const event of data.events) {
+    const node = nodes[event.node];
+    if (!events.has(node)) {
+      events.set(node, { enter: [], leave: [] });
+    }
+    const { kind } = event;
+    let args;
+    switch (kind.type) {
+      case "onCodePathStart":
+      case "onCodePathEnd":
+        args = [codePaths[kind.codePath], node];
+        break;
+      case "onCodePathSegmentLoop":
+        args = [segments[kind.fromSegment], segments[kind.toSegment], node];
+        break;
+      default:
+        args = [segments[kind.segment], node];
+    }
+    events.get(node)[event.phase].push([kind.type, ...args]);
+  }
+
+  return {
+    codePaths,
+    enter: (node) => events.get(node)?.enter || [],
+    leave: (node) => events.get(node)?.leave || [],
+  };
+};
+
+module.exports = { createCodePaths };
@@ -1,14 +1,13 @@
-const traverse = require("@babel/traverse").default;
 const { SourceCode } = require("eslint");
 const evk = require("eslint-visitor-keys");
 const eslintScope = require("eslint-scope");
+const { createScopeManager } = require("./scope_manager.js");
+const { createCodePaths } = require("./code_path.js");
+const { traverse } = require("./traverse.js");
 
 const noDebugger = require("../node_modules/eslint/lib/rules/no-debugger.js");
 const noConsole = require("../node_modules/eslint/lib/rules/no-console.js");
 
-const ast = require("./new_ast.js");
-const input = `console.log("hello, ");\ndebugger;\nconsole.log("world!");`;
-
 const rules = [noDebugger, noConsole];
 
 const DEFAULT_ECMA_VERSION = 5;
@@ -29,61 +28,90 @@ const analyzeScope = (ast, languageOptions, visitorKeys) => {
   });
 };
 
-const runRules = (ast, rules) => {
+// Returns the problems that `rules` report in `ast`, which was parsed from
+// `text`.  The Rust host passes the data from `visitor_keys::to_v8`,
+// `scope::to_v8` and `code_path::to_v8`, otherwise eslint-scope analyzes
+// `ast` and code paths aren't tracked.
+const runRules = (ast, rules, { text, visitorKeys, scopeData, codePathData }) => {
+  const scopeManager = scopeData
+    ? createScopeManager(ast, scopeData, visitorKeys)
+    : analyzeScope(
+        ast,
+        {
+          ecmaVersion: 13, // ES2022
+          parserOptions: { ecmaFeatures: { jsx: true } },
+        },
+        visitorKeys
+      );
   const config = {
-    text: input,
+    text,
     ast,
     undefined, // ParserServices | undefined,
-    scopeManager: analyzeScope(ast, {
-      ecmaVersion: 13, // ES2022
-      parserOptions: { ecmaFeatures: { jsx: true } },
-    }),
+    scopeManager,
   };
   const sourceCode = new SourceCode(config);
 
+  const problems = [];
   const visitors = rules.map((rule) => {
     return rule.create({
       report: ({ node, messageId }) => {
         const message = rule.meta.messages[messageId];
         const start = `${node.loc.start.line}:${node.loc.start.column}`;
         const end = `${node.loc.end.line}:${node.loc.end.column}`;
-        console.log(`${start} to ${end} - ${message}`);
+        problems.push(`${start} to ${end} - ${message}`);
       },
       options: {}, // TODO: populate with options from .eslintrc.js
       sourceCode,
     });
   });
 
-  traverse(
-    // NOTE: this wrapper is required in order for traverse() to visit
-    // the 'Program' node at the root of `ast`.
-    {
-      type: "File",
-      program: ast,
-    },
-    {
-      enter(path) {
-        const { node } = path;
-        node.parent = path.parentPath?.node;
-        for (const visitor of visitors) {
-          if (node.type in visitor) {
-            return visitor[node.type](node);
-          } else if (`${node.type}:enter` in visitor) {
-            return visitor[`${node.type}:enter`](node);
-          }
-        }
-      },
-      exit(path) {
-        const { node } = path;
-        node.parent = path.parentPath?.node;
-        for (const visitor of visitors) {
-          if (`${node.type}:exit` in visitor) {
-            return visitor[`${node.type}:exit`](node);
-          }
+  const codePaths = codePathData
+    ? createCodePaths(ast, codePathData, visitorKeys)
+    : { enter: () => [], leave: () => [] };
+  const emit = (events) => {
+    for (const [name, ...args] of events) {
+      for (const visitor of visitors) {
+        if (name in visitor) {
+          visitor[name](...args);
         }
-      },
+      }
     }
-  );
+  };
+
+  // Follows the same keys as scope analysis and code paths so that rules see
+  // the nodes in the order they were numbered in.
+  traverse(ast, visitorKeys || evk.KEYS, {
+    enter(node, parent) {
+      node.parent = parent ?? undefined;
+      emit(codePaths.enter(node));
+      for (const visitor of visitors) {
+        if (node.type in visitor) {
+          visitor[node.type](node);
+        } else if (`${node.type}:enter` in visitor) {
+          visitor[`${node.type}:enter`](node);
+        }
+      }
+    },
+    leave(node) {
+      for (const visitor of visitors) {
+        if (`${node.type}:exit` in visitor) {
+          visitor[`${node.type}:exit`](node);
+        }
+      }
+      emit(codePaths.leave(node));
+    },
+  });
+
+  return problems;
 };
 
-runRules(ast, rules);
+// Called from `main.rs` with `JsModule::call_export`.
+exports.lint = (ast, options) => runRules(ast, rules, options);
+
+if (require.main === module) {
+  const ast = require("./new_ast.js");
+  const text = `console.log("hello, ");\ndebugger;\nconsole.log("world!");`;
+  for (const problem of runRules(ast, rules, { text })) {
+    console.log(problem);
+  }
+}
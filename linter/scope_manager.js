@@ -0,0 +1,242 @@
+// Rebuilds eslint-scope's `ScopeManager` from the data produced by
+// `scope::analyze` in Rust.  Nodes are referred to by their index in a
+// pre-order traversal of the AST that follows `visitorKeys`.
+
+const { traverse } = require("./traverse.js");
+
+const READ = 1;
+const WRITE = 2;
+
+class Reference {
+  constructor(data, nodes) {
+    this.identifier = nodes[data.identifier];
+    this.from = null;
+    this.resolved = null;
+    this.flag = data.flag;
+    if (data.writeExpr !== null) {
+      this.writeExpr = nodes[data.writeExpr];
+    }
+    this.partial = data.partial;
+    this.init = data.init;
+    this.__maybeImplicitGlobal = null;
+  }
+
+  isStatic() {
+    return this.resolved !== null && this.resolved.scope.type !== "global";
+  }
+
+  isWrite() {
+    return !!(this.flag & WRITE);
+  }
+
+  isRead() {
+    return !!(this.flag & READ);
+  }
+
+  isReadOnly() {
+    return this.flag === READ;
+  }
+
+  isWriteOnly() {
+    return this.flag === WRITE;
+  }
+
+  isReadWrite() {
+    return this.flag === (READ | WRITE);
+  }
+}
+
+class Variable {
+  constructor(data, nodes) {
+    this.name = data.name;
+    this.identifiers = data.identifiers.map((id) => nodes[id]);
+    this.references = [];
+    this.defs = data.defs.map((def) => ({
+      type: def.type,
+      name: nodes[def.name],
+      node: nodes[def.node],
+      parent: def.parent === null ? null : nodes[def.parent],
+    }));
+    this.tainted = false;
+    this.stack = true;
+    this.scope = null;
+  }
+}
+
+class Scope {
+  constructor(data, nodes) {
+    this.type = data.type;
+    this.isStrict = data.isStrict;
+    this.upper = null;
+    this.childScopes = [];
+    this.variableScope = null;
+    this.functionExpressionScope = data.functionExpressionScope;
+    this.block = nodes[data.block];
+    this.variables = [];
+    this.set = new Map();
+    this.references = [];
+    this.through = [];
+    this.dynamic = this.type === "global" || this.type === "with";
+    this.thisFound = false;
+  }
+
+  resolve(ident) {
+    return (
+      this.references.find((reference) => reference.identifier === ident)
+        ?.resolved ?? null
+    );
+  }
+
+  isStatic() {
+    return !this.dynamic;
+  }
+
+  isArgumentsMaterialized() {
+    return true;
+  }
+
+  isThisMaterialized() {
+    return true;
+  }
+
+  isUsedName(name) {
+    return (
+      this.set.has(name) ||
+      this.through.some((reference) => reference.identifier.name === name)
+    );
+  }
+}
+
+class ScopeManager {
+  constructor(scopes, declaredVariables, sourceType, impliedStrict) {
+    this.scopes = scopes;
+    this.globalScope = scopes[0];
+    this.__declaredVariables = declaredVariables;
+    this.__sourceType = sourceType;
+    this.__impliedStrict = impliedStrict;
+  }
+
+  isGlobalReturn() {
+    return false;
+  }
+
+  isModule() {
+    return this.__sourceType === "module";
+  }
+
+  isImpliedStrict() {
+    return this.__impliedStrict;
+  }
+
+  isStrictModeSupported() {
+    return true;
+  }
+
+  getDeclaredVariables(node) {
+    return this.__declaredVariables.get(node) || [];
+  }
+
+  // Same as eslint-scope: the innermost scope when `inner` is set, otherwise
+  // the outermost one, skipping function expression name scopes.
+  acquire(node, inner) {
+    const scopes = this.scopes.filter((scope) => scope.block === node);
+    if (scopes.length === 0) {
+      return null;
+    }
+    if (scopes.length === 1) {
+      return scopes[0];
+    }
+    if (inner) {
+      for (let i = scopes.length - 1; i >= 0; i--) {
+        if (!scopes[i].functionExpressionScope) {
+          return scopes[i];
+        }
+      }
+    } else {
+      for (const scope of scopes) {
+        if (!scope.functionExpressionScope) {
+          return scope;
+        }
+      }
+    }
+    return null;
+  }
+
+  acquireAll(node) {
+    const scopes = this.scopes.filter((scope) => scope.block === node);
+    return scopes.length === 0 ? null : scopes;
+  }
+
+  release(node, inner) {
+    const scopes = this.acquireAll(node);
+    if (scopes && scopes[0].upper) {
+      return this.acquire(scopes[0].upper.block, inner);
+    }
+    return null;
+  }
+}
+
+const collectNodes = (ast, visitorKeys) => {
+  const nodes = [];
+  traverse(ast, visitorKeys, { enter: (node) => nodes.push(node) });
+  return nodes;
+};
+
+const createScopeManager = (ast, data, visitorKeys, options = {}) => {
+  const nodes = collectNodes(ast, visitorKeys);
+
+  const references = data.references.map((ref) => new Reference(ref, nodes));
+  const variables = data.variables.map((v) => new Variable(v, nodes));
+  const scopes = data.scopes.map((scope) => new Scope(scope, nodes));
+
+  data.references.forEach((ref, i) => {
+    references[i].from = scopes[ref.from];
+    if (ref.resolved !== null) {
+      references[i].resolved = variables[ref.resolved];
+    }
+  });
+
+  const declaredVariables = new WeakMap();
+  data.variables.forEach((v, i) => {
+    const variable = variables[i];
+    variable.scope = scopes[v.scope];
+    variable.references = v.references.map((id) => references[id]);
+    for (const def of variable.defs) {
+      const declared = declaredVariables.get(def.node) || [];
+      if (!declared.includes(variable)) {
+        declared.push(variable);
+      }
+      declaredVariables.set(def.node, declared);
+    }
+  });
+
+  data.scopes.forEach((s, i) => {
+    const scope = scopes[i];
+    scope.upper = s.upper === null ? null : scopes[s.upper];
+    scope.childScopes = s.childScopes.map((id) => scopes[id]);
+    scope.variableScope = scopes[s.variableScope];
+    scope.variables = s.variables.map((id) => variables[id]);
+    for (const variable of scope.variables) {
+      scope.set.set(variable.name, variable);
+    }
+    scope.references = s.references.map((id) => references[id]);
+    scope.through = s.through.map((id) => references[id]);
+    if (s.implicit !== null) {
+      const implicitVariables = s.implicit.variables.map((id) => variables[id]);
+      scope.implicit = {
+        set: new Map(implicitVariables.map((v) => [v.name, v])),
+        variables: implicitVariables,
+        left: s.implicit.left.map((id) => references[id]),
+      };
+    }
+  });
+
+  return new ScopeManager(
+    scopes,
+    declaredVariables,
+    options.sourceType || "script",
+    !!options.impliedStrict
+  );
+};
+
+module.exports = { collectNodes, createScopeManager };
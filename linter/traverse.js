@@ -0,0 +1,26 @@
+// Walks the AST in the pre-order that follows `visitorKeys`, like `traverse`
+// in Rust, so that the nth node entered here is the node with id n in the
+// data from `scope::to_v8` and `code_path::to_v8`.  `enter` and `leave` are
+// called with each node and its parent.
+
+const traverse = (ast, visitorKeys, { enter = () => {}, leave = () => {} }) => {
+  const visit = (node, parent) => {
+    enter(node, parent);
+    for (const key of visitorKeys[node.type] || []) {
+      const child = node[key];
+      if (Array.isArray(child)) {
+        for (const item of child) {
+          if (item) {
+            visit(item, node);
+          }
+        }
+      } else if (child) {
+        visit(child, node);
+      }
+    }
+    leave(node, parent);
+  };
+  visit(ast, null);
+};
+
+module.exports = { traverse };
@@ -0,0 +1,444 @@
+// Alternative to the types in `ast` that stores all of a program's nodes in a
+// single `Vec` and refers to them by index.  Names, string values and the text
+// of tokens and comments are `JsWord`s, which are interned and shared with
+// swc's AST, and type tags come from `ast::Node::TYPE` instead of being
+// allocated for every node.
+//
+// `Program` serializes to exactly the same ESTree as `ast::Program`.
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use swc_atoms::JsWord;
+use swc_common::Spanned;
+use swc_ecma_ast as swc;
+
+use crate::ast::{self, Node as _};
+use crate::parser::{self, SourceIndex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+// A range of `Arena::lists`, used for nodes with a variable number of children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeList {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub loc: ast::Location,
+    pub range: ast::Range,
+    pub source_type: ast::SourceType,
+    pub body: NodeList,
+    pub tokens: Vec<Token>,
+    pub comments: Vec<Comment>,
+    pub arena: Arena,
+}
+
+#[derive(Debug)]
+pub struct Token {
+    pub kind: ast::TokenKind,
+    pub loc: ast::Location,
+    pub range: ast::Range,
+    pub value: JsWord,
+}
+
+#[derive(Debug)]
+pub struct Comment {
+    pub kind: ast::CommentKind,
+    pub loc: ast::Location,
+    pub range: ast::Range,
+    pub value: JsWord,
+}
+
+#[derive(Debug, Default)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    lists: Vec<NodeId>,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub loc: ast::Location,
+    pub range: ast::Range,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug)]
+pub enum NodeKind {
+    DebuggerStatement,
+    ExpressionStatement {
+        expression: NodeId,
+    },
+    Identifier {
+        name: JsWord,
+    },
+    Literal {
+        value: Value,
+        raw: Option<JsWord>,
+    },
+    CallExpression {
+        callee: NodeId,
+        arguments: NodeList,
+        optional: bool,
+    },
+    SpreadElement {
+        argument: NodeId,
+    },
+    MemberExpression {
+        object: NodeId,
+        property: NodeId,
+        computed: bool,
+        optional: bool,
+    },
+}
+
+#[derive(Debug)]
+pub enum Value {
+    // The parser rejects numeric literals so far.
+    String(JsWord),
+}
+
+impl NodeKind {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NodeKind::DebuggerStatement => ast::DebuggerStatement::TYPE,
+            NodeKind::ExpressionStatement { .. } => ast::ExpressionStatement::TYPE,
+            NodeKind::Identifier { .. } => ast::Identifier::TYPE,
+            NodeKind::Literal { .. } => ast::Literal::TYPE,
+            NodeKind::CallExpression { .. } => ast::CallExpression::TYPE,
+            NodeKind::SpreadElement { .. } => ast::SpreadElement::TYPE,
+            NodeKind::MemberExpression { .. } => ast::MemberExpression::TYPE,
+        }
+    }
+}
+
+impl Arena {
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+
+    pub fn list(&self, list: NodeList) -> &[NodeId] {
+        &self.lists[list.start as usize..(list.start + list.len) as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn alloc(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    fn alloc_list(&mut self, ids: &[NodeId]) -> NodeList {
+        let start = self.lists.len() as u32;
+        self.lists.extend_from_slice(ids);
+        NodeList {
+            start,
+            len: ids.len() as u32,
+        }
+    }
+}
+
+pub fn parse(code: String) -> Result<Program, parser::Error> {
+    parser::parse_with(code, |module, tokens, comments, index| {
+        let tokens = parser::convert_tokens(tokens, index, |kind, value, span| Token {
+            kind,
+            loc: index.loc(span),
+            range: index.range(span),
+            value,
+        })?;
+        let comments = parser::convert_comments(comments, |kind, value, span| Comment {
+            kind,
+            loc: index.loc(span),
+            range: index.range(span),
+            value,
+        });
+        let mut builder = Builder {
+            arena: Arena::default(),
+            index,
+        };
+        let body = module
+            .body
+            .iter()
+            .map(|item| builder.module_item(item))
+            .collect::<Result<Vec<_>, _>>()?;
+        let body = builder.arena.alloc_list(&body);
+
+        Ok(Program {
+            loc: index.loc(&module.span),
+            range: index.range(&module.span),
+            source_type: ast::SourceType::Module,
+            body,
+            tokens,
+            comments,
+            arena: builder.arena,
+        })
+    })
+}
+
+// Mirrors the `convert_*` functions in `parser`.
+struct Builder<'a, 'b> {
+    arena: Arena,
+    index: &'a SourceIndex<'b>,
+}
+
+impl<'a, 'b> Builder<'a, 'b> {
+    fn alloc(&mut self, span: &swc_common::Span, kind: NodeKind) -> NodeId {
+        self.arena.alloc(Node {
+            loc: self.index.loc(span),
+            range: self.index.range(span),
+            kind,
+        })
+    }
+
+    fn module_item(&mut self, item: &swc::ModuleItem) -> Result<NodeId, parser::Error> {
+        match item {
+            swc::ModuleItem::ModuleDecl(_) => {
+                Err(self.index.unsupported("import or export declaration", item))
+            }
+            swc::ModuleItem::Stmt(stmt) => self.statement(stmt),
+        }
+    }
+
+    fn statement(&mut self, stmt: &swc::Stmt) -> Result<NodeId, parser::Error> {
+        Ok(match stmt {
+            swc::Stmt::Debugger(swc::DebuggerStmt { span }) => {
+                self.alloc(span, NodeKind::DebuggerStatement)
+            }
+            swc::Stmt::Expr(swc::ExprStmt { span, expr }) => {
+                let expression = self.expression(expr)?;
+                self.alloc(span, NodeKind::ExpressionStatement { expression })
+            }
+            _ => return Err(parser::unsupported_statement(stmt, self.index)),
+        })
+    }
+
+    fn expression(&mut self, expr: &swc::Expr) -> Result<NodeId, parser::Error> {
+        Ok(match expr {
+            swc::Expr::Member(swc::MemberExpr { span, obj, prop }) => {
+                let object = self.expression(obj)?;
+                let property = self.identifier(parser::member_prop_ident(prop, self.index)?);
+                self.alloc(
+                    span,
+                    NodeKind::MemberExpression {
+                        object,
+                        property,
+                        computed: false,
+                        optional: false,
+                    },
+                )
+            }
+            swc::Expr::Call(swc::CallExpr {
+                span,
+                callee: swc::Callee::Expr(callee),
+                args,
+                type_args: _,
+            }) => {
+                let callee = self.expression(callee)?;
+                let arguments = args
+                    .iter()
+                    .map(|arg| match arg {
+                        swc::ExprOrSpread { spread: None, expr } => self.expression(expr),
+                        swc::ExprOrSpread {
+                            spread: Some(spread),
+                            expr,
+                        } => {
+                            let argument = self.expression(expr)?;
+                            // swc's span only covers the `...`.
+                            let span = spread.with_hi(expr.span().hi);
+                            Ok(self.alloc(&span, NodeKind::SpreadElement { argument }))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let arguments = self.arena.alloc_list(&arguments);
+                self.alloc(
+                    span,
+                    NodeKind::CallExpression {
+                        callee,
+                        arguments,
+                        optional: false,
+                    },
+                )
+            }
+            swc::Expr::Ident(ident) => self.identifier(ident),
+            swc::Expr::Lit(swc::Lit::Str(swc::Str { span, value, raw })) => self.alloc(
+                span,
+                NodeKind::Literal {
+                    value: Value::String(value.clone()),
+                    raw: raw.as_deref().map(JsWord::from),
+                },
+            ),
+            _ => return Err(parser::unsupported_expression(expr, self.index)),
+        })
+    }
+
+    fn identifier(&mut self, ident: &swc::Ident) -> NodeId {
+        self.alloc(
+            &ident.span,
+            NodeKind::Identifier {
+                name: ident.sym.clone(),
+            },
+        )
+    }
+}
+
+impl Serialize for Program {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", ast::Program::TYPE)?;
+        map.serialize_entry("loc", &self.loc)?;
+        map.serialize_entry("range", &self.range)?;
+        map.serialize_entry("sourceType", &self.source_type)?;
+        map.serialize_entry("body", &self.nodes(self.body))?;
+        map.serialize_entry("tokens", &self.tokens)?;
+        map.serialize_entry("comments", &self.comments)?;
+        map.end()
+    }
+}
+
+impl Serialize for Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("type", self.kind.type_name())?;
+        map.serialize_entry("loc", &self.loc)?;
+        map.serialize_entry("range", &self.range)?;
+        map.serialize_entry("value", &*self.value)?;
+        map.end()
+    }
+}
+
+impl Serialize for Comment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("type", self.kind.type_name())?;
+        map.serialize_entry("loc", &self.loc)?;
+        map.serialize_entry("range", &self.range)?;
+        map.serialize_entry("value", &*self.value)?;
+        map.end()
+    }
+}
+
+impl Program {
+    fn node(&self, id: NodeId) -> NodeRef<'_> {
+        NodeRef {
+            arena: &self.arena,
+            id,
+        }
+    }
+
+    fn nodes(&self, list: NodeList) -> NodeListRef<'_> {
+        NodeListRef {
+            arena: &self.arena,
+            list,
+        }
+    }
+
+    pub fn root_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.arena
+            .list(self.body)
+            .iter()
+            .map(|id| self.node(*id).get())
+    }
+}
+
+// Helpers for serializing nodes along with the arena that they're in.
+#[derive(Clone, Copy)]
+struct NodeRef<'a> {
+    arena: &'a Arena,
+    id: NodeId,
+}
+
+#[derive(Clone, Copy)]
+struct NodeListRef<'a> {
+    arena: &'a Arena,
+    list: NodeList,
+}
+
+impl<'a> NodeRef<'a> {
+    fn get(self) -> &'a Node {
+        self.arena.node(self.id)
+    }
+
+    fn child(self, id: NodeId) -> NodeRef<'a> {
+        NodeRef {
+            arena: self.arena,
+            id,
+        }
+    }
+}
+
+impl Serialize for NodeRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.get();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", node.kind.type_name())?;
+        map.serialize_entry("loc", &node.loc)?;
+        map.serialize_entry("range", &node.range)?;
+
+        // Same fields, in the same order, as the structs in `ast`.
+        match &node.kind {
+            NodeKind::DebuggerStatement => {}
+            NodeKind::ExpressionStatement { expression } => {
+                map.serialize_entry("expression", &self.child(*expression))?;
+            }
+            NodeKind::Identifier { name } => {
+                map.serialize_entry("name", &**name)?;
+            }
+            NodeKind::Literal { value, raw } => {
+                match value {
+                    Value::String(value) => map.serialize_entry("value", &**value)?,
+                }
+                map.serialize_entry("raw", &raw.as_deref())?;
+            }
+            NodeKind::CallExpression {
+                callee,
+                arguments,
+                optional,
+            } => {
+                map.serialize_entry("callee", &self.child(*callee))?;
+                let arguments = NodeListRef {
+                    arena: self.arena,
+                    list: *arguments,
+                };
+                map.serialize_entry("arguments", &arguments)?;
+                map.serialize_entry("optional", optional)?;
+            }
+            NodeKind::SpreadElement { argument } => {
+                map.serialize_entry("argument", &self.child(*argument))?;
+            }
+            NodeKind::MemberExpression {
+                object,
+                property,
+                computed,
+                optional,
+            } => {
+                map.serialize_entry("object", &self.child(*object))?;
+                map.serialize_entry("property", &self.child(*property))?;
+                map.serialize_entry("computed", computed)?;
+                map.serialize_entry("optional", optional)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Serialize for NodeListRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ids = self.arena.list(self.list);
+        let mut seq = serializer.serialize_seq(Some(ids.len()))?;
+        for id in ids {
+            seq.serialize_element(&NodeRef {
+                arena: self.arena,
+                id: *id,
+            })?;
+        }
+        seq.end()
+    }
+}
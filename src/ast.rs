@@ -1,12 +1,16 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Position {
     pub line: u32,
     pub column: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Location {
     pub start: Position,
     pub end: Position,
@@ -14,40 +18,57 @@ pub struct Location {
 
 pub type Range = (u32, u32);
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Comment {}
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(tag = "type")]
+pub enum Comment {
+    Line(TokenValue),
+    Block(TokenValue),
+}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Program {
+    #[ts(type = "\"Program\"")]
+    #[schemars(schema_with = "type_tag::<Program>")]
     pub r#type: String,
     pub loc: Location,
     pub range: Range,
+    #[serde(rename = "sourceType")]
+    pub source_type: SourceType,
     pub body: Vec<Statement>,
+    #[serde(default)]
     pub tokens: Vec<Token>,
+    #[serde(default)]
     pub comments: Vec<Comment>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceType {
+    Script,
+    Module,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(tag = "type")]
 pub enum Statement {
     DebuggerStatement(DebuggerStatement),
     ExpressionStatement(ExpressionStatement),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct DebuggerStatement {
     pub loc: Location,
     pub range: Range,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct ExpressionStatement {
     pub loc: Location,
     pub range: Range,
     pub expression: Box<Expression>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(tag = "type")]
 pub enum Expression {
     Literal(Literal),
@@ -56,14 +77,14 @@ pub enum Expression {
     MemberExpression(MemberExpression),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Identifier {
     pub loc: Location,
     pub range: Range,
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Literal {
     pub loc: Location,
     pub range: Range,
@@ -71,45 +92,50 @@ pub struct Literal {
     pub raw: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(untagged)]
 pub enum Value {
     String(String),
     Number(f64),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct CallExpression {
     pub loc: Location,
     pub range: Range,
     pub callee: Box<Expression>,
     pub arguments: Vec<ExprOrSpread>,
+    pub optional: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(untagged)]
 pub enum ExprOrSpread {
     Expr(Expression),
     Spread(SpreadElement),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct SpreadElement {
+    #[ts(type = "\"SpreadElement\"")]
+    #[schemars(schema_with = "type_tag::<SpreadElement>")]
+    pub r#type: String,
     pub loc: Location,
     pub range: Range,
     pub argument: Box<Expression>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct MemberExpression {
     pub loc: Location,
     pub range: Range,
     pub object: Box<Expression>,
     pub property: Box<Prop>,
     pub computed: bool,
+    pub optional: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(tag = "type")]
 pub enum Prop {
     Identifier(Identifier),
@@ -118,7 +144,7 @@ pub enum Prop {
     // Computed(ComputedPropName),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(tag = "type")]
 pub enum Token {
     Identifier(TokenValue),
@@ -127,9 +153,132 @@ pub enum Token {
     Punctuator(TokenValue),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct TokenValue {
     pub loc: Location,
     pub range: Range,
     pub value: String,
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier,
+    Keyword,
+    String,
+    Punctuator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentKind {
+    Line,
+    Block,
+}
+
+impl TokenKind {
+    pub fn type_name(self) -> &'static str {
+        match self {
+            TokenKind::Identifier => "Identifier",
+            TokenKind::Keyword => "Keyword",
+            TokenKind::String => "String",
+            TokenKind::Punctuator => "Punctuator",
+        }
+    }
+}
+
+impl CommentKind {
+    pub fn type_name(self) -> &'static str {
+        match self {
+            CommentKind::Line => "Line",
+            CommentKind::Block => "Block",
+        }
+    }
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, value: TokenValue) -> Self {
+        match kind {
+            TokenKind::Identifier => Token::Identifier(value),
+            TokenKind::Keyword => Token::Keyword(value),
+            TokenKind::String => Token::String(value),
+            TokenKind::Punctuator => Token::Punctuator(value),
+        }
+    }
+
+    pub fn value(&self) -> &TokenValue {
+        match self {
+            Token::Identifier(value)
+            | Token::Keyword(value)
+            | Token::String(value)
+            | Token::Punctuator(value) => value,
+        }
+    }
+
+    pub fn value_mut(&mut self) -> &mut TokenValue {
+        match self {
+            Token::Identifier(value)
+            | Token::Keyword(value)
+            | Token::String(value)
+            | Token::Punctuator(value) => value,
+        }
+    }
+}
+
+impl Comment {
+    pub fn new(kind: CommentKind, value: TokenValue) -> Self {
+        match kind {
+            CommentKind::Line => Comment::Line(value),
+            CommentKind::Block => Comment::Block(value),
+        }
+    }
+
+    pub fn value(&self) -> &TokenValue {
+        match self {
+            Comment::Line(value) | Comment::Block(value) => value,
+        }
+    }
+
+    pub fn value_mut(&mut self) -> &mut TokenValue {
+        match self {
+            Comment::Line(value) | Comment::Block(value) => value,
+        }
+    }
+}
+
+pub trait Node {
+    const TYPE: &'static str;
+    // Fields that contain child nodes, in the order they should be visited.
+    const VISITOR_KEYS: &'static [&'static str];
+}
+
+macro_rules! impl_node {
+    ($($node:ident => [$($key:literal),*],)*) => {
+        $(
+            impl Node for $node {
+                const TYPE: &'static str = stringify!($node);
+                const VISITOR_KEYS: &'static [&'static str] = &[$($key),*];
+            }
+        )*
+    };
+}
+
+impl_node! {
+    Program => ["body"],
+    DebuggerStatement => [],
+    ExpressionStatement => ["expression"],
+    Identifier => [],
+    Literal => [],
+    CallExpression => ["callee", "arguments"],
+    SpreadElement => ["argument"],
+    MemberExpression => ["object", "property"],
+}
+
+// The schema of a `type` field that's a `String` in Rust, written the same way
+// as the tags of the enums above.
+fn type_tag<T: Node>(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(vec![T::TYPE.into()]),
+        ..Default::default()
+    }
+    .into()
+}
@@ -0,0 +1,59 @@
+// Usage: ast-server [--addr <host:port> | --unix <path>] [--cache <dir>]
+//
+// See `server` for the API.
+
+use std::env;
+use std::error;
+use std::net::TcpListener;
+
+use ast_store::server::Server;
+use ast_store::store::Store;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:7777";
+const DEFAULT_CACHE: &str = ".ast-store";
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut unix = None;
+    let mut cache = DEFAULT_CACHE.to_string();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--addr" => addr = value()?,
+            "--unix" => unix = Some(value()?),
+            "--cache" => cache = value()?,
+            _ => return Err(format!("unknown argument {arg}").into()),
+        }
+    }
+
+    let server = Server::new(Store::new(cache));
+
+    match unix {
+        #[cfg(unix)]
+        Some(path) => {
+            use std::os::unix::fs::FileTypeExt;
+
+            // Left behind if a previous server didn't shut down cleanly, but
+            // anything else at the path is left alone.
+            match std::fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(&path)?,
+                Ok(_) => return Err(format!("{path} exists and isn't a socket").into()),
+                Err(_) => {}
+            }
+            let listener = std::os::unix::net::UnixListener::bind(&path)?;
+            eprintln!("listening on {path}");
+            server.serve_unix(listener)?;
+        }
+        #[cfg(not(unix))]
+        Some(_) => return Err("unix sockets aren't supported on this platform".into()),
+        None => {
+            let listener = TcpListener::bind(&addr)?;
+            eprintln!("listening on http://{}", listener.local_addr()?);
+            server.serve_tcp(listener)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use std::error;
+use std::fs;
+use std::path::Path;
+
+use ast_store::schema;
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("types");
+    fs::create_dir_all(&dir)?;
+
+    fs::write(dir.join("ast.schema.json"), schema::json_schema())?;
+    fs::write(dir.join("ast.d.ts"), schema::typescript_declarations())?;
+
+    Ok(())
+}
@@ -0,0 +1,603 @@
+// Compact binary encoding of `ast::Program`, see `js/decode_ast.js` for a
+// decoder that runs inside of V8.
+//
+// Unless noted otherwise integers are unsigned LEB128 varints.
+//
+//   header    "ESTB", version
+//   strings   count, (byte length, UTF-8 bytes)*
+//   lines     count, (start - previous start)*
+//   program   source type (u8), range, statement count, node*
+//   tokens    count, (kind (u8), range, string)*
+//   comments  count, (kind (u8), range, string)*
+//
+// Nodes start with a `tag::*` byte followed by their range and fields, child
+// nodes are written in `VISITOR_KEYS` order.  Ranges are written as start and
+// length.  Strings are written as indexes into the string table.  `loc`s
+// aren't stored, they're computed from ranges using the line table.
+
+use std::collections::HashMap;
+use std::{error, fmt};
+
+use crate::ast;
+
+pub const MAGIC: &[u8; 4] = b"ESTB";
+pub const VERSION: u32 = 1;
+
+mod tag {
+    pub const DEBUGGER_STATEMENT: u8 = 0;
+    pub const EXPRESSION_STATEMENT: u8 = 1;
+    pub const IDENTIFIER: u8 = 2;
+    pub const LITERAL: u8 = 3;
+    pub const CALL_EXPRESSION: u8 = 4;
+    pub const SPREAD_ELEMENT: u8 = 5;
+    pub const MEMBER_EXPRESSION: u8 = 6;
+}
+
+// Bits of the flags byte of `CallExpression` and `MemberExpression`.
+const OPTIONAL: u8 = 1;
+const COMPUTED: u8 = 2;
+
+#[derive(Debug)]
+pub enum Error {
+    // The data doesn't start with `MAGIC`.
+    NotBinaryAst,
+    UnsupportedVersion(u32),
+    UnexpectedEnd,
+    InvalidTag { kind: &'static str, tag: u8 },
+    InvalidVarint,
+    InvalidString(usize),
+    InvalidUtf8,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotBinaryAst => write!(f, "not a binary AST"),
+            Error::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported binary AST version {version}, expected {VERSION}"
+                )
+            }
+            Error::UnexpectedEnd => write!(f, "unexpected end of binary AST"),
+            Error::InvalidTag { kind, tag } => write!(f, "invalid {kind} tag {tag}"),
+            Error::InvalidVarint => write!(f, "invalid varint"),
+            Error::InvalidString(index) => write!(f, "invalid string index {index}"),
+            Error::InvalidUtf8 => write!(f, "invalid UTF-8 in string table"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+// `loc`s are recomputed from ranges when decoding, so they only round-trip if
+// they agree with the ranges, which is always the case for parsed programs.
+pub fn encode(program: &ast::Program) -> Vec<u8> {
+    let mut body = Encoder::default();
+    body.program(program);
+
+    let mut out = Vec::with_capacity(body.out.len() + 64);
+    out.extend_from_slice(MAGIC);
+    write_varint(&mut out, VERSION);
+
+    write_varint(&mut out, body.strings.len() as u32);
+    for string in &body.strings {
+        write_varint(&mut out, string.len() as u32);
+        out.extend_from_slice(string.as_bytes());
+    }
+
+    let line_starts = body.line_starts();
+    write_varint(&mut out, line_starts.len() as u32);
+    let mut previous = 0;
+    for start in line_starts {
+        write_varint(&mut out, start.saturating_sub(previous));
+        previous = start;
+    }
+
+    out.extend_from_slice(&body.out);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<ast::Program, Error> {
+    let mut decoder = Decoder {
+        bytes,
+        pos: 0,
+        strings: vec![],
+        line_starts: vec![],
+    };
+
+    if decoder.take(MAGIC.len())? != MAGIC {
+        return Err(Error::NotBinaryAst);
+    }
+    let version = decoder.varint()?;
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    let count = decoder.varint()?;
+    for _ in 0..count {
+        let len = decoder.varint()? as usize;
+        let string = std::str::from_utf8(decoder.take(len)?).map_err(|_| Error::InvalidUtf8)?;
+        decoder.strings.push(string.to_string());
+    }
+
+    let count = decoder.varint()?;
+    let mut start = 0;
+    for _ in 0..count {
+        start = decoder.varint()?.saturating_add(start);
+        decoder.line_starts.push(start);
+    }
+
+    decoder.program()
+}
+
+// Wraps the encoded program in a `Uint8Array` without copying it.
+pub fn to_v8<'s>(scope: &mut v8::HandleScope<'s>, bytes: Vec<u8>) -> v8::Local<'s, v8::Uint8Array> {
+    let len = bytes.len();
+    let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+    v8::Uint8Array::new(scope, buffer, 0, len).unwrap()
+}
+
+#[derive(Default)]
+struct Encoder {
+    out: Vec<u8>,
+    strings: Vec<String>,
+    string_indexes: HashMap<String, u32>,
+    // The UTF-16 offset of the start of each line that a written range starts
+    // or ends on.
+    line_starts: Vec<Option<u32>>,
+}
+
+impl Encoder {
+    fn program(&mut self, program: &ast::Program) {
+        self.out.push(match program.source_type {
+            ast::SourceType::Script => 0,
+            ast::SourceType::Module => 1,
+        });
+        self.range(program.range, &program.loc);
+
+        self.varint(program.body.len() as u32);
+        for stmt in &program.body {
+            self.statement(stmt);
+        }
+
+        self.varint(program.tokens.len() as u32);
+        for token in &program.tokens {
+            let (kind, value) = match token {
+                ast::Token::Identifier(value) => (0, value),
+                ast::Token::Keyword(value) => (1, value),
+                ast::Token::String(value) => (2, value),
+                ast::Token::Punctuator(value) => (3, value),
+            };
+            self.out.push(kind);
+            self.token_value(value);
+        }
+
+        self.varint(program.comments.len() as u32);
+        for comment in &program.comments {
+            let (kind, value) = match comment {
+                ast::Comment::Line(value) => (0, value),
+                ast::Comment::Block(value) => (1, value),
+            };
+            self.out.push(kind);
+            self.token_value(value);
+        }
+    }
+
+    fn statement(&mut self, stmt: &ast::Statement) {
+        match stmt {
+            ast::Statement::DebuggerStatement(stmt) => {
+                self.out.push(tag::DEBUGGER_STATEMENT);
+                self.range(stmt.range, &stmt.loc);
+            }
+            ast::Statement::ExpressionStatement(stmt) => {
+                self.out.push(tag::EXPRESSION_STATEMENT);
+                self.range(stmt.range, &stmt.loc);
+                self.expression(&stmt.expression);
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &ast::Expression) {
+        match expr {
+            ast::Expression::Literal(literal) => {
+                self.out.push(tag::LITERAL);
+                self.range(literal.range, &literal.loc);
+                match &literal.value {
+                    ast::Value::String(value) => {
+                        self.out.push(0);
+                        self.string(value);
+                    }
+                    ast::Value::Number(value) => {
+                        self.out.push(1);
+                        self.out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                match &literal.raw {
+                    Some(raw) => {
+                        let index = self.string_index(raw);
+                        self.varint(index + 1);
+                    }
+                    None => self.varint(0),
+                }
+            }
+            ast::Expression::Identifier(ident) => self.identifier(ident),
+            ast::Expression::CallExpression(call) => {
+                self.out.push(tag::CALL_EXPRESSION);
+                self.range(call.range, &call.loc);
+                self.out.push(if call.optional { OPTIONAL } else { 0 });
+                self.expression(&call.callee);
+                self.varint(call.arguments.len() as u32);
+                for arg in &call.arguments {
+                    match arg {
+                        ast::ExprOrSpread::Expr(expr) => self.expression(expr),
+                        ast::ExprOrSpread::Spread(spread) => {
+                            self.out.push(tag::SPREAD_ELEMENT);
+                            self.range(spread.range, &spread.loc);
+                            self.expression(&spread.argument);
+                        }
+                    }
+                }
+            }
+            ast::Expression::MemberExpression(member) => {
+                self.out.push(tag::MEMBER_EXPRESSION);
+                self.range(member.range, &member.loc);
+                let mut flags = 0;
+                if member.optional {
+                    flags |= OPTIONAL;
+                }
+                if member.computed {
+                    flags |= COMPUTED;
+                }
+                self.out.push(flags);
+                self.expression(&member.object);
+                match member.property.as_ref() {
+                    ast::Prop::Identifier(ident) => self.identifier(ident),
+                }
+            }
+        }
+    }
+
+    fn identifier(&mut self, ident: &ast::Identifier) {
+        self.out.push(tag::IDENTIFIER);
+        self.range(ident.range, &ident.loc);
+        self.string(&ident.name);
+    }
+
+    fn token_value(&mut self, value: &ast::TokenValue) {
+        self.range(value.range, &value.loc);
+        self.string(&value.value);
+    }
+
+    fn range(&mut self, range: ast::Range, loc: &ast::Location) {
+        self.varint(range.0);
+        self.varint(range.1.saturating_sub(range.0));
+        self.line_start(range.0, &loc.start);
+        self.line_start(range.1, &loc.end);
+    }
+
+    fn line_start(&mut self, offset: u32, position: &ast::Position) {
+        let line = position.line.max(1) as usize;
+        if self.line_starts.len() < line {
+            self.line_starts.resize(line, None);
+        }
+        self.line_starts[line - 1] = Some(offset.saturating_sub(position.column));
+    }
+
+    // The program doesn't have its source so the line table is worked out from
+    // the `loc`s that were written.  Lines that nothing starts or ends on get
+    // the start of the next line that's known, which keeps the lookup in
+    // `Decoder::position` correct for every offset that's actually used.
+    fn line_starts(&self) -> Vec<u32> {
+        let mut next = u32::MAX;
+        let mut result: Vec<u32> = self
+            .line_starts
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(line, start)| {
+                next = if line == 0 { 0 } else { start.unwrap_or(next) };
+                next
+            })
+            .collect();
+        result.reverse();
+        result
+    }
+
+    fn string(&mut self, value: &str) {
+        let index = self.string_index(value);
+        self.varint(index);
+    }
+
+    fn string_index(&mut self, value: &str) -> u32 {
+        if let Some(index) = self.string_indexes.get(value) {
+            return *index;
+        }
+        let index = self.strings.len() as u32;
+        self.strings.push(value.to_string());
+        self.string_indexes.insert(value.to_string(), index);
+        index
+    }
+
+    fn varint(&mut self, value: u32) {
+        write_varint(&mut self.out, value);
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+    line_starts: Vec<u32>,
+}
+
+impl<'a> Decoder<'a> {
+    fn program(&mut self) -> Result<ast::Program, Error> {
+        let source_type = match self.byte()? {
+            0 => ast::SourceType::Script,
+            1 => ast::SourceType::Module,
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "source type",
+                    tag,
+                })
+            }
+        };
+        let range = self.range()?;
+
+        let count = self.varint()?;
+        let body = (0..count)
+            .map(|_| self.statement())
+            .collect::<Result<_, _>>()?;
+
+        let count = self.varint()?;
+        let tokens = (0..count)
+            .map(|_| {
+                let kind = self.byte()?;
+                let value = self.token_value()?;
+                Ok(match kind {
+                    0 => ast::Token::Identifier(value),
+                    1 => ast::Token::Keyword(value),
+                    2 => ast::Token::String(value),
+                    3 => ast::Token::Punctuator(value),
+                    tag => return Err(Error::InvalidTag { kind: "token", tag }),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let count = self.varint()?;
+        let comments = (0..count)
+            .map(|_| {
+                let kind = self.byte()?;
+                let value = self.token_value()?;
+                Ok(match kind {
+                    0 => ast::Comment::Line(value),
+                    1 => ast::Comment::Block(value),
+                    tag => {
+                        return Err(Error::InvalidTag {
+                            kind: "comment",
+                            tag,
+                        })
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ast::Program {
+            r#type: "Program".to_string(),
+            loc: self.loc(range),
+            range,
+            source_type,
+            body,
+            tokens,
+            comments,
+        })
+    }
+
+    fn statement(&mut self) -> Result<ast::Statement, Error> {
+        let tag = self.byte()?;
+        let range = self.range()?;
+        let loc = self.loc(range);
+        match tag {
+            tag::DEBUGGER_STATEMENT => {
+                Ok(ast::Statement::DebuggerStatement(ast::DebuggerStatement {
+                    loc,
+                    range,
+                }))
+            }
+            tag::EXPRESSION_STATEMENT => Ok(ast::Statement::ExpressionStatement(
+                ast::ExpressionStatement {
+                    loc,
+                    range,
+                    expression: Box::new(self.expression()?),
+                },
+            )),
+            tag => Err(Error::InvalidTag {
+                kind: "statement",
+                tag,
+            }),
+        }
+    }
+
+    fn expression(&mut self) -> Result<ast::Expression, Error> {
+        match self.expr_or_spread()? {
+            ast::ExprOrSpread::Expr(expr) => Ok(expr),
+            ast::ExprOrSpread::Spread(_) => Err(Error::InvalidTag {
+                kind: "expression",
+                tag: tag::SPREAD_ELEMENT,
+            }),
+        }
+    }
+
+    fn expr_or_spread(&mut self) -> Result<ast::ExprOrSpread, Error> {
+        let tag = self.byte()?;
+        let range = self.range()?;
+        let loc = self.loc(range);
+        let expr = match tag {
+            tag::IDENTIFIER => ast::Expression::Identifier(ast::Identifier {
+                loc,
+                range,
+                name: self.string()?,
+            }),
+            tag::LITERAL => {
+                let value = match self.byte()? {
+                    0 => ast::Value::String(self.string()?),
+                    1 => {
+                        let bytes = self.take(8)?.try_into().unwrap();
+                        ast::Value::Number(f64::from_le_bytes(bytes))
+                    }
+                    tag => return Err(Error::InvalidTag { kind: "value", tag }),
+                };
+                let raw = match self.varint()? {
+                    0 => None,
+                    index => Some(self.string_at(index - 1)?),
+                };
+                ast::Expression::Literal(ast::Literal {
+                    loc,
+                    range,
+                    value,
+                    raw,
+                })
+            }
+            tag::CALL_EXPRESSION => {
+                let flags = self.byte()?;
+                let callee = Box::new(self.expression()?);
+                let count = self.varint()?;
+                let arguments = (0..count)
+                    .map(|_| self.expr_or_spread())
+                    .collect::<Result<_, _>>()?;
+                ast::Expression::CallExpression(ast::CallExpression {
+                    loc,
+                    range,
+                    callee,
+                    arguments,
+                    optional: flags & OPTIONAL != 0,
+                })
+            }
+            tag::SPREAD_ELEMENT => {
+                return Ok(ast::ExprOrSpread::Spread(ast::SpreadElement {
+                    r#type: "SpreadElement".to_string(),
+                    loc,
+                    range,
+                    argument: Box::new(self.expression()?),
+                }))
+            }
+            tag::MEMBER_EXPRESSION => {
+                let flags = self.byte()?;
+                let object = Box::new(self.expression()?);
+                let property = match self.byte()? {
+                    tag::IDENTIFIER => ast::Prop::Identifier(self.identifier()?),
+                    tag => {
+                        return Err(Error::InvalidTag {
+                            kind: "property",
+                            tag,
+                        })
+                    }
+                };
+                ast::Expression::MemberExpression(ast::MemberExpression {
+                    loc,
+                    range,
+                    object,
+                    property: Box::new(property),
+                    computed: flags & COMPUTED != 0,
+                    optional: flags & OPTIONAL != 0,
+                })
+            }
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "expression",
+                    tag,
+                })
+            }
+        };
+        Ok(ast::ExprOrSpread::Expr(expr))
+    }
+
+    // Reads the rest of an identifier after its tag.
+    fn identifier(&mut self) -> Result<ast::Identifier, Error> {
+        let range = self.range()?;
+        Ok(ast::Identifier {
+            loc: self.loc(range),
+            range,
+            name: self.string()?,
+        })
+    }
+
+    fn token_value(&mut self) -> Result<ast::TokenValue, Error> {
+        let range = self.range()?;
+        Ok(ast::TokenValue {
+            loc: self.loc(range),
+            range,
+            value: self.string()?,
+        })
+    }
+
+    fn range(&mut self) -> Result<ast::Range, Error> {
+        let start = self.varint()?;
+        let len = self.varint()?;
+        Ok((start, start.saturating_add(len)))
+    }
+
+    fn loc(&self, range: ast::Range) -> ast::Location {
+        ast::Location {
+            start: self.position(range.0),
+            end: self.position(range.1),
+        }
+    }
+
+    fn position(&self, offset: u32) -> ast::Position {
+        let line = self
+            .line_starts
+            .partition_point(|start| *start <= offset)
+            .max(1);
+        let line_start = self.line_starts.get(line - 1).copied().unwrap_or_default();
+        ast::Position {
+            line: line as u32,
+            column: offset.saturating_sub(line_start),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let index = self.varint()?;
+        self.string_at(index)
+    }
+
+    fn string_at(&self, index: u32) -> Result<String, Error> {
+        self.strings
+            .get(index as usize)
+            .cloned()
+            .ok_or(Error::InvalidString(index as usize))
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.checked_add(len).ok_or(Error::UnexpectedEnd)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or(Error::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u32, Error> {
+        let mut value: u32 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::InvalidVarint)
+    }
+}
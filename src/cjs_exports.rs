@@ -0,0 +1,196 @@
+// Finds the names that a CommonJS module exports without running it, like
+// Node's `cjs-module-lexer`, so that ES modules can import them by name.  The
+// names have to be known when the module is imported but the module only runs
+// when the importer is evaluated.  These patterns are detected anywhere in the
+// module:
+//
+//     exports.name = ...;
+//     module.exports.name = ...;
+//     exports["name"] = ...;
+//     Object.defineProperty(exports, "name", ...);
+//     module.exports = { name, other: ..., ...require("./more") };
+//     module.exports = require("./more");
+//
+// The last two re-export whatever `./more` exports, resolving it is left to
+// the caller.
+
+use std::path::Path;
+
+use swc_common::sync::Lrc;
+use swc_common::{FileName, SourceMap};
+use swc_ecma_ast::{
+    AssignExpr, CallExpr, Callee, EsVersion, Expr, ExprOrSpread, Lit, MemberExpr, MemberProp,
+    ObjectLit, Pat, PatOrExpr, Prop, PropName, PropOrSpread,
+};
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use crate::transpile;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Exports {
+    // In the order they first appear.
+    pub names: Vec<String>,
+    // Specifiers of the modules whose exports are re-exported.
+    pub reexports: Vec<String>,
+}
+
+// Modules that don't parse have no exports, the syntax error is reported
+// when they run.
+pub fn analyze(path: &Path, source: &str) -> Exports {
+    let cm = Lrc::<SourceMap>::default();
+    let fm = cm.new_source_file(FileName::Real(path.to_path_buf()), source.to_string());
+    let syntax = if transpile::is_typescript(path) {
+        Syntax::Typescript(TsConfig::default())
+    } else {
+        Syntax::Es(Default::default())
+    };
+    let lexer = Lexer::new(syntax, EsVersion::latest(), StringInput::from(&*fm), None);
+    let mut parser = Parser::new_from(lexer);
+
+    let mut exports = Exports::default();
+    if let Ok(script) = parser.parse_script() {
+        script.visit_with(&mut exports);
+    }
+    exports
+}
+
+impl Exports {
+    fn add_name(&mut self, name: String) {
+        if !self.names.contains(&name) {
+            self.names.push(name);
+        }
+    }
+
+    fn add_reexport(&mut self, expr: &Expr) {
+        if let Some(specifier) = require_specifier(expr) {
+            self.reexports.push(specifier);
+        }
+    }
+
+    fn add_object(&mut self, object: &ObjectLit) {
+        for prop in &object.props {
+            match prop {
+                PropOrSpread::Spread(spread) => self.add_reexport(&spread.expr),
+                PropOrSpread::Prop(prop) => {
+                    let key = match prop.as_ref() {
+                        Prop::Shorthand(ident) => Some(ident.sym.to_string()),
+                        Prop::KeyValue(prop) => prop_name(&prop.key),
+                        Prop::Getter(prop) => prop_name(&prop.key),
+                        Prop::Method(prop) => prop_name(&prop.key),
+                        Prop::Assign(_) | Prop::Setter(_) => None,
+                    };
+                    if let Some(key) = key {
+                        self.add_name(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Visit for Exports {
+    fn visit_assign_expr(&mut self, assign: &AssignExpr) {
+        let target = match &assign.left {
+            PatOrExpr::Expr(expr) => Some(expr.as_ref()),
+            PatOrExpr::Pat(pat) => match pat.as_ref() {
+                Pat::Expr(expr) => Some(expr.as_ref()),
+                _ => None,
+            },
+        };
+        if let Some(Expr::Member(member)) = target {
+            if is_module_exports(member) {
+                match assign.right.as_ref() {
+                    Expr::Object(object) => self.add_object(object),
+                    right => self.add_reexport(right),
+                }
+            } else if is_exports(&member.obj) {
+                if let Some(name) = member_name(member) {
+                    self.add_name(name);
+                }
+            }
+        }
+        assign.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if is_define_property(call) {
+            if let [ExprOrSpread { expr: target, .. }, ExprOrSpread { expr: name, .. }, ..] =
+                call.args.as_slice()
+            {
+                if let (true, Expr::Lit(Lit::Str(name))) = (is_exports(target), name.as_ref()) {
+                    self.add_name(name.value.to_string());
+                }
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
+
+// `exports` or `module.exports`.
+fn is_exports(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(ident) => &*ident.sym == "exports",
+        Expr::Member(member) => is_module_exports(member),
+        _ => false,
+    }
+}
+
+fn is_module_exports(member: &MemberExpr) -> bool {
+    matches!(member.obj.as_ref(), Expr::Ident(ident) if &*ident.sym == "module")
+        && member_name(member).as_deref() == Some("exports")
+}
+
+// `Object.defineProperty(...)`.
+fn is_define_property(call: &CallExpr) -> bool {
+    let Callee::Expr(callee) = &call.callee else {
+        return false;
+    };
+    match callee.as_ref() {
+        Expr::Member(member) => {
+            matches!(member.obj.as_ref(), Expr::Ident(ident) if &*ident.sym == "Object")
+                && member_name(member).as_deref() == Some("defineProperty")
+        }
+        _ => false,
+    }
+}
+
+// The specifier of `require("specifier")`.
+fn require_specifier(expr: &Expr) -> Option<String> {
+    let Expr::Call(call) = expr else {
+        return None;
+    };
+    let Callee::Expr(callee) = &call.callee else {
+        return None;
+    };
+    if !matches!(callee.as_ref(), Expr::Ident(ident) if &*ident.sym == "require") {
+        return None;
+    }
+    match call.args.as_slice() {
+        [ExprOrSpread { spread: None, expr }] => match expr.as_ref() {
+            Expr::Lit(Lit::Str(specifier)) => Some(specifier.value.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// `name` in `object.name` and `object["name"]`.
+fn member_name(member: &MemberExpr) -> Option<String> {
+    match &member.prop {
+        MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+        MemberProp::Computed(computed) => match computed.expr.as_ref() {
+            Expr::Lit(Lit::Str(name)) => Some(name.value.to_string()),
+            _ => None,
+        },
+        MemberProp::PrivateName(_) => None,
+    }
+}
+
+fn prop_name(name: &PropName) -> Option<String> {
+    match name {
+        PropName::Ident(ident) => Some(ident.sym.to_string()),
+        PropName::Str(name) => Some(name.value.to_string()),
+        PropName::Num(_) | PropName::Computed(_) | PropName::BigInt(_) => None,
+    }
+}
@@ -0,0 +1,265 @@
+// Code path analysis over `ast::Program`, producing the same model as ESLint's
+// `CodePathAnalyzer` along with the `onCodePath*` events that it emits while
+// rules run.  Nodes are referred to by their index in the pre-order traversal
+// of `traverse`, see `linter/code_path.js` for how they're mapped back to the
+// AST's JS objects.
+
+use serde::Serialize;
+
+use crate::ast;
+use crate::traverse::{self, Node, NodeId, Visitor};
+
+pub type CodePathId = usize;
+pub type SegmentId = usize;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodePathAnalysis {
+    // The program's code path is always first.
+    pub code_paths: Vec<CodePath>,
+    pub segments: Vec<Segment>,
+    // In the order that ESLint emits them.
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodePath {
+    // Same format as ESLint's ids, "s1", "s2", ...
+    pub id: String,
+    pub origin: Origin,
+    pub upper: Option<CodePathId>,
+    pub child_code_paths: Vec<CodePathId>,
+    pub initial_segment: SegmentId,
+    // Segments that return or throw, in the order they were finalized.
+    pub final_segments: Vec<SegmentId>,
+    pub returned_segments: Vec<SegmentId>,
+    pub thrown_segments: Vec<SegmentId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    Program,
+    Function,
+    ClassFieldInitializer,
+    ClassStaticBlock,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    // The code path's id followed by a counter, "s1_1", "s1_2", ...
+    pub id: String,
+    pub code_path: CodePathId,
+    pub next_segments: Vec<SegmentId>,
+    pub prev_segments: Vec<SegmentId>,
+    pub reachable: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Event {
+    pub node: NodeId,
+    // Whether the event is emitted before the rules' handlers for entering
+    // `node` are called or after their handlers for leaving it.
+    pub phase: Phase,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Enter,
+    Leave,
+}
+
+// Serialized with the name of the rule handler in `type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum EventKind {
+    #[serde(rename = "onCodePathStart", rename_all = "camelCase")]
+    CodePathStart { code_path: CodePathId },
+    #[serde(rename = "onCodePathEnd", rename_all = "camelCase")]
+    CodePathEnd { code_path: CodePathId },
+    #[serde(rename = "onCodePathSegmentStart")]
+    SegmentStart { segment: SegmentId },
+    #[serde(rename = "onCodePathSegmentEnd")]
+    SegmentEnd { segment: SegmentId },
+    #[serde(rename = "onUnreachableCodePathSegmentStart")]
+    UnreachableSegmentStart { segment: SegmentId },
+    #[serde(rename = "onUnreachableCodePathSegmentEnd")]
+    UnreachableSegmentEnd { segment: SegmentId },
+    #[serde(rename = "onCodePathSegmentLoop", rename_all = "camelCase")]
+    SegmentLoop {
+        from_segment: SegmentId,
+        to_segment: SegmentId,
+    },
+}
+
+impl CodePathAnalysis {
+    // Segments of `code_path` in the order of ESLint's `traverseSegments`,
+    // each one after all of its previous segments.
+    // TODO: skip looped previous segments once loops are supported.
+    pub fn traverse_segments(&self, code_path: CodePathId) -> Vec<SegmentId> {
+        let mut order = vec![];
+        let mut visited = vec![false; self.segments.len()];
+        let mut stack = vec![self.code_paths[code_path].initial_segment];
+        while let Some(segment) = stack.pop() {
+            if visited[segment] {
+                continue;
+            }
+            let ready = self.segments[segment]
+                .prev_segments
+                .iter()
+                .all(|prev| visited[*prev]);
+            if !ready {
+                continue;
+            }
+            visited[segment] = true;
+            order.push(segment);
+            stack.extend(self.segments[segment].next_segments.iter().rev());
+        }
+        order
+    }
+}
+
+pub fn analyze(program: &ast::Program) -> CodePathAnalysis {
+    let mut analyzer = Analyzer {
+        analysis: CodePathAnalysis::default(),
+        current: vec![],
+        code_paths: vec![],
+    };
+    traverse::traverse(program, &mut analyzer);
+    analyzer.analysis
+}
+
+pub fn to_v8<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    analysis: &CodePathAnalysis,
+) -> Result<v8::Local<'s, v8::Value>, serde_v8::Error> {
+    serde_v8::to_v8(scope, analysis)
+}
+
+struct Analyzer {
+    analysis: CodePathAnalysis,
+    // The segments that code is currently being added to, there's more than
+    // one after branches join up again.
+    current: Vec<SegmentId>,
+    // The code paths that have started but not ended yet, innermost last.
+    code_paths: Vec<CodePathId>,
+}
+
+// None of the statements and expressions that `parser::parse` supports branch
+// or start a code path, so everything stays in the program's segments.
+impl Visitor<'_> for Analyzer {
+    fn enter(&mut self, id: NodeId, node: Node) {
+        if let Node::Program(_) = node {
+            let code_path = self.start_code_path(Origin::Program, id);
+            self.code_paths.push(code_path);
+        }
+    }
+
+    fn leave(&mut self, id: NodeId, node: Node) {
+        if let Node::Program(_) = node {
+            let code_path = self.code_paths.pop().unwrap();
+            self.end_code_path(code_path, id);
+        }
+    }
+}
+
+impl Analyzer {
+    fn emit(&mut self, node: NodeId, phase: Phase, kind: EventKind) {
+        self.analysis.events.push(Event { node, phase, kind });
+    }
+
+    fn new_segment(&mut self, code_path: CodePathId, prev_segments: Vec<SegmentId>) -> SegmentId {
+        let id = self.analysis.segments.len();
+        let counter = self
+            .analysis
+            .segments
+            .iter()
+            .filter(|segment| segment.code_path == code_path)
+            .count();
+        let reachable = prev_segments.is_empty()
+            || prev_segments
+                .iter()
+                .any(|prev| self.analysis.segments[*prev].reachable);
+        for prev in &prev_segments {
+            self.analysis.segments[*prev].next_segments.push(id);
+        }
+        self.analysis.segments.push(Segment {
+            id: format!("{}_{}", self.analysis.code_paths[code_path].id, counter + 1),
+            code_path,
+            next_segments: vec![],
+            prev_segments,
+            reachable,
+        });
+        id
+    }
+
+    fn start_segment(&mut self, segment: SegmentId, node: NodeId, phase: Phase) {
+        let kind = if self.analysis.segments[segment].reachable {
+            EventKind::SegmentStart { segment }
+        } else {
+            EventKind::UnreachableSegmentStart { segment }
+        };
+        self.emit(node, phase, kind);
+    }
+
+    fn end_segment(&mut self, segment: SegmentId, node: NodeId, phase: Phase) {
+        let kind = if self.analysis.segments[segment].reachable {
+            EventKind::SegmentEnd { segment }
+        } else {
+            EventKind::UnreachableSegmentEnd { segment }
+        };
+        self.emit(node, phase, kind);
+    }
+
+    fn start_code_path(&mut self, origin: Origin, node: NodeId) -> CodePathId {
+        let id = self.analysis.code_paths.len();
+        let upper = self
+            .current
+            .first()
+            .map(|segment| self.analysis.segments[*segment].code_path);
+        self.analysis.code_paths.push(CodePath {
+            id: format!("s{}", id + 1),
+            origin,
+            upper,
+            child_code_paths: vec![],
+            initial_segment: 0,
+            final_segments: vec![],
+            returned_segments: vec![],
+            thrown_segments: vec![],
+        });
+        if let Some(upper) = upper {
+            self.analysis.code_paths[upper].child_code_paths.push(id);
+        }
+
+        self.emit(
+            node,
+            Phase::Enter,
+            EventKind::CodePathStart { code_path: id },
+        );
+        let initial_segment = self.new_segment(id, vec![]);
+        self.analysis.code_paths[id].initial_segment = initial_segment;
+        self.current = vec![initial_segment];
+        self.start_segment(initial_segment, node, Phase::Enter);
+        id
+    }
+
+    // Falling off the end of a code path returns from it.
+    fn end_code_path(&mut self, code_path: CodePathId, node: NodeId) {
+        let segments = std::mem::take(&mut self.current);
+        for segment in &segments {
+            if self.analysis.segments[*segment].reachable {
+                let code_path = &mut self.analysis.code_paths[code_path];
+                code_path.returned_segments.push(*segment);
+                code_path.final_segments.push(*segment);
+            }
+        }
+        for segment in segments {
+            self.end_segment(segment, node, Phase::Leave);
+        }
+        self.emit(node, Phase::Leave, EventKind::CodePathEnd { code_path });
+    }
+}
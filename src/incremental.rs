@@ -0,0 +1,227 @@
+use crate::ast;
+use crate::parser::{self, SourceIndex};
+use crate::source_code::Ranged;
+
+// Replaces `range` of the source, in UTF-16 code units like ESTree ranges,
+// with `text`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub range: ast::Range,
+    pub text: String,
+}
+
+impl Edit {
+    pub fn apply(&self, source: &str) -> String {
+        let index = SourceIndex::new(source);
+        let start = index.byte_offset(self.range.0);
+        let end = index.byte_offset(self.range.1).max(start);
+        let mut result = String::with_capacity(source.len() + self.text.len());
+        result.push_str(&source[..start]);
+        result.push_str(&self.text);
+        result.push_str(&source[end..]);
+        result
+    }
+
+    fn delta(&self) -> i64 {
+        self.text.encode_utf16().count() as i64 - (self.range.1 as i64 - self.range.0 as i64)
+    }
+}
+
+// How `reparse` updated a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // Only the top-level statement at this index was parsed again.
+    Statement(usize),
+    // The whole source was parsed again.
+    Full,
+}
+
+// Updates `program`, which was parsed from `source`, for `edit`.
+//
+// When the edit is inside of a single top-level statement only that statement
+// is parsed again and the rest of the program is reused with its ranges and
+// locs shifted.  Otherwise, or when the edited statement no longer parses as a
+// single statement on its own, the new source is parsed from scratch.
+pub fn reparse(
+    program: ast::Program,
+    source: &str,
+    edit: &Edit,
+) -> Result<ast::Program, parser::Error> {
+    reparse_with_strategy(program, source, edit).map(|(program, _)| program)
+}
+
+// Like `reparse`, also returning which of the two ways was taken.
+pub fn reparse_with_strategy(
+    program: ast::Program,
+    source: &str,
+    edit: &Edit,
+) -> Result<(ast::Program, Strategy), parser::Error> {
+    let new_source = edit.apply(source);
+    let len = source.encode_utf16().count() as u32;
+    if edit.range.0 <= edit.range.1 && edit.range.1 <= len {
+        if let Ok((program, index)) = reparse_statement(program, &new_source, edit) {
+            return Ok((program, Strategy::Statement(index)));
+        }
+    }
+    parser::parse(new_source).map(|program| (program, Strategy::Full))
+}
+
+fn reparse_statement(
+    mut program: ast::Program,
+    new_source: &str,
+    edit: &Edit,
+) -> Result<(ast::Program, usize), ()> {
+    let index = program
+        .body
+        .iter()
+        .position(|stmt| {
+            let range = stmt.range();
+            range.0 <= edit.range.0 && edit.range.1 <= range.1
+        })
+        .ok_or(())?;
+    let old_range = program.body[index].range();
+    let delta = edit.delta();
+    let new_end = shift(old_range.1, delta);
+
+    // Without a `;` the statement could be continued by the next one, or
+    // continue the previous one, through automatic semicolon insertion.
+    let previous_token = program
+        .tokens
+        .iter()
+        .take_while(|token| token.range().1 <= old_range.0)
+        .last();
+    if previous_token.is_some_and(|token| !is_semicolon(token)) {
+        return Err(());
+    }
+
+    let lines = SourceIndex::new(new_source);
+    let start = lines.byte_offset(old_range.0);
+    let end = lines.byte_offset(new_end);
+    let snippet = new_source.get(start..end).ok_or(())?;
+    let mut reparsed = parser::parse(snippet.to_string()).map_err(|_| ())?;
+
+    if reparsed.body.len() != 1
+        || reparsed.body[0].range() != (0, new_end - old_range.0)
+        || !reparsed.tokens.last().is_some_and(is_semicolon)
+    {
+        return Err(());
+    }
+
+    let mut stmt = reparsed.body.remove(0);
+    for_each_position(&mut stmt, &mut |range, loc| {
+        update(&lines, range, loc, old_range.0 as i64)
+    });
+    program.body[index] = stmt;
+    for stmt in &mut program.body[index + 1..] {
+        for_each_position(stmt, &mut |range, loc| update(&lines, range, loc, delta));
+    }
+
+    program.tokens = splice(
+        program.tokens,
+        reparsed.tokens,
+        ast::Token::value_mut,
+        old_range,
+        delta,
+        &lines,
+    );
+    program.comments = splice(
+        program.comments,
+        reparsed.comments,
+        ast::Comment::value_mut,
+        old_range,
+        delta,
+        &lines,
+    );
+
+    program.range.1 = shift(program.range.1, delta);
+    program.loc = lines.loc_at(program.range);
+
+    Ok((program, index))
+}
+
+// Keeps the items before the edited statement, replaces the ones inside of it
+// with the reparsed ones and shifts the ones after it.
+fn splice<T>(
+    mut old: Vec<T>,
+    reparsed: Vec<T>,
+    value: fn(&mut T) -> &mut ast::TokenValue,
+    old_range: ast::Range,
+    delta: i64,
+    lines: &SourceIndex,
+) -> Vec<T> {
+    let start = old
+        .iter_mut()
+        .position(|item| value(item).range.1 > old_range.0)
+        .unwrap_or(old.len());
+    let mut rest = old.split_off(start);
+    rest.retain_mut(|item| value(item).range.0 >= old_range.1);
+
+    for mut item in reparsed {
+        let value = value(&mut item);
+        update(lines, &mut value.range, &mut value.loc, old_range.0 as i64);
+        old.push(item);
+    }
+    for mut item in rest {
+        let value = value(&mut item);
+        update(lines, &mut value.range, &mut value.loc, delta);
+        old.push(item);
+    }
+    old
+}
+
+fn for_each_position(
+    stmt: &mut ast::Statement,
+    f: &mut impl FnMut(&mut ast::Range, &mut ast::Location),
+) {
+    match stmt {
+        ast::Statement::DebuggerStatement(stmt) => f(&mut stmt.range, &mut stmt.loc),
+        ast::Statement::ExpressionStatement(stmt) => {
+            f(&mut stmt.range, &mut stmt.loc);
+            for_each_expression_position(&mut stmt.expression, f);
+        }
+    }
+}
+
+fn for_each_expression_position(
+    expr: &mut ast::Expression,
+    f: &mut impl FnMut(&mut ast::Range, &mut ast::Location),
+) {
+    match expr {
+        ast::Expression::Literal(literal) => f(&mut literal.range, &mut literal.loc),
+        ast::Expression::Identifier(ident) => f(&mut ident.range, &mut ident.loc),
+        ast::Expression::CallExpression(call) => {
+            f(&mut call.range, &mut call.loc);
+            for_each_expression_position(&mut call.callee, f);
+            for arg in &mut call.arguments {
+                match arg {
+                    ast::ExprOrSpread::Expr(expr) => for_each_expression_position(expr, f),
+                    ast::ExprOrSpread::Spread(spread) => {
+                        f(&mut spread.range, &mut spread.loc);
+                        for_each_expression_position(&mut spread.argument, f);
+                    }
+                }
+            }
+        }
+        ast::Expression::MemberExpression(member) => {
+            f(&mut member.range, &mut member.loc);
+            for_each_expression_position(&mut member.object, f);
+            match member.property.as_mut() {
+                ast::Prop::Identifier(ident) => f(&mut ident.range, &mut ident.loc),
+            }
+        }
+    }
+}
+
+// Moves `range` by `offset` and recomputes `loc` from it.
+fn update(lines: &SourceIndex, range: &mut ast::Range, loc: &mut ast::Location, offset: i64) {
+    *range = (shift(range.0, offset), shift(range.1, offset));
+    *loc = lines.loc_at(*range);
+}
+
+fn shift(offset: u32, delta: i64) -> u32 {
+    (offset as i64 + delta) as u32
+}
+
+fn is_semicolon(token: &ast::Token) -> bool {
+    matches!(token, ast::Token::Punctuator(value) if value.value == ";")
+}
@@ -0,0 +1,91 @@
+use serde_json::Value;
+
+use crate::ast;
+
+// Structural properties that every program returned by `parser::parse` must
+// satisfy, used by the property tests and the fuzz targets.
+pub fn check(program: &ast::Program) -> Result<(), String> {
+    let value = serde_json::to_value(program).map_err(|e| e.to_string())?;
+
+    check_nesting("Program", &value, &value["body"], "body")?;
+
+    let tokens = ranges(&value["tokens"]);
+    check_sorted("tokens", &tokens)?;
+
+    let comments = ranges(&value["comments"]);
+    check_sorted("comments", &comments)?;
+
+    for (i, comment) in comments.iter().enumerate() {
+        let overlapping = tokens
+            .iter()
+            .position(|token| token.0 < comment.1 && comment.0 < token.1);
+        if let Some(j) = overlapping {
+            return Err(format!(
+                "comments[{i}] {comment:?} overlaps tokens[{j}] {:?}",
+                tokens[j]
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Checks that every node's range is within the range of its parent node.
+fn check_nesting(path: &str, parent: &Value, value: &Value, key: &str) -> Result<(), String> {
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_nesting(path, parent, item, &format!("{key}[{i}]"))?;
+            }
+        }
+        Value::Object(node) if node.contains_key("range") => {
+            let path = format!("{path}.{key}");
+            let (start, end) = range(value).ok_or_else(|| format!("{path}: invalid range"))?;
+            let (parent_start, parent_end) = range(parent).unwrap();
+            if start > end {
+                return Err(format!("{path}: range [{start}, {end}] is reversed"));
+            }
+            if start < parent_start || end > parent_end {
+                return Err(format!(
+                    "{path}: range [{start}, {end}] is outside of parent range [{parent_start}, {parent_end}]"
+                ));
+            }
+            for (child_key, child) in node {
+                if child_key != "loc" && child_key != "range" {
+                    check_nesting(&path, value, child, child_key)?;
+                }
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+fn check_sorted(name: &str, ranges: &[(u64, u64)]) -> Result<(), String> {
+    for (i, range) in ranges.iter().enumerate() {
+        if range.0 > range.1 {
+            return Err(format!("{name}[{i}] {range:?} is reversed"));
+        }
+        if i > 0 && ranges[i - 1].1 > range.0 {
+            return Err(format!(
+                "{name}[{i}] {range:?} overlaps or precedes {name}[{}] {:?}",
+                i - 1,
+                ranges[i - 1]
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn range(node: &Value) -> Option<(u64, u64)> {
+    Some((node["range"][0].as_u64()?, node["range"][1].as_u64()?))
+}
+
+fn ranges(nodes: &Value) -> Vec<(u64, u64)> {
+    nodes
+        .as_array()
+        .map(|nodes| nodes.iter().filter_map(range).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+
+use serde::Serialize;
+
+use crate::ast::{self, Node};
+
+// Alternative to `serde_v8::to_v8(scope, &program)` which doesn't create all
+// of the JS objects up front.  Each node is backed by an object template with
+// an accessor per field that reads from the Rust node the first time the
+// field is accessed, after which the value is cached on the JS object.
+pub fn to_v8<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    program: ast::Program,
+) -> v8::Local<'a, v8::Value> {
+    if scope.get_slot::<LazyAst>().is_none() {
+        scope.set_slot(LazyAst::default());
+    }
+
+    // The JS objects keep pointers into the program, so it's owned by the
+    // isolate until the root object is collected.  Every node object refers
+    // to the root, so that only happens once none of them are reachable.
+    let program = Box::new(program);
+    let ptr: *const ast::Program = &*program;
+    let key = ptr as usize;
+    let lazy_ast = scope.get_slot_mut::<LazyAst>().unwrap();
+    lazy_ast.programs.insert(key, program);
+    // Weak handles can only be dropped once their finalizer has run,
+    // otherwise it never will.
+    let LazyAst {
+        programs, roots, ..
+    } = lazy_ast;
+    roots.retain(|(key, _)| programs.contains_key(key));
+
+    let template = template::<ast::Program>(scope);
+    let root = template.new_instance(scope).unwrap();
+    root.set_aligned_pointer_in_internal_field(NODE, ptr as *const c_void);
+    root.set_internal_field(ROOT, root.into());
+
+    let weak = v8::Weak::with_finalizer(
+        scope,
+        root,
+        Box::new(move |isolate| {
+            if let Some(lazy_ast) = isolate.get_slot_mut::<LazyAst>() {
+                lazy_ast.programs.remove(&key);
+            }
+        }),
+    );
+    let lazy_ast = scope.get_slot_mut::<LazyAst>().unwrap();
+    lazy_ast.roots.push((key, weak));
+
+    root.into()
+}
+
+// The number of programs passed to `to_v8` that are still reachable from JS.
+pub fn retained_programs(isolate: &v8::Isolate) -> usize {
+    isolate
+        .get_slot::<LazyAst>()
+        .map_or(0, |lazy_ast| lazy_ast.programs.len())
+}
+
+// Internal fields of node objects.
+const NODE: i32 = 0;
+const ROOT: usize = 1;
+
+#[derive(Default)]
+struct LazyAst {
+    // Keyed by address, boxed so that node addresses stay stable.
+    programs: HashMap<usize, Box<ast::Program>>,
+    // The root object of each program, whose finalizer frees it.
+    roots: Vec<(usize, v8::Weak<v8::Object>)>,
+    templates: HashMap<&'static str, v8::Global<v8::ObjectTemplate>>,
+}
+
+trait LazyNode: Node + Sized + 'static {
+    // All of the node's fields except for `type`.
+    const FIELDS: &'static [&'static str];
+
+    // `root` is the program's root object, which the returned nodes have to
+    // keep alive.
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>>;
+}
+
+fn template<'a, T: LazyNode>(scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::ObjectTemplate> {
+    let lazy_ast = scope.get_slot::<LazyAst>().unwrap();
+    if let Some(template) = lazy_ast.templates.get(T::TYPE).cloned() {
+        return v8::Local::new(scope, template);
+    }
+
+    let template = v8::ObjectTemplate::new(scope);
+    template.set_internal_field_count(2);
+
+    let key = v8::String::new(scope, "type").unwrap();
+    let value = v8::String::new(scope, T::TYPE).unwrap();
+    template.set(key.into(), value.into());
+
+    for field in T::FIELDS {
+        let key = v8::String::new(scope, field).unwrap();
+        template.set_accessor(key.into(), getter::<T>);
+    }
+
+    let global = v8::Global::new(scope, template);
+    let lazy_ast = scope.get_slot_mut::<LazyAst>().unwrap();
+    lazy_ast.templates.insert(T::TYPE, global);
+
+    template
+}
+
+fn getter<'a, T: LazyNode>(
+    scope: &mut v8::HandleScope<'a>,
+    key: v8::Local<'a, v8::Name>,
+    args: v8::PropertyCallbackArguments<'a>,
+    mut rv: v8::ReturnValue,
+) {
+    // The holder is the object with the accessor, the receiver can be any
+    // object that inherits from it, e.g. `Object.create(node).body`.
+    let holder = args.holder();
+    // SAFETY: the accessor is only defined on objects created from
+    // `template::<T>()`, which always have a pointer to a `T` in their
+    // internal field, and its program is owned by `LazyAst` for as long as
+    // the object is alive.
+    let node = unsafe { &*(holder.get_aligned_pointer_from_internal_field(NODE) as *const T) };
+    let root = holder.get_internal_field(scope, ROOT).unwrap();
+    let field = key.to_rust_string_lossy(scope);
+
+    if let Some(value) = node.get(scope, root, &field) {
+        // Replaces the accessor with a plain data property so that later
+        // reads don't call back into Rust.
+        holder.create_data_property(scope, key, value);
+        rv.set(value);
+    }
+}
+
+fn wrap<'a, T: LazyNode>(
+    scope: &mut v8::HandleScope<'a>,
+    root: v8::Local<'a, v8::Value>,
+    node: &T,
+) -> v8::Local<'a, v8::Value> {
+    let template = template::<T>(scope);
+    let object = template.new_instance(scope).unwrap();
+    object.set_aligned_pointer_in_internal_field(NODE, node as *const T as *const c_void);
+    object.set_internal_field(ROOT, root);
+    object.into()
+}
+
+fn wrap_statement<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    root: v8::Local<'a, v8::Value>,
+    stmt: &ast::Statement,
+) -> v8::Local<'a, v8::Value> {
+    match stmt {
+        ast::Statement::DebuggerStatement(stmt) => wrap(scope, root, stmt),
+        ast::Statement::ExpressionStatement(stmt) => wrap(scope, root, stmt),
+    }
+}
+
+fn wrap_expression<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    root: v8::Local<'a, v8::Value>,
+    expr: &ast::Expression,
+) -> v8::Local<'a, v8::Value> {
+    match expr {
+        ast::Expression::Literal(expr) => wrap(scope, root, expr),
+        ast::Expression::Identifier(expr) => wrap(scope, root, expr),
+        ast::Expression::CallExpression(expr) => wrap(scope, root, expr),
+        ast::Expression::MemberExpression(expr) => wrap(scope, root, expr),
+    }
+}
+
+fn wrap_all<'a, T>(
+    scope: &mut v8::HandleScope<'a>,
+    root: v8::Local<'a, v8::Value>,
+    items: &[T],
+    wrap_item: impl Fn(
+        &mut v8::HandleScope<'a>,
+        v8::Local<'a, v8::Value>,
+        &T,
+    ) -> v8::Local<'a, v8::Value>,
+) -> v8::Local<'a, v8::Value> {
+    let elements: Vec<_> = items
+        .iter()
+        .map(|item| wrap_item(scope, root, item))
+        .collect();
+    v8::Array::new_with_elements(scope, &elements).into()
+}
+
+// Fields that don't contain nodes are converted eagerly.
+fn to_value<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    value: &impl Serialize,
+) -> Option<v8::Local<'a, v8::Value>> {
+    serde_v8::to_v8(scope, value).ok()
+}
+
+impl LazyNode for ast::Program {
+    const FIELDS: &'static [&'static str] =
+        &["loc", "range", "sourceType", "body", "tokens", "comments"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "sourceType" => to_value(scope, &self.source_type),
+            "body" => Some(wrap_all(scope, root, &self.body, wrap_statement)),
+            "tokens" => to_value(scope, &self.tokens),
+            "comments" => to_value(scope, &self.comments),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::DebuggerStatement {
+    const FIELDS: &'static [&'static str] = &["loc", "range"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        _root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::ExpressionStatement {
+    const FIELDS: &'static [&'static str] = &["loc", "range", "expression"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "expression" => Some(wrap_expression(scope, root, &self.expression)),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::Identifier {
+    const FIELDS: &'static [&'static str] = &["loc", "range", "name"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        _root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "name" => to_value(scope, &self.name),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::Literal {
+    const FIELDS: &'static [&'static str] = &["loc", "range", "value", "raw"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        _root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "value" => to_value(scope, &self.value),
+            "raw" => to_value(scope, &self.raw),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::CallExpression {
+    const FIELDS: &'static [&'static str] = &["loc", "range", "callee", "arguments", "optional"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "callee" => Some(wrap_expression(scope, root, &self.callee)),
+            "arguments" => Some(wrap_all(
+                scope,
+                root,
+                &self.arguments,
+                |scope, root, arg| match arg {
+                    ast::ExprOrSpread::Expr(expr) => wrap_expression(scope, root, expr),
+                    ast::ExprOrSpread::Spread(spread) => wrap(scope, root, spread),
+                },
+            )),
+            "optional" => to_value(scope, &self.optional),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::SpreadElement {
+    const FIELDS: &'static [&'static str] = &["loc", "range", "argument"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "argument" => Some(wrap_expression(scope, root, &self.argument)),
+            _ => None,
+        }
+    }
+}
+
+impl LazyNode for ast::MemberExpression {
+    const FIELDS: &'static [&'static str] =
+        &["loc", "range", "object", "property", "computed", "optional"];
+
+    fn get<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        root: v8::Local<'a, v8::Value>,
+        field: &str,
+    ) -> Option<v8::Local<'a, v8::Value>> {
+        match field {
+            "loc" => to_value(scope, &self.loc),
+            "range" => to_value(scope, &self.range),
+            "object" => Some(wrap_expression(scope, root, &self.object)),
+            "property" => match self.property.as_ref() {
+                ast::Prop::Identifier(ident) => Some(wrap(scope, root, ident)),
+            },
+            "computed" => to_value(scope, &self.computed),
+            "optional" => to_value(scope, &self.optional),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+pub mod arena;
+pub mod ast;
+pub mod binary;
+pub mod cjs_exports;
+pub mod code_path;
+pub mod incremental;
+pub mod invariants;
+pub mod lazy_ast;
+pub mod loader;
+pub mod module_source;
+pub mod parser;
+pub mod printer;
+pub mod resolver;
+pub mod schema;
+pub mod scope;
+pub mod server;
+pub mod source_code;
+pub mod store;
+pub mod to_swc;
+pub mod transpile;
+pub mod traverse;
+pub mod visitor_keys;
@@ -1,66 +1,457 @@
 use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use std::num::NonZeroI32;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{error, fmt};
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::module_source::{Disk, ModuleSource};
+use crate::parser::{self, SourceIndex};
+use crate::resolver::{self, Format};
+use crate::{cjs_exports, transpile};
+
+// Every module that's been compiled, so that imports can be resolved relative
+// to the module that they're in, whatever order V8 asks for them in.
 struct Loader {
-    cwds: Vec<String>,
-    handles: HashMap<String, v8::Global<v8::Module>>,
+    // Where modules are read from.
+    source: Rc<dyn ModuleSource>,
+    // Canonical path -> module.
+    handles: HashMap<PathBuf, v8::Global<v8::Module>>,
+    // Module -> canonical path, keyed by identity hash.  Hashes aren't unique
+    // so each entry can have more than one module.
+    paths: HashMap<NonZeroI32, Vec<(v8::Global<v8::Module>, PathBuf)>>,
+    // Canonical path -> `module` object of each CommonJS module.  Modules are
+    // added before they run so that circular requires get their exports so
+    // far, like in Node.
+    cjs_modules: HashMap<PathBuf, v8::Global<v8::Object>>,
+    // Canonical path -> what each synthetic module exports, they wrap
+    // CommonJS and JSON modules so that they can be imported.
+    synthetic_modules: HashMap<PathBuf, SyntheticModule>,
+    // Canonical path -> source map of each TypeScript module, for mapping
+    // stack traces back to the TypeScript.
+    source_maps: HashMap<PathBuf, sourcemap::SourceMap>,
+    // What `import()` in scripts that don't have a path resolves relative to.
+    eval_referrer: PathBuf,
 }
 
-pub fn load_esm_module() -> Option<()> {
-    let isolate = &mut v8::Isolate::new(Default::default());
+enum SyntheticModule {
+    // Exports the value as `default`.
+    Json(v8::Global<v8::Value>),
+    // Runs the module when it's evaluated and exports its `module.exports`
+    // as `default`, along with each of these properties of it.
+    CommonJs(Vec<String>),
+}
 
-    let scope = &mut v8::HandleScope::new(isolate);
-    let context = v8::Context::new(scope);
-    let scope = &mut v8::ContextScope::new(scope, context);
+// Sets up the isolate for loading modules and adds a global `require` that
+// resolves specifiers relative to `dir`, like Node's REPL.
+pub fn init(scope: &mut v8::HandleScope, dir: &Path) {
+    init_with_source(scope, dir, Rc::new(Disk));
+}
 
-    let loader = Loader {
-        cwds: vec![".".to_string()],
+// `init`, but reading modules from `source` instead of the filesystem.
+pub fn init_with_source(scope: &mut v8::HandleScope, dir: &Path, source: Rc<dyn ModuleSource>) {
+    // `require` and `import()` resolve relative to the directory of the
+    // module they're given, so this module doesn't need to exist.
+    let referrer = dir.join("[eval]");
+    scope.set_slot(Loader {
+        source,
         handles: HashMap::new(),
-    };
-    scope.set_slot(loader);
+        paths: HashMap::new(),
+        cjs_modules: HashMap::new(),
+        synthetic_modules: HashMap::new(),
+        source_maps: HashMap::new(),
+        eval_referrer: referrer.clone(),
+    });
+    scope.set_host_import_module_dynamically_callback(host_import_module_dynamically_callback);
+
+    let require = require_function(scope, &referrer);
+    let key = v8::String::new(scope, "require").unwrap();
+    let global = scope.get_current_context().global(scope);
+    global.set(scope, key.into(), require.into());
+}
+
+// Runs the CommonJS module at `path`, or returns it from the cache if it has
+// already run, and returns its `module.exports`.  Returns `None` if an
+// exception was thrown.
+pub fn require<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    path: &Path,
+) -> Option<v8::Local<'a, v8::Value>> {
+    match module_source(scope).canonicalize(path) {
+        Ok(path) => load_cjs_module(scope, &path),
+        Err(e) => {
+            throw_error(
+                scope,
+                &format!("cannot find module {}: {e}", path.display()),
+            );
+            None
+        }
+    }
+}
+
+// An ES module that has been evaluated, whose exports can be used from Rust.
+pub struct JsModule {
+    path: PathBuf,
+    module: v8::Global<v8::Module>,
+}
+
+impl JsModule {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Sorted, like the keys of the module's namespace object.
+    pub fn export_names(&self, scope: &mut v8::HandleScope) -> Vec<String> {
+        let namespace = self.namespace(scope);
+        let Some(names) = namespace.get_own_property_names(scope, Default::default()) else {
+            return vec![];
+        };
+        (0..names.length())
+            .filter_map(|i| {
+                let name = names.get_index(scope, i)?;
+                Some(name.to_rust_string_lossy(scope))
+            })
+            .collect()
+    }
+
+    pub fn get_export_value<'a>(
+        &self,
+        scope: &mut v8::HandleScope<'a>,
+        name: &str,
+    ) -> Result<v8::Local<'a, v8::Value>, LoaderError> {
+        let namespace = self.namespace(scope);
+        let key = v8::String::new(scope, name).unwrap();
+        if namespace.has_own_property(scope, key.into()) != Some(true) {
+            return Err(self.export_error(name, "no such export"));
+        }
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        match namespace.get(tc_scope, key.into()) {
+            Some(value) => {
+                let value = v8::Global::new(tc_scope, value);
+                Ok(v8::Local::new(tc_scope, value))
+            }
+            None => Err(self.caught(tc_scope)),
+        }
+    }
+
+    // Deserializes the export called `name` with `serde_v8`.
+    pub fn get_export<T: DeserializeOwned>(
+        &self,
+        scope: &mut v8::HandleScope,
+        name: &str,
+    ) -> Result<T, LoaderError> {
+        let value = self.get_export_value(scope, name)?;
+        serde_v8::from_v8(scope, value).map_err(|e| self.export_error(name, e))
+    }
+
+    // Calls the function exported as `name` and deserializes what it
+    // returns, waiting for it to settle if it's a promise.  `args` is
+    // serialized to an array of arguments, e.g. from a tuple; use `()` to
+    // call the function without any.
+    pub fn call_export<A: Serialize, R: DeserializeOwned>(
+        &self,
+        scope: &mut v8::HandleScope,
+        name: &str,
+        args: A,
+    ) -> Result<R, LoaderError> {
+        let function = self.get_export_value(scope, name)?;
+        let function = v8::Local::<v8::Function>::try_from(function)
+            .map_err(|_| self.export_error(name, "not a function"))?;
 
-    let module = load_module("./js/index.js", scope)?;
+        let args = serde_v8::to_v8(scope, args).map_err(|e| self.export_error(name, e))?;
+        let args = if args.is_null_or_undefined() {
+            vec![]
+        } else {
+            let args = v8::Local::<v8::Array>::try_from(args)
+                .map_err(|_| self.export_error(name, "arguments must serialize to an array"))?;
+            (0..args.length())
+                .map(|i| args.get_index(scope, i).unwrap())
+                .collect()
+        };
+
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        let receiver = v8::undefined(tc_scope);
+        let Some(mut result) = function.call(tc_scope, receiver.into(), &args) else {
+            return Err(self.caught(tc_scope));
+        };
+        if let Ok(promise) = v8::Local::<v8::Promise>::try_from(result) {
+            if promise.state() == v8::PromiseState::Pending {
+                tc_scope.perform_microtask_checkpoint();
+            }
+            result = promise.result(tc_scope);
+            match promise.state() {
+                v8::PromiseState::Pending => {
+                    return Err(self.export_error(name, "returned a promise that never settled"));
+                }
+                v8::PromiseState::Rejected => {
+                    return Err(evaluation_error(tc_scope, &self.path, result));
+                }
+                v8::PromiseState::Fulfilled => {}
+            }
+        }
+        serde_v8::from_v8(tc_scope, result).map_err(|e| self.export_error(name, e))
+    }
 
-    module.evaluate(scope).unwrap();
+    fn namespace<'a>(&self, scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::Object> {
+        let module = v8::Local::new(scope, &self.module);
+        let namespace = v8::Global::new(scope, module.get_module_namespace());
+        v8::Local::new(scope, namespace).try_into().unwrap()
+    }
 
-    let ns = module.get_module_namespace();
-    eprintln!("ns.is_object() = {}", ns.is_object());
-    let obj = ns.to_object(scope)?;
-    let key = v8::String::new(scope, "foobar")?;
-    let foobar = obj.get(scope, key.into()).unwrap();
-    eprintln!("foobar = {}", foobar.to_rust_string_lossy(scope));
+    fn export_error(&self, name: &str, message: impl fmt::Display) -> LoaderError {
+        LoaderError::ExportError {
+            path: self.path.clone(),
+            name: name.to_string(),
+            message: message.to_string(),
+        }
+    }
 
-    Some(())
+    fn caught(&self, tc_scope: &mut v8::TryCatch<v8::HandleScope>) -> LoaderError {
+        match tc_scope.exception() {
+            Some(exception) => evaluation_error(tc_scope, &self.path, exception),
+            None => LoaderError::EvaluationError {
+                path: self.path.clone(),
+                message: "execution was terminated".to_string(),
+                stack: None,
+            },
+        }
+    }
 }
 
-fn load_module<'a>(
-    path: &str,
-    scope: &mut v8::HandleScope<'a>,
-) -> Option<v8::Local<'a, v8::Module>> {
-    let loader = scope.get_slot_mut::<Loader>().unwrap();
+// Loads, instantiates and evaluates the ES module at `path`, `init` must
+// have been called first.
+pub fn import(scope: &mut v8::HandleScope, path: &Path) -> Result<JsModule, LoaderError> {
+    let module = load_module(path, scope)?;
+    instantiate(scope, module)?;
+    evaluate(scope, module)?;
+    Ok(JsModule {
+        path: module_path(scope, module).unwrap_or_default(),
+        module: v8::Global::new(scope, module),
+    })
+}
+
+#[derive(Debug)]
+pub enum LoaderError {
+    // `specifier` couldn't be resolved or read, `referrer` is the module that
+    // imported it.
+    NotFound {
+        specifier: String,
+        referrer: Option<PathBuf>,
+        reason: String,
+    },
+    // With the 1-based line and column that V8 reported.
+    SyntaxError {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    // Instantiating the graph under `path` failed, e.g. because an import
+    // doesn't exist in the module that it's imported from.
+    LinkError {
+        path: PathBuf,
+        message: String,
+    },
+    // Thrown by a module while it was being evaluated, or the reason its
+    // evaluation promise was rejected.  `stack` is the exception's stack if
+    // it has one.
+    EvaluationError {
+        path: PathBuf,
+        message: String,
+        stack: Option<String>,
+    },
+    // Using the export `name` of the module at `path` from Rust failed, e.g.
+    // because it doesn't exist or couldn't be (de)serialized.
+    ExportError {
+        path: PathBuf,
+        name: String,
+        message: String,
+    },
+}
 
-    let current_cwd = loader.cwds.last().unwrap().clone();
-    let parent_dir = Path::new(&path).parent().unwrap();
+impl fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoaderError::NotFound {
+                specifier,
+                referrer: Some(referrer),
+                reason,
+            } => write!(
+                f,
+                "cannot find module {specifier:?} imported from {}: {reason}",
+                referrer.display()
+            ),
+            LoaderError::NotFound {
+                specifier,
+                referrer: None,
+                reason,
+            } => write!(f, "cannot find module {specifier:?}: {reason}"),
+            LoaderError::SyntaxError {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{line}:{column}: {message}", path.display()),
+            LoaderError::LinkError { path, message } => {
+                write!(f, "error linking {}: {message}", path.display())
+            }
+            LoaderError::EvaluationError {
+                path,
+                message,
+                stack,
+            } => write!(
+                f,
+                "error evaluating {}: {}",
+                path.display(),
+                stack.as_deref().unwrap_or(message)
+            ),
+            LoaderError::ExportError {
+                path,
+                name,
+                message,
+            } => write!(f, "export {name:?} of {}: {message}", path.display()),
+        }
+    }
+}
 
-    loader.cwds.push(
-        Path::new(&current_cwd)
-            .join(parent_dir)
-            .to_str()
-            .unwrap()
-            .to_string(),
-    );
+impl error::Error for LoaderError {}
 
-    let path = Path::new(&current_cwd).join(path);
-    let code = match fs::read_to_string(&path) {
-        Ok(src) => v8::String::new(scope, &src).unwrap(),
-        Err(e) => {
-            eprintln!("error reading file {:#?}: {:#?}", path, e);
-            return None;
+// Links `module` and everything it imports, which must have been loaded by
+// `load_module`.
+pub fn instantiate(
+    scope: &mut v8::HandleScope,
+    module: v8::Local<v8::Module>,
+) -> Result<(), LoaderError> {
+    if module.get_status() != v8::ModuleStatus::Uninstantiated {
+        return Ok(());
+    }
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    if module
+        .instantiate_module(tc_scope, module_resolve_callback)
+        .is_some()
+    {
+        return Ok(());
+    }
+    let message = tc_scope
+        .exception()
+        .map(|exception| exception.to_rust_string_lossy(tc_scope))
+        .unwrap_or_else(|| "instantiation failed".to_string());
+    Err(LoaderError::LinkError {
+        path: module_path(tc_scope, module).unwrap_or_default(),
+        message,
+    })
+}
+
+// Evaluates the instantiated `module`, running microtasks until its
+// evaluation promise settles so that modules with top-level `await` have
+// finished by the time this returns.  There's no event loop, so awaiting
+// anything other than promises that microtasks settle is an error.
+pub fn evaluate(
+    scope: &mut v8::HandleScope,
+    module: v8::Local<v8::Module>,
+) -> Result<(), LoaderError> {
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let path = module_path(tc_scope, module).unwrap_or_default();
+    let Some(result) = module.evaluate(tc_scope) else {
+        return Err(match tc_scope.exception() {
+            Some(exception) => evaluation_error(tc_scope, &path, exception),
+            None => LoaderError::EvaluationError {
+                path,
+                message: "evaluation was terminated".to_string(),
+                stack: None,
+            },
+        });
+    };
+
+    if let Ok(promise) = v8::Local::<v8::Promise>::try_from(result) {
+        if promise.state() == v8::PromiseState::Pending {
+            tc_scope.perform_microtask_checkpoint();
         }
+        match promise.state() {
+            v8::PromiseState::Pending => {
+                return Err(LoaderError::EvaluationError {
+                    path,
+                    message: "top-level await never settled".to_string(),
+                    stack: None,
+                });
+            }
+            v8::PromiseState::Rejected => {
+                let reason = promise.result(tc_scope);
+                return Err(evaluation_error(tc_scope, &path, reason));
+            }
+            v8::PromiseState::Fulfilled => {}
+        }
+    }
+
+    if module.get_status() == v8::ModuleStatus::Errored {
+        let exception = module.get_exception();
+        return Err(evaluation_error(tc_scope, &path, exception));
+    }
+    Ok(())
+}
+
+// Resolves an import `specifier` in the module at `referrer` the way Node
+// resolves ESM imports, see `resolver`.  Imports in TypeScript modules can
+// refer to `.ts` files by their `.js` names.
+pub fn resolve(specifier: &str, referrer: &Path) -> Result<PathBuf, resolver::Error> {
+    let options = resolver::Options {
+        typescript: transpile::is_typescript(referrer),
+        ..resolver::Options::esm()
     };
+    resolver::resolve(specifier, referrer, &options)
+}
+
+// Compiles the module at `path` along with everything it imports, the whole
+// graph has been loaded by the time the module is instantiated.  If any of it
+// fails, every ES module registered along the way is unregistered so that
+// importing one of them again retries the whole graph, otherwise a module in
+// a cycle with the failing one would be found without it.
+fn load_module<'a>(
+    path: &Path,
+    scope: &mut v8::HandleScope<'a>,
+) -> Result<v8::Local<'a, v8::Module>, LoaderError> {
+    let mut registered = vec![];
+    let result = load_graph(path, scope, &mut registered);
+    if result.is_err() {
+        for path in &registered {
+            unregister_module(scope, path);
+        }
+    }
+    result
+}
+
+// `registered` collects the paths of the ES modules that are registered.
+fn load_graph<'a>(
+    path: &Path,
+    scope: &mut v8::HandleScope<'a>,
+    registered: &mut Vec<PathBuf>,
+) -> Result<v8::Local<'a, v8::Module>, LoaderError> {
+    let path = module_source(scope)
+        .canonicalize(path)
+        .map_err(|e| LoaderError::NotFound {
+            specifier: path.display().to_string(),
+            referrer: None,
+            reason: e.to_string(),
+        })?;
+
+    let loader = scope.get_slot::<Loader>().unwrap();
+    if let Some(handle) = loader.handles.get(&path).cloned() {
+        return Ok(v8::Local::new(scope, handle));
+    }
 
-    let resource_name = v8::String::new(scope, path.to_str().unwrap()).unwrap();
+    match format(scope, &path) {
+        Format::Module => {}
+        Format::CommonJs => return load_cjs_as_esm(&path, scope),
+        Format::Json => return load_json_as_esm(&path, scope),
+    }
+
+    let source = read_source(scope, &path)?;
+    let source = transpile_typescript(scope, &path, source)?;
+    let code = v8::String::new(scope, &source).unwrap();
+
+    let resource_name = v8::String::new(scope, &path.to_string_lossy()).unwrap();
     let source_map_url = v8::undefined(scope);
     let script_origin = v8::ScriptOrigin::new(
         scope,
@@ -76,46 +467,772 @@ fn load_module<'a>(
     );
 
     let src = v8::script_compiler::Source::new(code, Some(&script_origin));
-    let module = v8::script_compiler::compile_module(scope, src).unwrap();
-    let handle = v8::Global::<v8::Module>::new(scope, module);
-
-    module
-        .instantiate_module(scope, module_resolve_callback)
-        .unwrap();
+    let module = {
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        match v8::script_compiler::compile_module(tc_scope, src) {
+            Some(module) => v8::Global::new(tc_scope, module),
+            None => return Err(syntax_error(tc_scope, &path)),
+        }
+    };
+    let module = v8::Local::new(scope, module);
 
-    let loader = scope.get_slot_mut::<Loader>().unwrap();
-    loader.cwds.pop();
+    // Registered before loading imports so that cycles find this module.
+    register_module(scope, &path, module);
+    registered.push(path.clone());
+    load_dependencies(scope, &path, module, registered)?;
 
-    let full_path = path.canonicalize().unwrap().to_str().unwrap().to_string();
-    loader.handles.insert(full_path, handle);
+    Ok(module)
+}
 
-    Some(module)
+// Loads everything that the module at `path` imports.
+fn load_dependencies(
+    scope: &mut v8::HandleScope,
+    path: &Path,
+    module: v8::Local<v8::Module>,
+    registered: &mut Vec<PathBuf>,
+) -> Result<(), LoaderError> {
+    let requests = module.get_module_requests();
+    for i in 0..requests.length() {
+        let request: v8::Local<v8::ModuleRequest> =
+            requests.get(scope, i).unwrap().try_into().unwrap();
+        let specifier = request.get_specifier().to_rust_string_lossy(scope);
+        let options = esm_options(scope, path);
+        let dependency =
+            resolver::resolve(&specifier, path, &options).map_err(|e| LoaderError::NotFound {
+                specifier,
+                referrer: Some(path.to_path_buf()),
+                reason: e.to_string(),
+            })?;
+        // Checked before loading so that nothing runs for a mismatched import.
+        let import_type = import_type(scope, request.get_import_assertions(), 3);
+        check_import_type(scope, &dependency, import_type.as_deref()).map_err(|message| {
+            LoaderError::LinkError {
+                path: path.to_path_buf(),
+                message: format!("TypeError: {message}"),
+            }
+        })?;
+        load_graph(&dependency, scope, registered)?;
+    }
+    Ok(())
 }
 
 fn module_resolve_callback<'a>(
     context: v8::Local<'a, v8::Context>,
     specifier: v8::Local<'a, v8::String>,
     _import_assertions: v8::Local<'a, v8::FixedArray>,
-    _referrer: v8::Local<'a, v8::Module>,
+    referrer: v8::Local<'a, v8::Module>,
 ) -> Option<v8::Local<'a, v8::Module>> {
     let scope = &mut unsafe { v8::CallbackScope::new(context) };
-    let path = specifier.to_rust_string_lossy(scope);
+    let specifier = specifier.to_rust_string_lossy(scope);
+
+    let referrer_path = module_path(scope, referrer)?;
+    let options = esm_options(scope, &referrer_path);
+    let path = match resolver::resolve(&specifier, &referrer_path, &options) {
+        Ok(path) => path,
+        Err(e) => {
+            throw_error(scope, &e.to_string());
+            return None;
+        }
+    };
+
+    let loader = scope.get_slot::<Loader>().unwrap();
+    match loader.handles.get(&path).cloned() {
+        Some(handle) => Some(v8::Local::new(scope, handle)),
+        // Everything is loaded, and the import attributes checked, up front
+        // by `load_module`.
+        None => {
+            throw_error(scope, &format!("{} wasn't loaded", path.display()));
+            None
+        }
+    }
+}
+
+// Like Node, JSON has to be imported with `with { type: "json" }` and that's
+// only allowed for JSON, so that importing a file can't run code that wasn't
+// expected.  Returns the message of the `TypeError` to throw if
+// `import_type` doesn't match `path`.
+fn check_import_type(
+    scope: &mut v8::HandleScope,
+    path: &Path,
+    import_type: Option<&str>,
+) -> Result<(), String> {
+    let is_json = format(scope, path) == Format::Json;
+    match import_type {
+        None if is_json => Err(format!(
+            "module {} needs an import attribute of type \"json\"",
+            path.display()
+        )),
+        Some("json") if !is_json => Err(format!("module {} is not a JSON module", path.display())),
+        None | Some("json") => Ok(()),
+        Some(other) => Err(format!("import attribute type {other:?} is not supported")),
+    }
+}
+
+// The value of the `type` import attribute.  `import_assertions` has a key and
+// value for each attribute, followed by its source offset in static imports,
+// `stride` is the number of entries per attribute.
+fn import_type(
+    scope: &mut v8::HandleScope,
+    import_assertions: v8::Local<v8::FixedArray>,
+    stride: usize,
+) -> Option<String> {
+    (0..import_assertions.length())
+        .step_by(stride)
+        .find_map(|i| {
+            let key = import_assertions.get(scope, i)?;
+            let key = v8::Local::<v8::String>::try_from(key).ok()?;
+            if key.to_rust_string_lossy(scope) != "type" {
+                return None;
+            }
+            let value = import_assertions.get(scope, i + 1)?;
+            let value = v8::Local::<v8::String>::try_from(value).ok()?;
+            Some(value.to_rust_string_lossy(scope))
+        })
+}
 
+// Loads, instantiates and evaluates the module that `import()` asks for.  The
+// promise resolves to its namespace once it has been evaluated, including
+// any top-level `await`s, or rejects with whatever was thrown.
+fn host_import_module_dynamically_callback<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    _host_defined_options: v8::Local<'s, v8::Data>,
+    resource_name: v8::Local<'s, v8::Value>,
+    specifier: v8::Local<'s, v8::String>,
+    import_assertions: v8::Local<'s, v8::FixedArray>,
+) -> Option<v8::Local<'s, v8::Promise>> {
+    let resolver = v8::PromiseResolver::new(scope)?;
+    let promise = resolver.get_promise(scope);
+
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let import_type = import_type(tc_scope, import_assertions, 2);
+    let specifier = specifier.to_rust_string_lossy(tc_scope);
+    let referrer = if resource_name.is_string() {
+        PathBuf::from(resource_name.to_rust_string_lossy(tc_scope))
+    } else {
+        let loader = tc_scope.get_slot::<Loader>().unwrap();
+        loader.eval_referrer.clone()
+    };
+
+    match import_module(tc_scope, &specifier, &referrer, import_type.as_deref()) {
+        Some(namespace) => {
+            resolver.resolve(tc_scope, namespace.into());
+        }
+        None => {
+            let exception = match tc_scope.exception() {
+                Some(exception) => exception,
+                None => {
+                    let message =
+                        format!("cannot import {specifier:?} from {}", referrer.display());
+                    let message = v8::String::new(tc_scope, &message).unwrap();
+                    v8::Exception::error(tc_scope, message)
+                }
+            };
+            resolver.reject(tc_scope, exception);
+        }
+    }
+
+    Some(promise)
+}
+
+// Returns a promise for the namespace of the module that `specifier` resolves
+// to from `referrer`, or `None` if it couldn't be loaded or instantiated.
+fn import_module<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    specifier: &str,
+    referrer: &Path,
+    import_type: Option<&str>,
+) -> Option<v8::Local<'a, v8::Promise>> {
+    let options = esm_options(scope, referrer);
+    let path = match resolver::resolve(specifier, referrer, &options) {
+        Ok(path) => path,
+        Err(e) => {
+            throw_error(scope, &e.to_string());
+            return None;
+        }
+    };
+    if let Err(message) = check_import_type(scope, &path, import_type) {
+        throw_type_error(scope, &message);
+        return None;
+    }
+
+    let module = match load_module(&path, scope) {
+        Ok(module) => module,
+        Err(e) => {
+            throw_loader_error(scope, &e);
+            return None;
+        }
+    };
+    if module.get_status() == v8::ModuleStatus::Uninstantiated {
+        module.instantiate_module(scope, module_resolve_callback)?;
+    }
+    let evaluation = module.evaluate(scope)?;
+    let evaluation = v8::Local::<v8::Promise>::try_from(evaluation).ok()?;
+
+    // Modules are only run once, importing one again gets a promise that
+    // settles along with the first evaluation.
+    let namespace = v8::Global::new(scope, module.get_module_namespace());
+    let namespace = v8::Local::new(scope, namespace);
+    let on_evaluated = v8::Function::builder(
+        |_: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue| {
+            rv.set(args.data());
+        },
+    )
+    .data(namespace)
+    .build(scope)?;
+    evaluation.then(scope, on_evaluated)
+}
+
+fn module_path(scope: &mut v8::HandleScope, module: v8::Local<v8::Module>) -> Option<PathBuf> {
+    let loader = scope.get_slot::<Loader>().unwrap();
+    let candidates = loader.paths.get(&module.get_identity_hash())?.clone();
+    candidates
+        .into_iter()
+        .find(|(handle, _)| v8::Local::new(scope, handle) == module)
+        .map(|(_, path)| path)
+}
+
+fn register_module(scope: &mut v8::HandleScope, path: &Path, module: v8::Local<v8::Module>) {
+    let handle = v8::Global::new(scope, module);
     let loader = scope.get_slot_mut::<Loader>().unwrap();
-    let full_path = Path::new(loader.cwds.last().unwrap())
-        .join(path)
-        .canonicalize()
-        .unwrap();
-
-    match loader
-        .handles
-        .get(&full_path.to_str().unwrap().to_string())
-        .cloned()
+    loader.handles.insert(path.to_path_buf(), handle.clone());
+    loader
+        .paths
+        .entry(module.get_identity_hash())
+        .or_default()
+        .push((handle, path.to_path_buf()));
+}
+
+fn unregister_module(scope: &mut v8::HandleScope, path: &Path) {
+    let loader = scope.get_slot_mut::<Loader>().unwrap();
+    loader.handles.remove(path);
+    loader.paths.retain(|_, modules| {
+        modules.retain(|(_, module_path)| module_path != path);
+        !modules.is_empty()
+    });
+}
+
+// Wraps the CommonJS module at `path` in a synthetic ES module, which runs it
+// when it's evaluated.  It exports `module.exports` as `default` along with
+// the names that `cjs_exports` finds in the module and the ones it re-exports.
+fn load_cjs_as_esm<'a>(
+    path: &Path,
+    scope: &mut v8::HandleScope<'a>,
+) -> Result<v8::Local<'a, v8::Module>, LoaderError> {
+    let mut names = vec![];
+    cjs_export_names(scope, path, &mut vec![], &mut names)?;
+    names.retain(|name| name != "default" && is_identifier(name));
+
+    let export_names = std::iter::once("default".to_string())
+        .chain(names.iter().cloned())
+        .collect();
+    Ok(create_synthetic_module(
+        scope,
+        path,
+        export_names,
+        SyntheticModule::CommonJs(names),
+    ))
+}
+
+// Adds the names that the CommonJS module at `path` exports to `names`,
+// following its re-exports unless they're in `visited`.
+fn cjs_export_names(
+    scope: &mut v8::HandleScope,
+    path: &Path,
+    visited: &mut Vec<PathBuf>,
+    names: &mut Vec<String>,
+) -> Result<(), LoaderError> {
+    visited.push(path.to_path_buf());
+    let source = read_source(scope, path)?;
+    let exports = cjs_exports::analyze(path, &source);
+    for name in exports.names {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let options = require_options(scope, path);
+    for specifier in exports.reexports {
+        // Re-exports that can't be found throw when the module runs.
+        let Ok(dependency) = resolver::resolve(&specifier, path, &options) else {
+            continue;
+        };
+        if visited.contains(&dependency) || format(scope, &dependency) != Format::CommonJs {
+            continue;
+        }
+        cjs_export_names(scope, &dependency, visited, names).ok();
+    }
+    Ok(())
+}
+
+// Parses the JSON file at `path` into a synthetic ES module with the value as
+// its `default` export.
+fn load_json_as_esm<'a>(
+    path: &Path,
+    scope: &mut v8::HandleScope<'a>,
+) -> Result<v8::Local<'a, v8::Module>, LoaderError> {
+    let value = load_json(scope, path)?;
+    let value = v8::Global::new(scope, value);
+    Ok(create_synthetic_module(
+        scope,
+        path,
+        vec!["default".to_string()],
+        SyntheticModule::Json(value),
+    ))
+}
+
+fn create_synthetic_module<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    path: &Path,
+    export_names: Vec<String>,
+    synthetic: SyntheticModule,
+) -> v8::Local<'a, v8::Module> {
+    let module_name = v8::String::new(scope, &path.to_string_lossy()).unwrap();
+    let export_names: Vec<_> = export_names
+        .iter()
+        .map(|name| v8::String::new(scope, name).unwrap())
+        .collect();
+    let module = v8::Module::create_synthetic_module(
+        scope,
+        module_name,
+        &export_names,
+        synthetic_evaluation_steps,
+    );
+
+    register_module(scope, path, module);
+    let loader = scope.get_slot_mut::<Loader>().unwrap();
+    loader
+        .synthetic_modules
+        .insert(path.to_path_buf(), synthetic);
+
+    module
+}
+
+fn synthetic_evaluation_steps<'a>(
+    context: v8::Local<'a, v8::Context>,
+    module: v8::Local<'a, v8::Module>,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let scope = &mut unsafe { v8::CallbackScope::new(context) };
+    let path = module_path(scope, module)?;
+
+    let loader = scope.get_slot::<Loader>().unwrap();
+    let exports = match loader.synthetic_modules.get(&path)? {
+        SyntheticModule::Json(value) => vec![("default".to_string(), value.clone())],
+        SyntheticModule::CommonJs(names) => {
+            let names = names.clone();
+            // Anything the module throws is left for `evaluate` to catch.
+            let exports = load_cjs_module(scope, &path)?;
+            let mut values = vec![("default".to_string(), v8::Global::new(scope, exports))];
+            let object = v8::Local::<v8::Object>::try_from(exports).ok();
+            for name in names {
+                let value = match object {
+                    // Getters can throw.
+                    Some(object) => get(scope, object, &name)?,
+                    None => v8::undefined(scope).into(),
+                };
+                values.push((name, v8::Global::new(scope, value)));
+            }
+            values
+        }
+    };
+    for (name, value) in exports {
+        let name = v8::String::new(scope, &name).unwrap();
+        let value = v8::Local::new(scope, value);
+        module.set_synthetic_module_export(scope, name, value)?;
+    }
+
+    let resolver = v8::PromiseResolver::new(scope)?;
+    let undefined = v8::undefined(scope);
+    resolver.resolve(scope, undefined.into());
+    Some(resolver.get_promise(scope).into())
+}
+
+// Reads and parses the JSON file at `path`.
+fn load_json<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    path: &Path,
+) -> Result<v8::Local<'a, v8::Value>, LoaderError> {
+    let source = read_source(scope, path)?;
+    // Node allows a byte order mark.
+    let source = source.strip_prefix('\u{feff}').unwrap_or(&source);
+    let json = v8::String::new(scope, source).unwrap();
+
+    let message = {
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        if let Some(value) = v8::json::parse(tc_scope, json) {
+            return Ok(value);
+        }
+        tc_scope
+            .exception()
+            .map(|exception| exception.to_rust_string_lossy(tc_scope))
+            .unwrap_or_default()
+    };
+    // V8 only reports where JSON is invalid in the message, as a UTF-16
+    // offset.
+    let (line, column) = json_error_position(&message)
+        .map(|offset| {
+            let position = SourceIndex::new(source).position_at(offset);
+            (position.line as usize, position.column as usize + 1)
+        })
+        .unwrap_or((1, 1));
+    Err(LoaderError::SyntaxError {
+        path: path.to_path_buf(),
+        line,
+        column,
+        message,
+    })
+}
+
+// Returns the `module.exports` of the CommonJS module at the canonical `path`,
+// running it first unless it's in the cache.
+fn load_cjs_module<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    path: &Path,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let loader = scope.get_slot::<Loader>().unwrap();
+    if let Some(module) = loader.cjs_modules.get(path).cloned() {
+        let module = v8::Local::new(scope, module);
+        return get(scope, module, "exports");
+    }
+
+    if format(scope, path) == Format::Json {
+        let exports = match load_json(scope, path) {
+            Ok(exports) => exports,
+            Err(e) => {
+                throw_loader_error(scope, &e);
+                return None;
+            }
+        };
+        let module = v8::Object::new(scope);
+        set(scope, module, "exports", exports);
+        let loaded = v8::Boolean::new(scope, true);
+        set(scope, module, "loaded", loaded.into());
+        let handle = v8::Global::new(scope, module);
+        let loader = scope.get_slot_mut::<Loader>().unwrap();
+        loader.cjs_modules.insert(path.to_path_buf(), handle);
+        return Some(exports);
+    }
+
+    let source = match read_source(scope, path)
+        .and_then(|source| transpile_typescript(scope, path, source))
     {
-        Some(handle) => {
-            eprintln!("found handle for {:#?}", full_path);
-            Some(v8::Local::new(scope, handle))
+        Ok(source) => source,
+        Err(e) => {
+            throw_loader_error(scope, &e);
+            return None;
         }
-        None => load_module(full_path.to_str().unwrap(), scope),
+    };
+
+    let filename = v8::String::new(scope, &path.to_string_lossy()).unwrap();
+    let dirname = path.parent().unwrap_or(path);
+    let dirname = v8::String::new(scope, &dirname.to_string_lossy()).unwrap();
+    let exports = v8::Object::new(scope);
+    let module = v8::Object::new(scope);
+    set(scope, module, "id", filename.into());
+    set(scope, module, "filename", filename.into());
+    set(scope, module, "exports", exports.into());
+    let loaded = v8::Boolean::new(scope, false);
+    set(scope, module, "loaded", loaded.into());
+
+    let handle = v8::Global::new(scope, module);
+    let loader = scope.get_slot_mut::<Loader>().unwrap();
+    loader.cjs_modules.insert(path.to_path_buf(), handle);
+
+    // Compiled as a function, like Node's wrapper, so that line and column
+    // numbers in stack traces match the file.
+    let source = v8::String::new(scope, strip_shebang(&source)).unwrap();
+    let source_map_url = v8::undefined(scope);
+    let script_origin = v8::ScriptOrigin::new(
+        scope,
+        filename.into(),
+        0,
+        0,
+        false,
+        0,
+        source_map_url.into(),
+        false,
+        false,
+        false,
+    );
+    let source = v8::script_compiler::Source::new(source, Some(&script_origin));
+    let parameters: Vec<_> = ["exports", "require", "module", "__filename", "__dirname"]
+        .iter()
+        .map(|name| v8::String::new(scope, name).unwrap())
+        .collect();
+    let function = v8::script_compiler::compile_function(
+        scope,
+        source,
+        &parameters,
+        &[],
+        v8::script_compiler::CompileOptions::NoCompileOptions,
+        v8::script_compiler::NoCacheReason::NoReason,
+    );
+
+    let require = require_function(scope, path);
+    let result = function.and_then(|function| {
+        function.call(
+            scope,
+            exports.into(),
+            &[
+                exports.into(),
+                require.into(),
+                module.into(),
+                filename.into(),
+                dirname.into(),
+            ],
+        )
+    });
+    if result.is_none() {
+        // So that requiring the module again runs it again, like Node.
+        let loader = scope.get_slot_mut::<Loader>().unwrap();
+        loader.cjs_modules.remove(path);
+        return None;
+    }
+
+    let loaded = v8::Boolean::new(scope, true);
+    set(scope, module, "loaded", loaded.into());
+    get(scope, module, "exports")
+}
+
+// A `require` function for the module at `referrer`.
+fn require_function<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    referrer: &Path,
+) -> v8::Local<'a, v8::Function> {
+    let referrer = v8::String::new(scope, &referrer.to_string_lossy()).unwrap();
+    v8::Function::builder(require_callback)
+        .data(referrer.into())
+        .build(scope)
+        .unwrap()
+}
+
+fn require_callback(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let referrer = args.data().to_rust_string_lossy(scope);
+    if !args.get(0).is_string() {
+        throw_error(scope, "the \"id\" argument must be a string");
+        return;
+    }
+    let specifier = args.get(0).to_rust_string_lossy(scope);
+    let referrer = Path::new(&referrer);
+
+    let options = require_options(scope, referrer);
+    let path = match resolver::resolve(&specifier, referrer, &options) {
+        Ok(path) => path,
+        Err(e) => {
+            throw_error(scope, &e.to_string());
+            return;
+        }
+    };
+    if format(scope, &path) == Format::Module {
+        throw_error(
+            scope,
+            &format!(
+                "require() of ES module {} from {} is not supported, use import instead",
+                path.display(),
+                referrer.display()
+            ),
+        );
+        return;
+    }
+
+    if let Some(exports) = load_cjs_module(scope, &path) {
+        rv.set(exports);
+    }
+}
+
+fn get<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    object: v8::Local<v8::Object>,
+    key: &str,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let key = v8::String::new(scope, key).unwrap();
+    object.get(scope, key.into())
+}
+
+fn set(
+    scope: &mut v8::HandleScope,
+    object: v8::Local<v8::Object>,
+    key: &str,
+    value: v8::Local<v8::Value>,
+) {
+    let key = v8::String::new(scope, key).unwrap();
+    object.set(scope, key.into(), value);
+}
+
+fn throw_error(scope: &mut v8::HandleScope, message: &str) {
+    let message = v8::String::new(scope, message).unwrap();
+    let exception = v8::Exception::error(scope, message);
+    scope.throw_exception(exception);
+}
+
+fn throw_loader_error(scope: &mut v8::HandleScope, error: &LoaderError) {
+    let message = v8::String::new(scope, &error.to_string()).unwrap();
+    let exception = match error {
+        LoaderError::SyntaxError { .. } => v8::Exception::syntax_error(scope, message),
+        _ => v8::Exception::error(scope, message),
+    };
+    scope.throw_exception(exception);
+}
+
+fn module_source(scope: &mut v8::HandleScope) -> Rc<dyn ModuleSource> {
+    scope.get_slot::<Loader>().unwrap().source.clone()
+}
+
+// Options for resolving imports in the module at `referrer`.
+fn esm_options(scope: &mut v8::HandleScope, referrer: &Path) -> resolver::Options {
+    resolver::Options {
+        source: module_source(scope),
+        typescript: transpile::is_typescript(referrer),
+        ..resolver::Options::esm()
+    }
+}
+
+// Options for resolving `require()` in the module at `referrer`, which can
+// leave out the extension of TypeScript modules too.
+fn require_options(scope: &mut v8::HandleScope, referrer: &Path) -> resolver::Options {
+    let mut options = resolver::Options {
+        source: module_source(scope),
+        typescript: transpile::is_typescript(referrer),
+        ..resolver::Options::require()
+    };
+    options
+        .extensions
+        .extend([".ts".to_string(), ".cts".to_string()]);
+    options
+}
+
+fn format(scope: &mut v8::HandleScope, path: &Path) -> Format {
+    resolver::format_in(&*module_source(scope), path)
+}
+
+fn read_source(scope: &mut v8::HandleScope, path: &Path) -> Result<String, LoaderError> {
+    module_source(scope)
+        .read_to_string(path)
+        .map_err(|e| LoaderError::NotFound {
+            specifier: path.display().to_string(),
+            referrer: None,
+            reason: e.to_string(),
+        })
+}
+
+// Strips the types from `source` if the module at `path` is TypeScript, see
+// `transpile`.
+fn transpile_typescript(
+    scope: &mut v8::HandleScope,
+    path: &Path,
+    source: String,
+) -> Result<String, LoaderError> {
+    if !transpile::is_typescript(path) {
+        return Ok(source);
     }
+    let output = transpile::transpile(path, source).map_err(|e| {
+        let diagnostic = match e {
+            parser::Error::Syntax(diagnostics) => diagnostics.into_iter().next(),
+            parser::Error::Unsupported(diagnostic) => Some(diagnostic),
+        };
+        let (line, column, message) = match diagnostic {
+            Some(diagnostic) => (
+                diagnostic.loc.start.line as usize,
+                diagnostic.loc.start.column as usize + 1,
+                diagnostic.message,
+            ),
+            None => (1, 1, "invalid TypeScript".to_string()),
+        };
+        LoaderError::SyntaxError {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    })?;
+    let loader = scope.get_slot_mut::<Loader>().unwrap();
+    loader
+        .source_maps
+        .insert(path.to_path_buf(), output.source_map);
+    Ok(output.code)
+}
+
+// The error that `tc_scope` caught while compiling the module at `path`.
+fn syntax_error(tc_scope: &mut v8::TryCatch<v8::HandleScope>, path: &Path) -> LoaderError {
+    let (line, column, message) = match tc_scope.message() {
+        Some(message) => (
+            message.get_line_number(tc_scope).unwrap_or(1),
+            message.get_start_column() + 1,
+            message.get(tc_scope).to_rust_string_lossy(tc_scope),
+        ),
+        None => (1, 1, "compilation failed".to_string()),
+    };
+    LoaderError::SyntaxError {
+        path: path.to_path_buf(),
+        line,
+        column,
+        message,
+    }
+}
+
+fn evaluation_error(
+    scope: &mut v8::HandleScope,
+    path: &Path,
+    exception: v8::Local<v8::Value>,
+) -> LoaderError {
+    let message = exception.to_rust_string_lossy(scope);
+    let stack = if exception.is_object() {
+        let object = exception.to_object(scope).unwrap();
+        get(scope, object, "stack")
+            .filter(|stack| stack.is_string())
+            .map(|stack| stack.to_rust_string_lossy(scope))
+    } else {
+        None
+    };
+    let stack = stack.map(|stack| {
+        let loader = scope.get_slot::<Loader>().unwrap();
+        loader
+            .source_maps
+            .iter()
+            .fold(stack, |stack, (path, source_map)| {
+                transpile::remap_stack(&stack, path, source_map)
+            })
+    });
+    LoaderError::EvaluationError {
+        path: path.to_path_buf(),
+        message,
+        stack,
+    }
+}
+
+// The offset in V8's messages for invalid JSON, e.g. "Unexpected token } in
+// JSON at position 12".
+fn json_error_position(message: &str) -> Option<u32> {
+    let (_, rest) = message.split_once(" at position ")?;
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+fn throw_type_error(scope: &mut v8::HandleScope, message: &str) {
+    let message = v8::String::new(scope, message).unwrap();
+    let exception = v8::Exception::type_error(scope, message);
+    scope.throw_exception(exception);
+}
+
+// Node ignores a `#!` line at the start of CommonJS modules.  It's replaced by
+// an empty line so that line numbers still match.
+fn strip_shebang(source: &str) -> &str {
+    match source.strip_prefix("#!") {
+        Some(rest) => &rest[rest.find('\n').unwrap_or(rest.len())..],
+        None => source,
+    }
+}
+
+// Whether `name` can be imported by name, e.g. `import { name } from`.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
 }
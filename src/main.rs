@@ -1,12 +1,26 @@
 use std::error;
+use std::path::Path;
+use std::rc::Rc;
 
-mod ast;
-mod loader;
-mod parser;
+use ast_store::module_source::{Disk, Layered};
+use ast_store::{code_path, lazy_ast, loader, parser, scope, visitor_keys};
+use serde::Serialize;
 
-use loader::load_esm_module;
+// The options that `lint` in `linter/linter.js` takes along with the AST.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LintOptions<'s, 'a> {
+    text: &'a str,
+    visitor_keys: serde_v8::Value<'s>,
+    scope_data: serde_v8::Value<'s>,
+    code_path_data: serde_v8::Value<'s>,
+}
 
 fn main() -> Result<(), Box<dyn error::Error>> {
+    // `import ... with { type: "json" }` is still behind a flag in this
+    // version of V8, flags have to be set before it's initialized.
+    v8::V8::set_flags_from_string("--harmony-import-attributes");
+
     // NOTE: the platform must only be initialized once
     let platform = v8::new_default_platform(0, false).make_shared();
     v8::V8::initialize_platform(platform);
@@ -38,24 +52,46 @@ fn main() -> Result<(), Box<dyn error::Error>> {
     let result = result.to_string(scope).unwrap();
     println!("result: {}", result.to_rust_string_lossy(scope));
 
-    load_esm_module();
+    // The built-in modules shadow the ones on disk, so the binary doesn't
+    // need to be run from the repo.
+    let builtin = ast_store::bundle!(
+        "/builtin",
+        [
+            "js/package.json",
+            "js/index.js",
+            "js/foo.js",
+            "js/bar.js",
+            "js/rules/foobar.js",
+        ]
+    );
+    let index = builtin.root().join("js/index.js");
+    let source = Layered::new(vec![Rc::new(builtin), Rc::new(Disk)]);
+    loader::init_with_source(scope, Path::new("."), Rc::new(source));
 
-    let code = "console.log(\"hello, \");\ndebugger;\nconsole.log(\"world!\");";
-    let ast = parser::parse(code.to_string());
+    let module = loader::import(scope, &index)?;
+    println!("exports: {:?}", module.export_names(scope));
+    let foobar: String = module.get_export(scope, "foobar")?;
+    println!("foobar: {foobar}");
 
-    // TODO: figure out how to call JS functions from Rust so that
-    // we don't have to set globals like this.
-    let global = context.global(scope);
-    let my_func_key = v8::String::new(scope, "ast").unwrap();
-    let v8_obj = serde_v8::to_v8(scope, &ast)?;
-    global.set(scope, my_func_key.into(), v8_obj);
-
-    let code = v8::String::new(scope, "JSON.stringify(ast, null, 2)").unwrap();
+    let code = "console.log(\"hello, \");\ndebugger;\nconsole.log(\"world!\");";
+    let ast = parser::parse(code.to_string())?;
+    let scope_manager = scope::analyze(&ast, &Default::default());
+    let code_paths = code_path::analyze(&ast);
 
-    let script = v8::Script::compile(scope, code, None).unwrap();
-    let result = script.run(scope).unwrap();
-    let result = result.to_string(scope).unwrap();
-    println!("result: {}", result.to_rust_string_lossy(scope));
+    // The linter needs ESLint from `node_modules`, see `package.json`.
+    let linter = Path::new(env!("CARGO_MANIFEST_DIR")).join("linter/linter.js");
+    let linter = loader::import(scope, &linter)?;
+    let options = LintOptions {
+        text: code,
+        visitor_keys: visitor_keys::to_v8(scope)?.into(),
+        scope_data: scope::to_v8(scope, &scope_manager)?.into(),
+        code_path_data: code_path::to_v8(scope, &code_paths)?.into(),
+    };
+    let ast: serde_v8::Value = lazy_ast::to_v8(scope, ast).into();
+    let problems: Vec<String> = linter.call_export(scope, "lint", (ast, options))?;
+    for problem in problems {
+        println!("{problem}");
+    }
 
     Ok(())
 }
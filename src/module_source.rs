@@ -0,0 +1,223 @@
+// Where the loader and resolver read modules and package.json files from.
+// `Disk` is the real filesystem, `MemorySource` and `Bundle` are virtual ones
+// whose paths must be absolute, e.g. for JS that's embedded in the binary
+// with `bundle!`.  `Layered` combines them so that built-in modules can be
+// used alongside ones on disk.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+pub trait ModuleSource: fmt::Debug {
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    fn is_file(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    // The absolute path of `path` with `.` and `..` resolved, or an error if
+    // it doesn't exist.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Disk;
+
+impl ModuleSource for Disk {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+}
+
+// Files keyed by absolute path, directories are implied by the files in them.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        let path = path.into();
+        let path = normalize(&path).unwrap_or(path);
+        self.files.insert(path, source.into());
+    }
+}
+
+impl ModuleSource for MemorySource {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        normalize(path)
+            .and_then(|path| self.files.get(&path))
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        normalize(path).is_some_and(|path| self.files.contains_key(&path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        normalize(path).is_some_and(|dir| {
+            self.files
+                .keys()
+                .any(|file| file != &dir && file.starts_with(&dir))
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        canonicalize(self, path)
+    }
+}
+
+// Files embedded in the binary, under `root`.  Created with `bundle!`.
+#[derive(Debug, Clone, Copy)]
+pub struct Bundle {
+    root: &'static str,
+    // Paths relative to `root` and their contents.
+    files: &'static [(&'static str, &'static str)],
+}
+
+impl Bundle {
+    pub const fn new(root: &'static str, files: &'static [(&'static str, &'static str)]) -> Self {
+        Bundle { root, files }
+    }
+
+    pub fn root(&self) -> &Path {
+        Path::new(self.root)
+    }
+
+    fn files(&self) -> impl Iterator<Item = (PathBuf, &'static str)> + '_ {
+        self.files
+            .iter()
+            .map(|(path, source)| (self.root().join(path), *source))
+    }
+}
+
+impl ModuleSource for Bundle {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let normalized = normalize(path).ok_or_else(|| not_found(path))?;
+        self.files()
+            .find(|(file, _)| file == &normalized)
+            .map(|(_, source)| source.to_string())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        normalize(path).is_some_and(|path| self.files().any(|(file, _)| file == path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        normalize(path).is_some_and(|dir| {
+            self.files()
+                .any(|(file, _)| file != dir && file.starts_with(&dir))
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        canonicalize(self, path)
+    }
+}
+
+// Embeds files from the crate's directory in the binary as a `Bundle` rooted
+// at `$root`, e.g. `bundle!("/builtin", ["js/index.js", "js/package.json"])`
+// makes `js/index.js` available as `/builtin/js/index.js`.
+#[macro_export]
+macro_rules! bundle {
+    ($root:expr, [$($path:literal),* $(,)?]) => {
+        $crate::module_source::Bundle::new(
+            $root,
+            &[$((
+                $path,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+            )),*],
+        )
+    };
+}
+
+// Tries each source in order, so earlier ones shadow later ones.
+#[derive(Debug, Clone, Default)]
+pub struct Layered {
+    layers: Vec<Rc<dyn ModuleSource>>,
+}
+
+impl Layered {
+    pub fn new(layers: Vec<Rc<dyn ModuleSource>>) -> Self {
+        Layered { layers }
+    }
+}
+
+impl ModuleSource for Layered {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.layers
+            .iter()
+            .find(|layer| layer.is_file(path))
+            .ok_or_else(|| not_found(path))?
+            .read_to_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.layers.iter().any(|layer| layer.is_file(path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.layers.iter().any(|layer| layer.is_dir(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.layers
+            .iter()
+            .find_map(|layer| layer.canonicalize(path).ok())
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+// Virtual sources don't have symlinks so canonicalizing is only normalizing.
+fn canonicalize(source: &dyn ModuleSource, path: &Path) -> io::Result<PathBuf> {
+    match normalize(path) {
+        Some(normalized) if source.exists(&normalized) => Ok(normalized),
+        _ => Err(not_found(path)),
+    }
+}
+
+// Resolves `.` and `..` in the absolute `path`.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} doesn't exist", path.display()),
+    )
+}
@@ -1,6 +1,9 @@
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 use swc_atoms::JsWord;
-use swc_common::{comments::SingleThreadedComments, FileName, SourceMap};
+use swc_common::{
+    comments::{CommentKind, SingleThreadedComments},
+    FileName, SourceMap, Spanned,
+};
 use swc_ecma_ast::*;
 use swc_ecma_parser::{
     lexer::Lexer, token::TokenAndSpan, Capturing, Parser, StringInput, Syntax, TsConfig,
@@ -8,316 +11,619 @@ use swc_ecma_parser::{
 
 use crate::ast;
 
-pub fn parse(code: String) -> ast::Program {
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub range: ast::Range,
+    pub loc: ast::Location,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // The code isn't valid JavaScript or TypeScript.
+    Syntax(Vec<Diagnostic>),
+    // The code is valid, but uses syntax that can't be converted yet.
+    Unsupported(Diagnostic),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ast::Position { line, column } = self.loc.start;
+        write!(f, "{line}:{column}: {}", self.message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Error::Unsupported(diagnostic) => write!(f, "{diagnostic}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+pub fn parse(code: String) -> Result<ast::Program, Error> {
+    parse_with(code, |module, tokens, comments, index| {
+        let value = |value: JsWord, span: &swc_common::Span| ast::TokenValue {
+            loc: index.loc(span),
+            range: index.range(span),
+            value: value.to_string(),
+        };
+        let tokens = convert_tokens(tokens, index, |kind, text, span| {
+            ast::Token::new(kind, value(text, span))
+        })?;
+        let comments = convert_comments(comments, |kind, text, span| {
+            ast::Comment::new(kind, value(text, span))
+        });
+        Ok(ast::Program {
+            r#type: "Program".to_string(),
+            loc: index.loc(&module.span),
+            range: index.range(&module.span),
+            source_type: ast::SourceType::Module,
+            body: module
+                .body
+                .iter()
+                .map(|item| convert_module_item(item, index))
+                .collect::<Result<_, _>>()?,
+            tokens,
+            comments,
+        })
+    })
+}
+
+// Parses `code` with swc and passes the result, along with the tokens and
+// comments swc collected, to `convert`.
+pub(crate) fn parse_with<T>(
+    code: String,
+    convert: impl FnOnce(
+        &Module,
+        &[TokenAndSpan],
+        &SingleThreadedComments,
+        &SourceIndex,
+    ) -> Result<T, Error>,
+) -> Result<T, Error> {
     let comments = SingleThreadedComments::default();
     let cm = Arc::<SourceMap>::default();
     let fm = cm.new_source_file(FileName::Anon, code);
 
-    let lexer = Lexer::new(
-        Syntax::Typescript(TsConfig {
-            tsx: false,
-            dts: true,
-            decorators: false,
-            no_early_errors: false,
-            disallow_ambiguous_jsx_like: false,
-        }),
-        Default::default(),
-        StringInput::from(&*fm),
-        Some(&comments),
-    );
+    let (syntax, target) = options();
+    let lexer = Lexer::new(syntax, target, StringInput::from(&*fm), Some(&comments));
     let capturing = Capturing::new(lexer);
     let mut parser = Parser::new_from(capturing);
 
-    let ast = parser.parse_module().expect("Failed to parse module.");
+    let index = SourceIndex::new(&fm.src);
+
+    let result = parser.parse_module();
+    let mut errors: Vec<_> = parser
+        .take_errors()
+        .iter()
+        .map(|error| index.diagnostic(error.kind().msg().to_string(), &error.span()))
+        .collect();
+    let ast = match result {
+        Ok(ast) if errors.is_empty() => ast,
+        Ok(_) => return Err(Error::Syntax(errors)),
+        Err(error) => {
+            errors.push(index.diagnostic(error.kind().msg().to_string(), &error.span()));
+            return Err(Error::Syntax(errors));
+        }
+    };
 
-    convert_module(&ast, &parser.input().take())
+    let tokens = parser.input().take();
+    convert(&ast, &tokens, &comments, &index)
 }
 
-fn convert_span(span: &swc_common::Span) -> ast::Range {
-    (span.lo.0 - 1, span.hi.0 - 1)
+// The options every file is parsed with.  `store` includes these in its cache
+// keys so changing them invalidates cached programs.
+pub(crate) fn options() -> (Syntax, EsVersion) {
+    let syntax = Syntax::Typescript(TsConfig {
+        tsx: false,
+        // Files are parsed as code rather than as declaration files.  swc's
+        // declaration mode also asserts that it never reparses an expression
+        // as a pattern, which any assignment does, so `a = 1` panicked.
+        dts: false,
+        decorators: false,
+        no_early_errors: false,
+        disallow_ambiguous_jsx_like: false,
+    });
+    (syntax, EsVersion::default())
 }
 
-// TODO: convert span to location
-fn dummy_loc() -> ast::Location {
-    ast::Location {
-        start: ast::Position { line: 1, column: 0 },
-        end: ast::Position { line: 1, column: 0 },
-    }
+// Maps swc's byte positions to ESTree ranges and locations, and ESTree's
+// UTF-16 offsets back to byte offsets and locations.  ESTree consumers index
+// into the source using JS strings so ranges and columns are counted in UTF-16
+// code units.
+#[derive(Debug)]
+pub(crate) struct SourceIndex<'a> {
+    code: &'a str,
+    // (byte offset, UTF-16 offset) of the start of each line.
+    line_starts: Vec<(usize, u32)>,
+    // Length of `code` in UTF-16 code units.
+    len_utf16: u32,
 }
 
-fn convert_module(ast: &Module, tokens: &[TokenAndSpan]) -> ast::Program {
-    for token in tokens {
-        eprintln!("token: {:?}", token);
+impl<'a> SourceIndex<'a> {
+    pub(crate) fn new(code: &'a str) -> Self {
+        let mut line_starts = vec![(0, 0)];
+        let mut offset = 0;
+        let mut chars = code.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            offset += c.len_utf16() as u32;
+            let is_line_break = match c {
+                '\r' => chars.peek().map(|(_, next)| *next) != Some('\n'),
+                '\n' | '\u{2028}' | '\u{2029}' => true,
+                _ => false,
+            };
+            if is_line_break {
+                line_starts.push((i + c.len_utf8(), offset));
+            }
+        }
+
+        SourceIndex {
+            code,
+            line_starts,
+            len_utf16: offset,
+        }
+    }
+
+    pub(crate) fn code(&self) -> &'a str {
+        self.code
+    }
+
+    pub(crate) fn len_utf16(&self) -> u32 {
+        self.len_utf16
+    }
+
+    // (byte offset, UTF-16 offset) of the start of each line.
+    pub(crate) fn line_starts(&self) -> &[(usize, u32)] {
+        &self.line_starts
+    }
+
+    // Converts a UTF-16 offset into a byte offset, offsets past the end or in
+    // the middle of a character are moved back to the nearest character
+    // boundary.
+    pub(crate) fn byte_offset(&self, offset: u32) -> usize {
+        let line = self
+            .line_starts
+            .partition_point(|(_, start)| *start <= offset)
+            - 1;
+        let (line_start, mut utf16) = self.line_starts[line];
+        for (i, c) in self.code[line_start..].char_indices() {
+            utf16 += c.len_utf16() as u32;
+            if utf16 > offset {
+                return line_start + i;
+            }
+        }
+        self.code.len()
+    }
+
+    // The line and column of a UTF-16 offset.
+    pub(crate) fn position_at(&self, offset: u32) -> ast::Position {
+        let line = self
+            .line_starts
+            .partition_point(|(_, start)| *start <= offset);
+        ast::Position {
+            line: line as u32,
+            column: offset - self.line_starts[line - 1].1,
+        }
+    }
+
+    pub(crate) fn loc_at(&self, range: ast::Range) -> ast::Location {
+        ast::Location {
+            start: self.position_at(range.0),
+            end: self.position_at(range.1),
+        }
+    }
+
+    fn position(&self, pos: swc_common::BytePos) -> (u32, ast::Position) {
+        // swc's byte positions start at 1
+        let pos = (pos.0 as usize).saturating_sub(1).min(self.code.len());
+        let line = self.line_starts.partition_point(|(start, _)| *start <= pos) - 1;
+        let (line_start, line_offset) = self.line_starts[line];
+        let column = self
+            .code
+            .get(line_start..pos)
+            .unwrap_or_default()
+            .chars()
+            .map(|c| c.len_utf16() as u32)
+            .sum();
+
+        (
+            line_offset + column,
+            ast::Position {
+                line: line as u32 + 1,
+                column,
+            },
+        )
+    }
+
+    pub(crate) fn range(&self, span: &swc_common::Span) -> ast::Range {
+        (self.position(span.lo).0, self.position(span.hi).0)
+    }
+
+    pub(crate) fn loc(&self, span: &swc_common::Span) -> ast::Location {
+        ast::Location {
+            start: self.position(span.lo).1,
+            end: self.position(span.hi).1,
+        }
+    }
+
+    pub(crate) fn diagnostic(&self, message: String, span: &swc_common::Span) -> Diagnostic {
+        Diagnostic {
+            message,
+            range: self.range(span),
+            loc: self.loc(span),
+        }
+    }
+
+    pub(crate) fn unsupported(&self, kind: &str, node: &impl Spanned) -> Error {
+        Error::Unsupported(self.diagnostic(format!("unsupported syntax: {kind}"), &node.span()))
+    }
+
+    fn unsupported_token(
+        &self,
+        token: &swc_ecma_parser::token::Token,
+        span: &swc_common::Span,
+    ) -> Error {
+        Error::Unsupported(self.diagnostic(format!("unsupported token: {token:?}"), span))
     }
+}
 
-    let tokens: Vec<ast::Token> = tokens
+// Converts swc's tokens to the ones ESTree has, `make` builds each of them from
+// its kind, value and span.
+pub(crate) fn convert_tokens<T>(
+    tokens: &[TokenAndSpan],
+    index: &SourceIndex,
+    mut make: impl FnMut(ast::TokenKind, JsWord, &swc_common::Span) -> T,
+) -> Result<Vec<T>, Error> {
+    tokens
         .iter()
         .map(
             |TokenAndSpan {
                  token,
                  span,
                  had_line_break: _,
-             }| match &token {
-                swc_ecma_parser::token::Token::Word(word) => match word {
-                    swc_ecma_parser::token::Word::Keyword(_) => {
-                        ast::Token::Keyword(ast::TokenValue {
-                            loc: dummy_loc(),
-                            range: convert_span(span),
-                            value: JsWord::from(word.clone()).to_string(),
-                        })
-                    }
-                    swc_ecma_parser::token::Word::Null => todo!(),
-                    swc_ecma_parser::token::Word::True => todo!(),
-                    swc_ecma_parser::token::Word::False => todo!(),
-                    swc_ecma_parser::token::Word::Ident(ident) => {
-                        ast::Token::Identifier(ast::TokenValue {
-                            loc: dummy_loc(),
-                            range: convert_span(span),
-                            value: ident.to_string(),
-                        })
-                    }
-                },
-                swc_ecma_parser::token::Token::Arrow => todo!(),
-                swc_ecma_parser::token::Token::Hash => todo!(),
-                swc_ecma_parser::token::Token::At => todo!(),
-                swc_ecma_parser::token::Token::Dot => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: ".".to_string(),
-                }),
-                swc_ecma_parser::token::Token::DotDotDot => todo!(),
-                swc_ecma_parser::token::Token::Bang => todo!(),
-                swc_ecma_parser::token::Token::LParen => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: "(".to_string(),
-                }),
-                swc_ecma_parser::token::Token::RParen => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: ")".to_string(),
-                }),
-                swc_ecma_parser::token::Token::LBracket => {
-                    ast::Token::Punctuator(ast::TokenValue {
-                        loc: dummy_loc(),
-                        range: convert_span(span),
-                        value: "[".to_string(),
-                    })
-                }
-                swc_ecma_parser::token::Token::RBracket => {
-                    ast::Token::Punctuator(ast::TokenValue {
-                        loc: dummy_loc(),
-                        range: convert_span(span),
-                        value: "]".to_string(),
-                    })
-                }
-                swc_ecma_parser::token::Token::LBrace => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: "{".to_string(),
-                }),
-                swc_ecma_parser::token::Token::RBrace => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: "}".to_string(),
-                }),
-                swc_ecma_parser::token::Token::Semi => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: ";".to_string(),
-                }),
-                swc_ecma_parser::token::Token::Comma => ast::Token::Punctuator(ast::TokenValue {
-                    loc: dummy_loc(),
-                    range: convert_span(span),
-                    value: ",".to_string(),
-                }),
-                swc_ecma_parser::token::Token::BackQuote => todo!(),
-                swc_ecma_parser::token::Token::Template { raw: _, cooked: _ } => todo!(),
-                swc_ecma_parser::token::Token::Colon => todo!(),
-                swc_ecma_parser::token::Token::BinOp(_) => todo!(),
-                swc_ecma_parser::token::Token::AssignOp(_) => todo!(),
-                swc_ecma_parser::token::Token::DollarLBrace => todo!(),
-                swc_ecma_parser::token::Token::QuestionMark => todo!(),
-                swc_ecma_parser::token::Token::PlusPlus => todo!(),
-                swc_ecma_parser::token::Token::MinusMinus => todo!(),
-                swc_ecma_parser::token::Token::Tilde => todo!(),
-                swc_ecma_parser::token::Token::Str { value: _, raw } => {
-                    ast::Token::String(ast::TokenValue {
-                        loc: dummy_loc(),
-                        range: convert_span(span),
-                        value: raw.to_string(),
-                    })
-                }
-                swc_ecma_parser::token::Token::Regex(_, _) => todo!(),
-                swc_ecma_parser::token::Token::Num { value: _, raw: _ } => todo!(),
-                swc_ecma_parser::token::Token::BigInt { value: _, raw: _ } => todo!(),
-                swc_ecma_parser::token::Token::JSXName { name: _ } => todo!(),
-                swc_ecma_parser::token::Token::JSXText { raw: _ } => todo!(),
-                swc_ecma_parser::token::Token::JSXTagStart => todo!(),
-                swc_ecma_parser::token::Token::JSXTagEnd => todo!(),
-                swc_ecma_parser::token::Token::Shebang(_) => todo!(),
-                swc_ecma_parser::token::Token::Error(_) => todo!(),
+             }| {
+                let (kind, value) = match &token {
+                    swc_ecma_parser::token::Token::Word(word) => match word {
+                        swc_ecma_parser::token::Word::Keyword(_) => {
+                            (ast::TokenKind::Keyword, JsWord::from(word.clone()))
+                        }
+                        swc_ecma_parser::token::Word::Null => {
+                            return Err(index.unsupported_token(token, span))
+                        }
+                        swc_ecma_parser::token::Word::True => {
+                            return Err(index.unsupported_token(token, span))
+                        }
+                        swc_ecma_parser::token::Word::False => {
+                            return Err(index.unsupported_token(token, span))
+                        }
+                        swc_ecma_parser::token::Word::Ident(ident) => {
+                            (ast::TokenKind::Identifier, ident.clone())
+                        }
+                    },
+                    swc_ecma_parser::token::Token::Arrow => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Hash => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::At => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Dot => {
+                        (ast::TokenKind::Punctuator, JsWord::from("."))
+                    }
+                    swc_ecma_parser::token::Token::DotDotDot => {
+                        (ast::TokenKind::Punctuator, JsWord::from("..."))
+                    }
+                    swc_ecma_parser::token::Token::Bang => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::LParen => {
+                        (ast::TokenKind::Punctuator, JsWord::from("("))
+                    }
+                    swc_ecma_parser::token::Token::RParen => {
+                        (ast::TokenKind::Punctuator, JsWord::from(")"))
+                    }
+                    swc_ecma_parser::token::Token::LBracket => {
+                        (ast::TokenKind::Punctuator, JsWord::from("["))
+                    }
+                    swc_ecma_parser::token::Token::RBracket => {
+                        (ast::TokenKind::Punctuator, JsWord::from("]"))
+                    }
+                    swc_ecma_parser::token::Token::LBrace => {
+                        (ast::TokenKind::Punctuator, JsWord::from("{"))
+                    }
+                    swc_ecma_parser::token::Token::RBrace => {
+                        (ast::TokenKind::Punctuator, JsWord::from("}"))
+                    }
+                    swc_ecma_parser::token::Token::Semi => {
+                        (ast::TokenKind::Punctuator, JsWord::from(";"))
+                    }
+                    swc_ecma_parser::token::Token::Comma => {
+                        (ast::TokenKind::Punctuator, JsWord::from(","))
+                    }
+                    swc_ecma_parser::token::Token::BackQuote => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Template { raw: _, cooked: _ } => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Colon => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::BinOp(_) => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::AssignOp(_) => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::DollarLBrace => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::QuestionMark => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::PlusPlus => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::MinusMinus => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Tilde => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Str { value: _, raw } => {
+                        (ast::TokenKind::String, JsWord::from(&**raw))
+                    }
+                    swc_ecma_parser::token::Token::Regex(_, _) => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Num { value: _, raw: _ } => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::BigInt { value: _, raw: _ } => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::JSXName { name: _ } => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::JSXText { raw: _ } => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::JSXTagStart => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::JSXTagEnd => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Shebang(_) => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                    swc_ecma_parser::token::Token::Error(_) => {
+                        return Err(index.unsupported_token(token, span))
+                    }
+                };
+                Ok(make(kind, value, span))
             },
         )
+        .collect()
+}
+
+// Converts the comments swc collected, in source order, with `make`
+// building each of them from its kind, text and span.
+pub(crate) fn convert_comments<T>(
+    comments: &SingleThreadedComments,
+    mut make: impl FnMut(ast::CommentKind, JsWord, &swc_common::Span) -> T,
+) -> Vec<T> {
+    let (leading, trailing) = comments.borrow_all();
+    let mut comments: Vec<_> = leading
+        .values()
+        .chain(trailing.values())
+        .flatten()
         .collect();
+    comments.sort_by_key(|comment| comment.span.lo);
+    comments.dedup_by_key(|comment| comment.span.lo);
 
-    ast::Program {
-        r#type: "Program".to_string(),
-        loc: dummy_loc(),
-        range: convert_span(&ast.span),
-        body: ast.body.iter().map(convert_module_item).collect(),
-        tokens,
-        comments: vec![],
-    }
+    comments
+        .iter()
+        .map(|comment| {
+            let kind = match comment.kind {
+                CommentKind::Line => ast::CommentKind::Line,
+                CommentKind::Block => ast::CommentKind::Block,
+            };
+            make(kind, JsWord::from(&*comment.text), &comment.span)
+        })
+        .collect()
 }
 
-fn convert_module_item(item: &ModuleItem) -> ast::Statement {
+fn convert_module_item(item: &ModuleItem, index: &SourceIndex) -> Result<ast::Statement, Error> {
     match item {
-        ModuleItem::ModuleDecl(_) => todo!(),
-        ModuleItem::Stmt(stmt) => convert_statement(stmt),
+        ModuleItem::ModuleDecl(_) => Err(index.unsupported("import or export declaration", item)),
+        ModuleItem::Stmt(stmt) => convert_statement(stmt, index),
     }
 }
 
-fn convert_statement(stmt: &Stmt) -> ast::Statement {
-    match stmt {
-        Stmt::Block(_) => todo!(),
-        Stmt::Empty(_) => todo!(),
+fn convert_statement(stmt: &Stmt, index: &SourceIndex) -> Result<ast::Statement, Error> {
+    Ok(match stmt {
         Stmt::Debugger(DebuggerStmt { span }) => {
             ast::Statement::DebuggerStatement(ast::DebuggerStatement {
-                loc: dummy_loc(),
-                range: convert_span(span),
+                loc: index.loc(span),
+                range: index.range(span),
             })
         }
-        Stmt::With(_) => todo!(),
-        Stmt::Return(_) => todo!(),
-        Stmt::Labeled(_) => todo!(),
-        Stmt::Break(_) => todo!(),
-        Stmt::Continue(_) => todo!(),
-        Stmt::If(_) => todo!(),
-        Stmt::Switch(_) => todo!(),
-        Stmt::Throw(_) => todo!(),
-        Stmt::Try(_) => todo!(),
-        Stmt::While(_) => todo!(),
-        Stmt::DoWhile(_) => todo!(),
-        Stmt::For(_) => todo!(),
-        Stmt::ForIn(_) => todo!(),
-        Stmt::ForOf(_) => todo!(),
-        Stmt::Decl(_) => todo!(),
         Stmt::Expr(ExprStmt { span, expr }) => {
             ast::Statement::ExpressionStatement(ast::ExpressionStatement {
-                loc: dummy_loc(),
-                range: convert_span(span),
-                expression: Box::new(convert_expression(expr)),
+                loc: index.loc(span),
+                range: index.range(span),
+                expression: Box::new(convert_expression(expr, index)?),
             })
         }
-    }
+        _ => return Err(unsupported_statement(stmt, index)),
+    })
 }
 
-fn convert_expression(expr: &Expr) -> ast::Expression {
-    match expr {
-        Expr::This(_) => todo!(),
-        Expr::Array(_) => todo!(),
-        Expr::Object(_) => todo!(),
-        Expr::Fn(_) => todo!(),
-        Expr::Unary(_) => todo!(),
-        Expr::Update(_) => todo!(),
-        Expr::Bin(_) => todo!(),
-        Expr::Assign(_) => todo!(),
+pub(crate) fn unsupported_statement(stmt: &Stmt, index: &SourceIndex) -> Error {
+    let kind = match stmt {
+        Stmt::Block(_) => "BlockStatement",
+        Stmt::Empty(_) => "EmptyStatement",
+        Stmt::With(_) => "WithStatement",
+        Stmt::Return(_) => "ReturnStatement",
+        Stmt::Labeled(_) => "LabeledStatement",
+        Stmt::Break(_) => "BreakStatement",
+        Stmt::Continue(_) => "ContinueStatement",
+        Stmt::If(_) => "IfStatement",
+        Stmt::Switch(_) => "SwitchStatement",
+        Stmt::Throw(_) => "ThrowStatement",
+        Stmt::Try(_) => "TryStatement",
+        Stmt::While(_) => "WhileStatement",
+        Stmt::DoWhile(_) => "DoWhileStatement",
+        Stmt::For(_) => "ForStatement",
+        Stmt::ForIn(_) => "ForInStatement",
+        Stmt::ForOf(_) => "ForOfStatement",
+        Stmt::Decl(_) => "declaration",
+        // These are supported, see `convert_statement`.
+        Stmt::Debugger(_) | Stmt::Expr(_) => "statement",
+    };
+    index.unsupported(kind, stmt)
+}
+
+fn convert_expression(expr: &Expr, index: &SourceIndex) -> Result<ast::Expression, Error> {
+    Ok(match expr {
         Expr::Member(MemberExpr { span, obj, prop }) => {
             ast::Expression::MemberExpression(ast::MemberExpression {
-                loc: dummy_loc(),
-                range: convert_span(span),
-                object: Box::new(convert_expression(obj)),
-                property: match prop {
-                    MemberProp::Ident(ident) => {
-                        Box::new(ast::Prop::Identifier(convert_ident(ident)))
-                    }
-                    MemberProp::PrivateName(_) => todo!(),
-                    MemberProp::Computed(_) => todo!(),
-                },
+                loc: index.loc(span),
+                range: index.range(span),
+                object: Box::new(convert_expression(obj, index)?),
+                property: Box::new(ast::Prop::Identifier(convert_ident(
+                    member_prop_ident(prop, index)?,
+                    index,
+                ))),
                 computed: false, // TODO
+                optional: false,
             })
         }
-        Expr::SuperProp(_) => todo!(),
-        Expr::Cond(_) => todo!(),
         Expr::Call(CallExpr {
             span,
             callee: Callee::Expr(callee),
             args,
             type_args: _, // TODO
         }) => ast::Expression::CallExpression(ast::CallExpression {
-            loc: dummy_loc(),
-            range: convert_span(span),
-            callee: Box::new(convert_expression(callee)),
+            loc: index.loc(span),
+            range: index.range(span),
+            callee: Box::new(convert_expression(callee, index)?),
             arguments: args
                 .iter()
                 .map(|arg| match arg {
                     ExprOrSpread { spread: None, expr } => {
-                        ast::ExprOrSpread::Expr(convert_expression(expr))
+                        Ok(ast::ExprOrSpread::Expr(convert_expression(expr, index)?))
                     }
                     ExprOrSpread {
-                        spread: Some(span),
+                        spread: Some(spread),
                         expr,
-                    } => ast::ExprOrSpread::Spread(ast::SpreadElement {
-                        loc: dummy_loc(),
-                        range: convert_span(span),
-                        argument: Box::new(convert_expression(expr)),
-                    }),
+                    } => {
+                        // swc's span only covers the `...`.
+                        let span = spread.with_hi(expr.span().hi);
+                        Ok(ast::ExprOrSpread::Spread(ast::SpreadElement {
+                            r#type: "SpreadElement".to_string(),
+                            loc: index.loc(&span),
+                            range: index.range(&span),
+                            argument: Box::new(convert_expression(expr, index)?),
+                        }))
+                    }
                 })
-                .collect(),
+                .collect::<Result<_, _>>()?,
+            optional: false,
         }),
-        Expr::Call(_) => todo!(), // handle `super()` and `import()`
-        Expr::New(_) => todo!(),
-        Expr::Seq(_) => todo!(),
-        Expr::Ident(ident) => ast::Expression::Identifier(convert_ident(ident)),
+        Expr::Ident(ident) => ast::Expression::Identifier(convert_ident(ident, index)),
+        Expr::Lit(Lit::Str(Str { span, value, raw })) => ast::Expression::Literal(ast::Literal {
+            loc: index.loc(span),
+            range: index.range(span),
+            value: ast::Value::String(value.to_string()),
+            raw: raw.as_ref().map(|s| s.to_string()),
+        }),
+        _ => return Err(unsupported_expression(expr, index)),
+    })
+}
+
+// Only identifier properties are supported so far.
+pub(crate) fn member_prop_ident<'a>(
+    prop: &'a MemberProp,
+    index: &SourceIndex,
+) -> Result<&'a Ident, Error> {
+    match prop {
+        MemberProp::Ident(ident) => Ok(ident),
+        MemberProp::PrivateName(name) => Err(index.unsupported("PrivateIdentifier", name)),
+        MemberProp::Computed(prop) => Err(index.unsupported("computed member property", prop)),
+    }
+}
+
+pub(crate) fn unsupported_expression(expr: &Expr, index: &SourceIndex) -> Error {
+    let kind = match expr {
+        Expr::This(_) => "ThisExpression",
+        Expr::Array(_) => "ArrayExpression",
+        Expr::Object(_) => "ObjectExpression",
+        Expr::Fn(_) => "FunctionExpression",
+        Expr::Unary(_) => "UnaryExpression",
+        Expr::Update(_) => "UpdateExpression",
+        Expr::Bin(_) => "BinaryExpression",
+        Expr::Assign(_) => "AssignmentExpression",
+        Expr::SuperProp(_) => "super property",
+        Expr::Cond(_) => "ConditionalExpression",
+        Expr::Call(_) => "super() or import() call",
+        Expr::New(_) => "NewExpression",
+        Expr::Seq(_) => "SequenceExpression",
+        Expr::Tpl(_) => "TemplateLiteral",
+        Expr::TaggedTpl(_) => "TaggedTemplateExpression",
+        Expr::Arrow(_) => "ArrowFunctionExpression",
+        Expr::Class(_) => "ClassExpression",
+        Expr::Yield(_) => "YieldExpression",
+        Expr::MetaProp(_) => "MetaProperty",
+        Expr::Await(_) => "AwaitExpression",
+        Expr::Paren(_) => "parenthesized expression",
+        Expr::JSXMember(_) => "JSXMemberExpression",
+        Expr::JSXNamespacedName(_) => "JSXNamespacedName",
+        Expr::JSXEmpty(_) => "JSXEmptyExpression",
+        Expr::JSXElement(_) => "JSXElement",
+        Expr::JSXFragment(_) => "JSXFragment",
+        Expr::TsTypeAssertion(_) => "TSTypeAssertion",
+        Expr::TsConstAssertion(_) => "const assertion",
+        Expr::TsNonNull(_) => "TSNonNullExpression",
+        Expr::TsAs(_) => "TSAsExpression",
+        Expr::TsInstantiation(_) => "TSInstantiationExpression",
+        Expr::TsSatisfies(_) => "TSSatisfiesExpression",
+        Expr::PrivateName(_) => "PrivateIdentifier",
+        Expr::OptChain(_) => "ChainExpression",
+        Expr::Invalid(_) => "invalid expression",
         Expr::Lit(lit) => match lit {
-            Lit::Str(Str { span, value, raw }) => ast::Expression::Literal(ast::Literal {
-                loc: dummy_loc(),
-                range: convert_span(span),
-                value: ast::Value::String(value.to_string()),
-                raw: raw.as_ref().map(|s| s.to_string()),
-            }),
-            Lit::Bool(_) => todo!(),
-            Lit::Null(_) => todo!(),
-            Lit::Num(Number { span, value, raw }) => ast::Expression::Literal(ast::Literal {
-                loc: dummy_loc(),
-                range: convert_span(span),
-                value: ast::Value::Number(value.to_owned()),
-                raw: raw.as_ref().map(|s| s.to_string()),
-            }),
-            Lit::BigInt(_) => todo!(),
-            Lit::Regex(_) => todo!(),
-            Lit::JSXText(_) => todo!(),
+            Lit::Bool(_) => "boolean literal",
+            Lit::Null(_) => "null literal",
+            Lit::BigInt(_) => "bigint literal",
+            Lit::Regex(_) => "regular expression literal",
+            Lit::JSXText(_) => "JSXText",
+            Lit::Num(_) => "numeric literal",
+            // This is supported, see `convert_expression`.
+            Lit::Str(_) => "literal",
         },
-        Expr::Tpl(_) => todo!(),
-        Expr::TaggedTpl(_) => todo!(),
-        Expr::Arrow(_) => todo!(),
-        Expr::Class(_) => todo!(),
-        Expr::Yield(_) => todo!(),
-        Expr::MetaProp(_) => todo!(),
-        Expr::Await(_) => todo!(),
-        Expr::Paren(_) => todo!(),
-        Expr::JSXMember(_) => todo!(),
-        Expr::JSXNamespacedName(_) => todo!(),
-        Expr::JSXEmpty(_) => todo!(),
-        Expr::JSXElement(_) => todo!(),
-        Expr::JSXFragment(_) => todo!(),
-        Expr::TsTypeAssertion(_) => todo!(),
-        Expr::TsConstAssertion(_) => todo!(),
-        Expr::TsNonNull(_) => todo!(),
-        Expr::TsAs(_) => todo!(),
-        Expr::TsInstantiation(_) => todo!(),
-        Expr::TsSatisfies(_) => todo!(),
-        Expr::PrivateName(_) => todo!(),
-        Expr::OptChain(_) => todo!(),
-        Expr::Invalid(_) => todo!(),
-    }
+        Expr::Member(_) | Expr::Ident(_) => "expression",
+    };
+    index.unsupported(kind, expr)
 }
 
-fn convert_ident(ident: &Ident) -> ast::Identifier {
+fn convert_ident(ident: &Ident, index: &SourceIndex) -> ast::Identifier {
     ast::Identifier {
-        loc: dummy_loc(),
-        range: convert_span(&ident.span),
+        loc: index.loc(&ident.span),
+        range: index.range(&ident.span),
         name: ident.sym.to_string(),
     }
 }
@@ -0,0 +1,262 @@
+use swc_common::sync::Lrc;
+use swc_common::{FileName, SourceMap};
+use swc_ecma_parser::lexer::Lexer;
+use swc_ecma_parser::token::Token;
+use swc_ecma_parser::StringInput;
+
+use crate::ast;
+use crate::parser::{self, SourceIndex};
+use crate::source_code::Ranged;
+
+#[derive(Debug, Default)]
+pub struct PrintOptions<'a> {
+    // Source text the program was parsed from.  When provided, statements
+    // whose tokens still match the original ones are copied verbatim so that
+    // their formatting is preserved.
+    pub source: Option<&'a str>,
+}
+
+pub fn print(program: &ast::Program, options: &PrintOptions) -> String {
+    let mut printer = Printer {
+        program,
+        source: options.source.map(SourceIndex::new),
+        out: String::new(),
+        next_comment: 0,
+    };
+
+    for stmt in &program.body {
+        printer.print_statement(stmt);
+    }
+    printer.print_comments_before(u32::MAX);
+
+    printer.out
+}
+
+struct Printer<'a> {
+    program: &'a ast::Program,
+    source: Option<SourceIndex<'a>>,
+    out: String,
+    next_comment: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn print_statement(&mut self, stmt: &ast::Statement) {
+        let range = stmt.range();
+
+        let mut writer = Writer::default();
+        writer.print_statement(stmt);
+
+        match self.original_text(range, &writer.tokens) {
+            Some(text) => {
+                // Comments inside of the statement are part of `text`.
+                self.print_comments_before(range.0);
+                self.skip_comments_before(range.1);
+                self.out.push_str(text);
+            }
+            None => {
+                // There's no way to place comments that were inside of a
+                // modified statement so we print them before it instead.
+                self.print_comments_before(range.1);
+                self.out.push_str(&writer.out);
+            }
+        }
+        self.out.push('\n');
+    }
+
+    fn original_text(&self, range: ast::Range, tokens: &[String]) -> Option<&'a str> {
+        let source = self.source.as_ref()?;
+        if range.0 > range.1 || range.1 > source.len_utf16() {
+            return None;
+        }
+        let text = &source.code()[source.byte_offset(range.0)..source.byte_offset(range.1)];
+
+        let original = self
+            .program
+            .tokens
+            .iter()
+            .map(ast::Token::value)
+            .filter(|value| value.range.0 >= range.0 && value.range.1 <= range.1)
+            .map(|value| value.value.as_str());
+
+        if original.eq(tokens.iter().map(String::as_str)) {
+            Some(text)
+        } else {
+            None
+        }
+    }
+
+    fn print_comments_before(&mut self, pos: u32) {
+        while let Some(comment) = self.program.comments.get(self.next_comment) {
+            let (prefix, value, suffix) = match comment {
+                ast::Comment::Line(value) => ("//", value, "\n"),
+                ast::Comment::Block(value) => ("/*", value, "*/\n"),
+            };
+            if value.range.0 >= pos {
+                break;
+            }
+            self.out.push_str(prefix);
+            self.out.push_str(&value.value);
+            self.out.push_str(suffix);
+            self.next_comment += 1;
+        }
+    }
+
+    fn skip_comments_before(&mut self, pos: u32) {
+        while let Some(comment) = self.program.comments.get(self.next_comment) {
+            let (ast::Comment::Line(value) | ast::Comment::Block(value)) = comment;
+            if value.range.0 >= pos {
+                break;
+            }
+            self.next_comment += 1;
+        }
+    }
+}
+
+// Prints a single statement, keeping track of the tokens it writes.
+#[derive(Default)]
+struct Writer {
+    out: String,
+    tokens: Vec<String>,
+}
+
+impl Writer {
+    fn print_statement(&mut self, stmt: &ast::Statement) {
+        match stmt {
+            ast::Statement::DebuggerStatement(_) => {
+                self.token("debugger");
+                self.token(";");
+            }
+            ast::Statement::ExpressionStatement(ast::ExpressionStatement {
+                expression, ..
+            }) => {
+                self.print_expression(expression);
+                self.token(";");
+            }
+        }
+    }
+
+    fn print_expression(&mut self, expr: &ast::Expression) {
+        match expr {
+            ast::Expression::Literal(ast::Literal { value, raw, .. }) => match raw {
+                Some(raw) if raw_matches(raw, value) => self.token(raw),
+                _ => match value {
+                    ast::Value::String(value) => self.token(&quote(value)),
+                    ast::Value::Number(value) => self.token(&number(*value)),
+                },
+            },
+            ast::Expression::Identifier(ident) => self.token(&ident.name),
+            ast::Expression::CallExpression(ast::CallExpression {
+                callee, arguments, ..
+            }) => {
+                self.print_callee(callee);
+                self.token("(");
+                for (i, arg) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        self.token(",");
+                        self.out.push(' ');
+                    }
+                    match arg {
+                        ast::ExprOrSpread::Expr(expr) => self.print_expression(expr),
+                        ast::ExprOrSpread::Spread(ast::SpreadElement { argument, .. }) => {
+                            self.token("...");
+                            self.print_expression(argument);
+                        }
+                    }
+                }
+                self.token(")");
+            }
+            ast::Expression::MemberExpression(ast::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            }) => {
+                self.print_callee(object);
+                let ast::Prop::Identifier(ident) = property.as_ref();
+                if *computed {
+                    self.token("[");
+                    self.token(&ident.name);
+                    self.token("]");
+                } else {
+                    self.token(".");
+                    self.token(&ident.name);
+                }
+            }
+        }
+    }
+
+    // Prints the object of a member expression or the callee of a call.
+    fn print_callee(&mut self, expr: &ast::Expression) {
+        match expr {
+            // `1.toString()` is a syntax error so number literals need parens.
+            ast::Expression::Literal(ast::Literal {
+                value: ast::Value::Number(_),
+                ..
+            }) => {
+                self.token("(");
+                self.print_expression(expr);
+                self.token(")");
+            }
+            _ => self.print_expression(expr),
+        }
+    }
+
+    fn token(&mut self, value: &str) {
+        self.out.push_str(value);
+        self.tokens.push(value.to_string());
+    }
+}
+
+// `raw` goes stale when only a literal's `value` is edited, in which case the
+// value has to be printed instead.
+fn raw_matches(raw: &str, value: &ast::Value) -> bool {
+    let cm = Lrc::<SourceMap>::default();
+    let fm = cm.new_source_file(FileName::Anon, raw.to_string());
+    let (syntax, target) = parser::options();
+    let mut lexer = Lexer::new(syntax, target, StringInput::from(&*fm), None);
+    let token = lexer.next().map(|token| token.token);
+    if lexer.next().is_some() {
+        return false;
+    }
+    match (token, value) {
+        (Some(Token::Str { value: cooked, .. }), ast::Value::String(value)) => &*cooked == value,
+        // Compares bits so that `-0` doesn't match `0`.
+        (Some(Token::Num { value: cooked, .. }), ast::Value::Number(value)) => {
+            cooked.to_bits() == value.to_bits()
+        }
+        _ => false,
+    }
+}
+
+// Rust prints `NaN`, `inf` and `-inf`, which aren't valid JS.
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "Infinity".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Infinity".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\u{2028}' => result.push_str("\\u2028"),
+            '\u{2029}' => result.push_str("\\u2029"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
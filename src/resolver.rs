@@ -0,0 +1,640 @@
+// Node's module resolution algorithm, see
+// https://nodejs.org/api/esm.html#resolution-algorithm-specification and
+// https://nodejs.org/api/modules.html#all-together.
+//
+// `Options::esm` resolves like `import` does: paths must be exact apart from
+// the legacy `main` fallback.  `Options::require` resolves like `require()`,
+// trying extensions and `index` files too.  `format` tells which kind of
+// module a resolved file is.  Files are read from `Options::source`.
+//
+// With `Options::typescript`, relative imports of `.js`, `.mjs` and `.cjs`
+// files that don't exist resolve to the `.ts`, `.mts` and `.cts` files they
+// would be compiled from, like TypeScript resolves them.
+
+use std::error;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use crate::module_source::{Disk, ModuleSource};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // Conditions that `exports` and `imports` can match, other than "default"
+    // which always matches.
+    pub conditions: Vec<String>,
+    // Extensions to try for paths that don't exist as is.
+    pub extensions: Vec<String>,
+    // `Disk` unless set otherwise.
+    pub source: Rc<dyn ModuleSource>,
+    // Whether paths can resolve to the TypeScript files that compile to them,
+    // for imports in TypeScript modules.
+    pub typescript: bool,
+}
+
+impl Options {
+    pub fn esm() -> Self {
+        Options {
+            conditions: vec!["node".to_string(), "import".to_string()],
+            extensions: vec![],
+            source: Rc::new(Disk),
+            typescript: false,
+        }
+    }
+
+    pub fn require() -> Self {
+        Options {
+            conditions: vec!["node".to_string(), "require".to_string()],
+            extensions: vec![".js".to_string(), ".json".to_string(), ".node".to_string()],
+            source: Rc::new(Disk),
+            typescript: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NotFound {
+        specifier: String,
+        referrer: PathBuf,
+    },
+    // ESM can't import directories, only their files.
+    UnsupportedDirectoryImport(PathBuf),
+    // `node:` modules, which the embedded isolate doesn't have.
+    UnsupportedBuiltin(String),
+    PackagePathNotExported {
+        package: PathBuf,
+        subpath: String,
+    },
+    PackageImportNotDefined {
+        specifier: String,
+        referrer: PathBuf,
+    },
+    InvalidPackageTarget {
+        package: PathBuf,
+        target: String,
+    },
+    InvalidPackageConfig {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound {
+                specifier,
+                referrer,
+            } => write!(
+                f,
+                "cannot find module {specifier:?} imported from {}",
+                referrer.display()
+            ),
+            Error::UnsupportedDirectoryImport(path) => write!(
+                f,
+                "directory import {} is not supported, import a file instead",
+                path.display()
+            ),
+            Error::UnsupportedBuiltin(name) => write!(f, "builtin module {name:?} is not available"),
+            Error::PackagePathNotExported { package, subpath } => write!(
+                f,
+                "package subpath {subpath:?} is not defined by \"exports\" in {}",
+                package.join("package.json").display()
+            ),
+            Error::PackageImportNotDefined {
+                specifier,
+                referrer,
+            } => write!(
+                f,
+                "package import specifier {specifier:?} is not defined in package.json imported from {}",
+                referrer.display()
+            ),
+            Error::InvalidPackageTarget { package, target } => write!(
+                f,
+                "invalid target {target:?} in {}",
+                package.join("package.json").display()
+            ),
+            Error::InvalidPackageConfig { path, message } => {
+                write!(f, "invalid package config {}: {message}", path.display())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+// How a resolved file should be loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Module,
+    CommonJs,
+    Json,
+}
+
+// Node's `ESM_FILE_FORMAT`: `.mjs`, `.cjs` and `.json` files are always ES,
+// CommonJS and JSON modules, other files depend on the `type` of the package
+// they're in.  `.mts` and `.cts` are TypeScript's equivalents of `.mjs` and
+// `.cjs`.
+pub fn format(path: &Path) -> Format {
+    format_in(&Disk, path)
+}
+
+// `format` for a file in `source`.
+pub fn format_in(source: &dyn ModuleSource, path: &Path) -> Format {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("mjs" | "mts") => Format::Module,
+        Some("cjs" | "cts") => Format::CommonJs,
+        Some("json") => Format::Json,
+        _ => {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            match find_package_scope(source, dir) {
+                Ok(Some((_, config))) if config.module_type.as_deref() == Some("module") => {
+                    Format::Module
+                }
+                _ => Format::CommonJs,
+            }
+        }
+    }
+}
+
+// Resolves `specifier`, imported by the module at `referrer`, to the
+// canonical path of a file.
+pub fn resolve(specifier: &str, referrer: &Path, options: &Options) -> Result<PathBuf, Error> {
+    let not_found = || Error::NotFound {
+        specifier: specifier.to_string(),
+        referrer: referrer.to_path_buf(),
+    };
+    let dir = referrer.parent().unwrap_or(Path::new("."));
+
+    let resolved = if let Some(path) = specifier.strip_prefix("file://") {
+        resolve_path(Path::new(path), options).ok_or_else(not_found)?
+    } else if is_relative(specifier) {
+        resolve_path(&dir.join(specifier), options).ok_or_else(not_found)?
+    } else if specifier.starts_with('#') {
+        resolve_package_import(specifier, referrer, options)?
+    } else if let Some(name) = specifier.strip_prefix("node:") {
+        return Err(Error::UnsupportedBuiltin(name.to_string()));
+    } else {
+        resolve_package(specifier, dir, referrer, options)?
+    };
+
+    if options.source.is_dir(&resolved) {
+        return Err(Error::UnsupportedDirectoryImport(resolved));
+    }
+    options
+        .source
+        .canonicalize(&resolved)
+        .map_err(|_| not_found())
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier.starts_with('/') || specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+// An existing file for `path`, trying extensions and directory indexes when
+// `options` has extensions.
+fn resolve_path(path: &Path, options: &Options) -> Option<PathBuf> {
+    let source = &*options.source;
+    if source.is_file(path) || (options.extensions.is_empty() && source.exists(path)) {
+        return Some(path.to_path_buf());
+    }
+    if options.typescript {
+        if let Some(file) = typescript_source(path).filter(|file| source.is_file(file)) {
+            return Some(file);
+        }
+    }
+    if options.extensions.is_empty() {
+        return None;
+    }
+    for extension in &options.extensions {
+        let file = append(path, extension);
+        if source.is_file(&file) {
+            return Some(file);
+        }
+    }
+    if source.is_dir(path) {
+        if let Ok(Some(config)) = PackageConfig::read(source, path) {
+            if let Some(main) = &config.main {
+                if let Some(file) = resolve_path(&path.join(main), options) {
+                    return Some(file);
+                }
+            }
+        }
+        for extension in &options.extensions {
+            let file = path.join(format!("index{extension}"));
+            if source.is_file(&file) {
+                return Some(file);
+            }
+        }
+    }
+    None
+}
+
+// The TypeScript file that `path` would be compiled from, e.g. `util.ts` for
+// `util.js`.
+fn typescript_source(path: &Path) -> Option<PathBuf> {
+    let extension = match path.extension()?.to_str()? {
+        "js" => "ts",
+        "mjs" => "mts",
+        "cjs" => "cts",
+        _ => return None,
+    };
+    Some(path.with_extension(extension))
+}
+
+fn resolve_package(
+    specifier: &str,
+    dir: &Path,
+    referrer: &Path,
+    options: &Options,
+) -> Result<PathBuf, Error> {
+    let not_found = || Error::NotFound {
+        specifier: specifier.to_string(),
+        referrer: referrer.to_path_buf(),
+    };
+    let (name, subpath) = parse_package_name(specifier).ok_or_else(not_found)?;
+
+    // A package can import itself by name through its own `exports`.
+    if let Some((package_dir, config)) = find_package_scope(&*options.source, dir)? {
+        if config.name.as_deref() == Some(name) {
+            if let Some(exports) = &config.exports {
+                return resolve_package_exports(&package_dir, &subpath, exports, options);
+            }
+        }
+    }
+
+    for ancestor in dir.ancestors() {
+        let package_dir = ancestor.join("node_modules").join(name);
+        if !options.source.is_dir(&package_dir) {
+            continue;
+        }
+        let config = PackageConfig::read(&*options.source, &package_dir)?.unwrap_or_default();
+        if let Some(exports) = &config.exports {
+            return resolve_package_exports(&package_dir, &subpath, exports, options);
+        }
+        if subpath == "." {
+            return resolve_legacy_main(&package_dir, &config, options).ok_or_else(not_found);
+        }
+        return resolve_path(&package_dir.join(&subpath), options).ok_or_else(not_found);
+    }
+
+    Err(not_found())
+}
+
+// Splits `name/sub/path` or `@scope/name/sub/path` into the package name and
+// `./sub/path`.
+fn parse_package_name(specifier: &str) -> Option<(&str, String)> {
+    let name_end = if specifier.starts_with('@') {
+        let scope_end = specifier.find('/')?;
+        specifier[scope_end + 1..]
+            .find('/')
+            .map_or(specifier.len(), |end| scope_end + 1 + end)
+    } else {
+        specifier.find('/').unwrap_or(specifier.len())
+    };
+    let name = &specifier[..name_end];
+    if name.is_empty() || name.starts_with('.') || name.contains(['\\', '%']) {
+        return None;
+    }
+    Some((name, format!(".{}", &specifier[name_end..])))
+}
+
+// `main`, then the files Node tries for packages without `exports`.
+fn resolve_legacy_main(
+    package_dir: &Path,
+    config: &PackageConfig,
+    options: &Options,
+) -> Option<PathBuf> {
+    let source = &*options.source;
+    let extensions = [".js", ".json", ".node"];
+    if let Some(main) = &config.main {
+        let main = package_dir.join(main);
+        if source.is_file(&main) {
+            return Some(main);
+        }
+        for extension in extensions {
+            let file = append(&main, extension);
+            if source.is_file(&file) {
+                return Some(file);
+            }
+        }
+        for extension in extensions {
+            let file = main.join(format!("index{extension}"));
+            if source.is_file(&file) {
+                return Some(file);
+            }
+        }
+    }
+    if !options.extensions.is_empty() {
+        return resolve_path(package_dir, options);
+    }
+    extensions
+        .iter()
+        .map(|extension| package_dir.join(format!("index{extension}")))
+        .find(|file| source.is_file(file))
+}
+
+fn resolve_package_exports(
+    package_dir: &Path,
+    subpath: &str,
+    exports: &Target,
+    options: &Options,
+) -> Result<PathBuf, Error> {
+    let not_exported = || Error::PackagePathNotExported {
+        package: package_dir.to_path_buf(),
+        subpath: subpath.to_string(),
+    };
+
+    let subpath_keys = match exports {
+        Target::Conditions(entries) => {
+            let dot_keys = entries
+                .iter()
+                .filter(|(key, _)| key.starts_with('.'))
+                .count();
+            if dot_keys != 0 && dot_keys != entries.len() {
+                return Err(Error::InvalidPackageConfig {
+                    path: package_dir.join("package.json"),
+                    message: "\"exports\" cannot mix subpaths and conditions".to_string(),
+                });
+            }
+            dot_keys != 0
+        }
+        _ => false,
+    };
+
+    let resolved = match (subpath, exports) {
+        (".", _) if !subpath_keys => {
+            resolve_package_target(package_dir, exports, None, false, options)?
+        }
+        (_, Target::Conditions(entries)) if subpath_keys => {
+            resolve_imports_exports(subpath, entries, package_dir, false, options)?
+        }
+        _ => None,
+    };
+    let resolved = resolved.ok_or_else(not_exported)?;
+    if !options.source.exists(&resolved) {
+        return Err(Error::NotFound {
+            specifier: subpath.to_string(),
+            referrer: package_dir.join("package.json"),
+        });
+    }
+    Ok(resolved)
+}
+
+fn resolve_package_import(
+    specifier: &str,
+    referrer: &Path,
+    options: &Options,
+) -> Result<PathBuf, Error> {
+    let not_defined = || Error::PackageImportNotDefined {
+        specifier: specifier.to_string(),
+        referrer: referrer.to_path_buf(),
+    };
+    if specifier == "#" || specifier.starts_with("#/") {
+        return Err(not_defined());
+    }
+    let dir = referrer.parent().unwrap_or(Path::new("."));
+    let (package_dir, config) =
+        find_package_scope(&*options.source, dir)?.ok_or_else(not_defined)?;
+    let Some(Target::Conditions(imports)) = &config.imports else {
+        return Err(not_defined());
+    };
+    let resolved = resolve_imports_exports(specifier, imports, &package_dir, true, options)?
+        .ok_or_else(not_defined)?;
+    if !options.source.exists(&resolved) {
+        return Err(Error::NotFound {
+            specifier: specifier.to_string(),
+            referrer: referrer.to_path_buf(),
+        });
+    }
+    Ok(resolved)
+}
+
+// Looks `key` up in an `exports` or `imports` map, including `*` patterns.
+fn resolve_imports_exports(
+    key: &str,
+    entries: &[(String, Target)],
+    package_dir: &Path,
+    is_imports: bool,
+    options: &Options,
+) -> Result<Option<PathBuf>, Error> {
+    if !key.contains('*') {
+        if let Some((_, target)) = entries.iter().find(|(k, _)| k == key) {
+            return resolve_package_target(package_dir, target, None, is_imports, options);
+        }
+    }
+
+    let mut patterns: Vec<_> = entries
+        .iter()
+        .filter(|(k, _)| k.matches('*').count() == 1)
+        .collect();
+    patterns.sort_by(|(a, _), (b, _)| pattern_key_compare(a, b));
+
+    for (pattern, target) in patterns {
+        let (base, trailer) = pattern.split_once('*').unwrap();
+        if key.starts_with(base)
+            && key != base
+            && (trailer.is_empty() || (key.ends_with(trailer) && key.len() >= pattern.len()))
+        {
+            let matched = &key[base.len()..key.len() - trailer.len()];
+            return resolve_package_target(package_dir, target, Some(matched), is_imports, options);
+        }
+    }
+    Ok(None)
+}
+
+// Node's `PATTERN_KEY_COMPARE`: longer prefixes before the `*` come first.
+fn pattern_key_compare(a: &str, b: &str) -> std::cmp::Ordering {
+    let base_a = a.find('*').map_or(a.len(), |i| i + 1);
+    let base_b = b.find('*').map_or(b.len(), |i| i + 1);
+    base_b.cmp(&base_a).then(b.len().cmp(&a.len()))
+}
+
+fn resolve_package_target(
+    package_dir: &Path,
+    target: &Target,
+    pattern_match: Option<&str>,
+    is_imports: bool,
+    options: &Options,
+) -> Result<Option<PathBuf>, Error> {
+    let invalid = |target: &str| Error::InvalidPackageTarget {
+        package: package_dir.to_path_buf(),
+        target: target.to_string(),
+    };
+
+    match target {
+        Target::Null => Ok(None),
+        Target::Path(target) => {
+            let substituted = match pattern_match {
+                Some(matched) => target.replace('*', matched),
+                None => target.clone(),
+            };
+            if !target.starts_with("./") {
+                // `imports` can map to other packages.
+                if is_imports && !target.starts_with("../") && !target.starts_with('/') {
+                    let referrer = package_dir.join("package.json");
+                    return resolve_package(&substituted, package_dir, &referrer, options)
+                        .map(Some);
+                }
+                return Err(invalid(target));
+            }
+            let escapes = Path::new(&target[2..]).components().any(|component| {
+                matches!(component, Component::ParentDir | Component::CurDir)
+                    || component.as_os_str() == "node_modules"
+            });
+            let match_escapes = pattern_match.is_some_and(|matched| {
+                matched
+                    .split(['/', '\\'])
+                    .any(|segment| matches!(segment, "." | ".." | "node_modules"))
+            });
+            if escapes || match_escapes {
+                return Err(invalid(&substituted));
+            }
+            Ok(Some(package_dir.join(&substituted[2..])))
+        }
+        Target::Alternatives(targets) => {
+            let mut last_error = None;
+            for target in targets {
+                match resolve_package_target(
+                    package_dir,
+                    target,
+                    pattern_match,
+                    is_imports,
+                    options,
+                ) {
+                    Ok(Some(resolved)) => return Ok(Some(resolved)),
+                    Ok(None) => last_error = None,
+                    Err(error @ Error::InvalidPackageTarget { .. }) => last_error = Some(error),
+                    Err(error) => return Err(error),
+                }
+            }
+            match last_error {
+                Some(error) => Err(error),
+                None => Ok(None),
+            }
+        }
+        Target::Conditions(entries) => {
+            for (condition, target) in entries {
+                if condition == "default" || options.conditions.contains(condition) {
+                    let resolved = resolve_package_target(
+                        package_dir,
+                        target,
+                        pattern_match,
+                        is_imports,
+                        options,
+                    )?;
+                    if resolved.is_some() {
+                        return Ok(resolved);
+                    }
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+// The closest directory with a package.json, starting at `dir`.
+fn find_package_scope(
+    source: &dyn ModuleSource,
+    dir: &Path,
+) -> Result<Option<(PathBuf, PackageConfig)>, Error> {
+    for ancestor in dir.ancestors() {
+        if ancestor
+            .file_name()
+            .is_some_and(|name| name == "node_modules")
+        {
+            break;
+        }
+        if let Some(config) = PackageConfig::read(source, ancestor)? {
+            return Ok(Some((ancestor.to_path_buf(), config)));
+        }
+    }
+    Ok(None)
+}
+
+fn append(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+// The fields of package.json that resolution uses.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct PackageConfig {
+    pub(crate) name: Option<String>,
+    pub(crate) main: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) module_type: Option<String>,
+    exports: Option<Target>,
+    imports: Option<Target>,
+}
+
+impl PackageConfig {
+    // The package.json in `dir`, if there is one.
+    pub(crate) fn read(source: &dyn ModuleSource, dir: &Path) -> Result<Option<Self>, Error> {
+        let path = dir.join("package.json");
+        let Ok(json) = source.read_to_string(&path) else {
+            return Ok(None);
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|error| Error::InvalidPackageConfig {
+                path,
+                message: error.to_string(),
+            })
+    }
+}
+
+// A value in `exports` or `imports`.  Conditions are matched in the order
+// they're written in, which `serde_json::Map` doesn't keep.
+#[derive(Debug)]
+enum Target {
+    Null,
+    Path(String),
+    Alternatives(Vec<Target>),
+    Conditions(Vec<(String, Target)>),
+}
+
+impl<'de> Deserialize<'de> for Target {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TargetVisitor;
+
+        impl<'de> Visitor<'de> for TargetVisitor {
+            type Value = Target;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, array, object or null")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Target, E> {
+                Ok(Target::Null)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Target, E> {
+                Ok(Target::Path(value.to_string()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Target, A::Error> {
+                let mut targets = vec![];
+                while let Some(target) = seq.next_element()? {
+                    targets.push(target);
+                }
+                Ok(Target::Alternatives(targets))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Target, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Target::Conditions(entries))
+            }
+        }
+
+        deserializer.deserialize_any(TargetVisitor)
+    }
+}
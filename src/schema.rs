@@ -0,0 +1,56 @@
+use schemars::schema_for;
+use ts_rs::TS;
+
+use crate::ast::{self, Node};
+
+// Both of these are checked in under `types/`, run
+// `cargo run --bin generate-types` after changing `ast.rs`.
+
+pub fn json_schema() -> String {
+    let schema = schema_for!(ast::Program);
+    serde_json::to_string_pretty(&schema).unwrap() + "\n"
+}
+
+pub fn typescript_declarations() -> String {
+    let decls = [
+        ast::Position::decl(),
+        ast::Location::decl(),
+        ast::Comment::decl(),
+        ast::Program::decl(),
+        ast::SourceType::decl(),
+        ast::Statement::decl(),
+        node_decl::<ast::DebuggerStatement>(),
+        node_decl::<ast::ExpressionStatement>(),
+        ast::Expression::decl(),
+        node_decl::<ast::Identifier>(),
+        node_decl::<ast::Literal>(),
+        ast::Value::decl(),
+        node_decl::<ast::CallExpression>(),
+        ast::ExprOrSpread::decl(),
+        ast::SpreadElement::decl(),
+        node_decl::<ast::MemberExpression>(),
+        ast::Prop::decl(),
+        ast::Token::decl(),
+        ast::TokenValue::decl(),
+    ];
+
+    let mut result =
+        String::from("// Generated by `cargo run --bin generate-types`, do not edit.\n\n");
+    for decl in decls {
+        result.push_str("export ");
+        result.push_str(&decl);
+        result.push('\n');
+    }
+    result
+}
+
+// serde writes the `type` of nodes that are variants of tagged enums, so it
+// isn't a field in Rust and ts-rs only includes it in the enums' unions.
+fn node_decl<T: TS + Node>() -> String {
+    let decl = T::decl();
+    let start = format!("interface {} {{ ", T::TYPE);
+    match decl.strip_prefix(&start) {
+        Some(fields) => format!("{start}type: \"{}\", {fields}", T::TYPE),
+        None => panic!("unexpected declaration of {}: {decl}", T::TYPE),
+    }
+}
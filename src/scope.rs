@@ -0,0 +1,328 @@
+// Scope analysis over `ast::Program`, producing the same model as
+// eslint-scope's `ScopeManager`.  Nodes are referred to by their index in the
+// pre-order traversal of `traverse`, which is how `linter/scope_manager.js`
+// maps them back to the AST's JS objects.
+
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer};
+
+use crate::ast;
+use crate::traverse::{self, Node, Visitor};
+
+pub use crate::traverse::NodeId;
+pub type ScopeId = usize;
+pub type VariableId = usize;
+pub type ReferenceId = usize;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ScopeManager {
+    // The global scope is always first.
+    pub scopes: Vec<Scope>,
+    pub variables: Vec<Variable>,
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scope {
+    pub r#type: ScopeType,
+    pub is_strict: bool,
+    pub upper: Option<ScopeId>,
+    pub child_scopes: Vec<ScopeId>,
+    pub variable_scope: ScopeId,
+    pub function_expression_scope: bool,
+    pub block: NodeId,
+    pub variables: Vec<VariableId>,
+    pub references: Vec<ReferenceId>,
+    // References that couldn't be resolved in this scope.
+    pub through: Vec<ReferenceId>,
+    // Only set for the global scope.
+    pub implicit: Option<Implicit>,
+    #[serde(skip)]
+    set: HashMap<String, VariableId>,
+    // References from this scope and its children that haven't been resolved
+    // yet, see `ScopeManager::close`.
+    #[serde(skip)]
+    left: Vec<ReferenceId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScopeType {
+    Global,
+    Module,
+    Function,
+    FunctionExpressionName,
+    Block,
+    Switch,
+    Catch,
+    With,
+    For,
+    Class,
+    ClassFieldInitializer,
+    ClassStaticBlock,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Implicit {
+    // Variables created by assigning to undeclared names in sloppy mode.
+    pub variables: Vec<VariableId>,
+    // References that weren't resolved to any variable.
+    pub left: Vec<ReferenceId>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Variable {
+    pub name: String,
+    pub scope: ScopeId,
+    pub identifiers: Vec<NodeId>,
+    pub references: Vec<ReferenceId>,
+    pub defs: Vec<Definition>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Definition {
+    pub r#type: DefinitionType,
+    pub name: NodeId,
+    pub node: NodeId,
+    pub parent: Option<NodeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DefinitionType {
+    CatchClause,
+    ClassName,
+    FunctionName,
+    ImplicitGlobalVariable,
+    ImportBinding,
+    Parameter,
+    TDZ,
+    Variable,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reference {
+    pub identifier: NodeId,
+    pub from: ScopeId,
+    pub resolved: Option<VariableId>,
+    pub flag: ReferenceFlag,
+    pub write_expr: Option<NodeId>,
+    pub partial: bool,
+    pub init: bool,
+}
+
+// Serialized as eslint-scope's `Reference.READ`, `WRITE` and `RW` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceFlag {
+    Read = 1,
+    Write = 2,
+    ReadWrite = 3,
+}
+
+impl Serialize for ReferenceFlag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub source_type: ast::SourceType,
+    // Treat the whole program as strict mode code.
+    pub implied_strict: bool,
+}
+
+impl Default for Options {
+    // Matches the options `linter.js` passes to eslint-scope.
+    fn default() -> Self {
+        Options {
+            source_type: ast::SourceType::Script,
+            implied_strict: false,
+        }
+    }
+}
+
+pub fn analyze(program: &ast::Program, options: &Options) -> ScopeManager {
+    let mut analyzer = Analyzer {
+        manager: ScopeManager::default(),
+        current: 0,
+        reference_names: vec![],
+        options,
+    };
+    traverse::traverse(program, &mut analyzer);
+    analyzer.manager
+}
+
+pub fn to_v8<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    manager: &ScopeManager,
+) -> Result<v8::Local<'s, v8::Value>, serde_v8::Error> {
+    serde_v8::to_v8(scope, manager)
+}
+
+struct Analyzer<'o> {
+    manager: ScopeManager,
+    current: ScopeId,
+    // Indexed by `ReferenceId`.
+    reference_names: Vec<String>,
+    options: &'o Options,
+}
+
+impl Visitor<'_> for Analyzer<'_> {
+    fn enter(&mut self, id: NodeId, node: Node) {
+        match node {
+            Node::Program(program) => {
+                let strict = self.options.implied_strict || has_use_strict_directive(program);
+                self.open(ScopeType::Global, id, strict);
+                if let ast::SourceType::Module = self.options.source_type {
+                    self.open(ScopeType::Module, id, true);
+                }
+            }
+            Node::Expression(ast::Expression::Identifier(ident)) => self.reference(id, ident),
+            Node::Property(member) => match member.property.as_ref() {
+                // `a[b]` reads `b`, `a.b` doesn't.
+                ast::Prop::Identifier(ident) if member.computed => self.reference(id, ident),
+                ast::Prop::Identifier(_) => {}
+            },
+            Node::Statement(_) | Node::Expression(_) | Node::SpreadElement(_) => {}
+        }
+    }
+
+    fn leave(&mut self, _id: NodeId, node: Node) {
+        if let Node::Program(_) = node {
+            if let ast::SourceType::Module = self.options.source_type {
+                self.close();
+            }
+            self.close();
+        }
+    }
+}
+
+impl Analyzer<'_> {
+    fn open(&mut self, r#type: ScopeType, block: NodeId, is_strict: bool) {
+        let id = self.manager.scopes.len();
+        let upper = if id == 0 { None } else { Some(self.current) };
+        let is_strict =
+            is_strict || upper.is_some_and(|upper| self.manager.scopes[upper].is_strict);
+        let variable_scope = match r#type {
+            ScopeType::Global
+            | ScopeType::Module
+            | ScopeType::Function
+            | ScopeType::ClassFieldInitializer
+            | ScopeType::ClassStaticBlock => id,
+            _ => self.manager.scopes[self.current].variable_scope,
+        };
+
+        self.manager.scopes.push(Scope {
+            r#type,
+            is_strict,
+            upper,
+            child_scopes: vec![],
+            variable_scope,
+            function_expression_scope: r#type == ScopeType::FunctionExpressionName,
+            block,
+            variables: vec![],
+            references: vec![],
+            through: vec![],
+            implicit: (r#type == ScopeType::Global).then(Implicit::default),
+            set: HashMap::new(),
+            left: vec![],
+        });
+        if let Some(upper) = upper {
+            self.manager.scopes[upper].child_scopes.push(id);
+        }
+        self.current = id;
+    }
+
+    // Resolves the references made in the current scope, passing the ones that
+    // can't be resolved on to the enclosing scope.
+    fn close(&mut self) {
+        let id = self.current;
+        let left = std::mem::take(&mut self.manager.scopes[id].left);
+
+        if let Some(implicit) = &mut self.manager.scopes[id].implicit {
+            implicit.left = left.clone();
+        }
+
+        for reference in left {
+            if !self.resolve(id, reference) {
+                let scope = &mut self.manager.scopes[id];
+                scope.through.push(reference);
+                if let Some(upper) = scope.upper {
+                    self.manager.scopes[upper].left.push(reference);
+                }
+            }
+        }
+
+        self.current = self.manager.scopes[id].upper.unwrap_or(id);
+    }
+
+    fn resolve(&mut self, scope: ScopeId, reference: ReferenceId) -> bool {
+        let name = &self.reference_names[reference];
+        let Some(&variable) = self.manager.scopes[scope].set.get(name) else {
+            return false;
+        };
+
+        // Names declared in the global scope can be shadowed by properties of
+        // the global object, so only lexical declarations resolve statically.
+        // TODO: `let` and `const` once variable declarations are supported.
+        if self.manager.scopes[scope].r#type == ScopeType::Global {
+            let defs = &self.manager.variables[variable].defs;
+            if defs.is_empty()
+                || defs
+                    .iter()
+                    .any(|def| def.r#type != DefinitionType::ClassName)
+            {
+                return false;
+            }
+        }
+
+        self.manager.references[reference].resolved = Some(variable);
+        self.manager.variables[variable].references.push(reference);
+        true
+    }
+
+    fn reference(&mut self, identifier: NodeId, ident: &ast::Identifier) {
+        let id = self.manager.references.len();
+        self.reference_names.push(ident.name.clone());
+        self.manager.references.push(Reference {
+            identifier,
+            from: self.current,
+            resolved: None,
+            flag: ReferenceFlag::Read,
+            write_expr: None,
+            partial: false,
+            init: false,
+        });
+        let scope = &mut self.manager.scopes[self.current];
+        scope.references.push(id);
+        scope.left.push(id);
+    }
+}
+
+// Whether the program starts with a "use strict" directive.
+fn has_use_strict_directive(program: &ast::Program) -> bool {
+    for stmt in &program.body {
+        let ast::Statement::ExpressionStatement(stmt) = stmt else {
+            return false;
+        };
+        let ast::Expression::Literal(ast::Literal {
+            value: ast::Value::String(value),
+            raw,
+            ..
+        }) = stmt.expression.as_ref()
+        else {
+            return false;
+        };
+        let is_use_strict = match raw {
+            Some(raw) => raw == "\"use strict\"" || raw == "'use strict'",
+            None => value == "use strict",
+        };
+        if is_use_strict {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,351 @@
+// Serves parsed programs to local clients over HTTP, either on a TCP port or
+// on a Unix socket.
+//
+//   GET /ast?path=<file>[&format=<format>][&tokens=true][&comments=true]
+//   GET /ast?hash=<key>[&format=<format>][&tokens=true][&comments=true]
+//
+// `format` is `estree` (the default), `swc` or `binary`.  Responses include the
+// program's store key in an `X-Ast-Key` header so that it can be requested
+// again by hash without sending the path.  Only programs that are in the store
+// can be requested by hash, and not in the `swc` format since swc's spans are
+// byte offsets that can't be worked out without the source.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::store::{self, Store};
+use crate::{binary, to_swc};
+
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    // Parses the request line, e.g. "GET /ast?path=foo.js HTTP/1.1".
+    pub fn parse(line: &str) -> Option<Request> {
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?;
+        parts.next()?.strip_prefix("HTTP/")?;
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Some((percent_decode(key)?, percent_decode(value)?))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Request {
+            method,
+            path: percent_decode(path)?,
+            query,
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        matches!(self.param(name), Some("" | "true" | "1"))
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub key: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, key: String, body: Vec<u8>) -> Self {
+        Response {
+            status: 200,
+            content_type,
+            key: Some(key),
+            body,
+        }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            key: None,
+            body: format!("{}\n", message.to_string()).into_bytes(),
+        }
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            422 => "Unprocessable Entity",
+            _ => "Internal Server Error",
+        };
+        write!(out, "HTTP/1.1 {} {reason}\r\n", self.status)?;
+        write!(out, "Content-Type: {}\r\n", self.content_type)?;
+        write!(out, "Content-Length: {}\r\n", self.body.len())?;
+        if let Some(key) = &self.key {
+            write!(out, "X-Ast-Key: {key}\r\n")?;
+        }
+        write!(out, "Connection: close\r\n\r\n")?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Estree,
+    Swc,
+    Binary,
+}
+
+// How long to wait before accepting again after `accept` fails, so that
+// running out of file descriptors doesn't turn into a busy loop.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// The most bytes read for the request line and headers together, so that a
+// client that never sends a newline can't make them grow without bound.
+const MAX_HEAD_LEN: u64 = 8 * 1024;
+
+#[derive(Debug)]
+pub struct ServerOptions {
+    // Connections are closed when reading from or writing to them stalls for
+    // longer than this.
+    pub timeout: Option<Duration>,
+    // Connections beyond this many wait to be accepted until one of the
+    // others is done.
+    pub max_connections: usize,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        ServerOptions {
+            timeout: Some(Duration::from_secs(10)),
+            max_connections: 64,
+        }
+    }
+}
+
+pub struct Server {
+    store: Store,
+    options: ServerOptions,
+    connections: Mutex<usize>,
+    connection_done: Condvar,
+}
+
+impl Server {
+    pub fn new(store: Store) -> Self {
+        Server::with_options(store, ServerOptions::default())
+    }
+
+    pub fn with_options(store: Store, options: ServerOptions) -> Self {
+        Server {
+            store,
+            options,
+            connections: Mutex::new(0),
+            connection_done: Condvar::new(),
+        }
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        if request.method != "GET" {
+            return Response::error(405, format!("unsupported method {}", request.method));
+        }
+        if request.path != "/ast" {
+            return Response::error(404, format!("no such endpoint {}", request.path));
+        }
+
+        let format = match request.param("format") {
+            None | Some("estree") => Format::Estree,
+            Some("swc") => Format::Swc,
+            Some("binary") => Format::Binary,
+            Some(format) => return Response::error(400, format!("unknown format {format}")),
+        };
+
+        let (key, source, mut program) = match (request.param("path"), request.param("hash")) {
+            (Some(path), None) => {
+                let source = match fs::read_to_string(path) {
+                    Ok(source) => source,
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                        return Response::error(404, format!("{path}: {error}"))
+                    }
+                    Err(error) => return Response::error(500, format!("{path}: {error}")),
+                };
+                match self.store.get_or_parse(&source) {
+                    Ok(program) => (Store::key(&source), Some(source), program),
+                    Err(store::Error::Parse(error)) => {
+                        return Response::error(422, format!("{path}:{error}"))
+                    }
+                    Err(error) => return Response::error(500, error),
+                }
+            }
+            (None, Some(hash)) => match self.store.get_by_key(hash) {
+                Some(program) => (hash.to_string(), None, program),
+                None => return Response::error(404, format!("no program with hash {hash}")),
+            },
+            _ => return Response::error(400, "expected exactly one of `path` or `hash`"),
+        };
+
+        if !request.flag("tokens") {
+            program.tokens.clear();
+        }
+        if !request.flag("comments") {
+            program.comments.clear();
+        }
+
+        match format {
+            Format::Estree => json_response(key, &program),
+            Format::Swc => match source {
+                Some(source) => json_response(key, &to_swc::convert_program(&program, &source)),
+                None => Response::error(400, "the swc format needs `path`, not `hash`"),
+            },
+            Format::Binary => {
+                Response::ok("application/octet-stream", key, binary::encode(&program))
+            }
+        }
+    }
+
+    pub fn serve_tcp(&self, listener: TcpListener) -> io::Result<()> {
+        self.serve(listener.incoming(), |stream: &TcpStream, timeout| {
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(timeout)
+        })
+    }
+
+    #[cfg(unix)]
+    pub fn serve_unix(&self, listener: std::os::unix::net::UnixListener) -> io::Result<()> {
+        use std::os::unix::net::UnixStream;
+        self.serve(listener.incoming(), |stream: &UnixStream, timeout| {
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(timeout)
+        })
+    }
+
+    // Serves each connection on its own thread.  Failing to accept one
+    // connection doesn't stop the others from being served.
+    fn serve<S>(
+        &self,
+        incoming: impl Iterator<Item = io::Result<S>>,
+        set_timeout: impl Fn(&S, Option<Duration>) -> io::Result<()>,
+    ) -> io::Result<()>
+    where
+        S: Send,
+        for<'a> &'a S: Read + Write,
+    {
+        thread::scope(|scope| {
+            for stream in incoming {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        eprintln!("failed to accept connection: {error}");
+                        thread::sleep(ACCEPT_RETRY_DELAY);
+                        continue;
+                    }
+                };
+                if let Err(error) = set_timeout(&stream, self.options.timeout) {
+                    eprintln!("failed to set connection timeout: {error}");
+                    continue;
+                }
+                let slot = self.acquire_connection();
+                scope.spawn(move || {
+                    self.serve_connection(&stream, &stream);
+                    drop(slot);
+                });
+            }
+            Ok(())
+        })
+    }
+
+    // Waits until fewer than `max_connections` connections are being served.
+    fn acquire_connection(&self) -> ConnectionSlot<'_> {
+        let mut connections = self.connections.lock().unwrap();
+        while *connections >= self.options.max_connections.max(1) {
+            connections = self.connection_done.wait(connections).unwrap();
+        }
+        *connections += 1;
+        ConnectionSlot(self)
+    }
+
+    // Handles a single request, connections aren't kept alive.
+    fn serve_connection(&self, input: impl io::Read, mut output: impl Write) {
+        let mut reader = BufReader::new(input).take(MAX_HEAD_LEN);
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            return;
+        }
+
+        // Requests are GETs so the headers aren't needed.
+        let mut header = String::new();
+        let mut ended = false;
+        while !ended && reader.read_line(&mut header).is_ok_and(|len| len > 0) {
+            ended = header == "\r\n" || header == "\n";
+            header.clear();
+        }
+
+        let response = if !ended && reader.limit() == 0 {
+            Response::error(400, "request header too large")
+        } else {
+            match Request::parse(&line) {
+                Some(request) => self.handle(&request),
+                None => Response::error(400, "malformed request line"),
+            }
+        };
+        // The client going away isn't the server's problem.
+        let _ = response.write_to(&mut output);
+    }
+}
+
+// Frees up a connection for the accept loop once dropped, even if serving the
+// connection panicked.
+struct ConnectionSlot<'a>(&'a Server);
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        *self.0.connections.lock().unwrap() -= 1;
+        self.0.connection_done.notify_one();
+    }
+}
+
+fn json_response(key: String, value: &impl serde::Serialize) -> Response {
+    match serde_json::to_vec(value) {
+        Ok(body) => Response::ok("application/json", key, body),
+        Err(error) => Response::error(500, error),
+    }
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b'+' => bytes.push(b' '),
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,405 @@
+// Rust counterpart of ESLint's `SourceCode`, for looking up the tokens,
+// comments and text around nodes.  Like ESLint's, every lookup is a binary
+// search over the program's tokens and comments, which are sorted by range.
+//
+// Ranges and columns are in UTF-16 code units, like the rest of the AST.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::ast;
+use crate::parser::SourceIndex;
+
+// Anything with an ESTree range: nodes, tokens, comments and ranges
+// themselves.
+pub trait Ranged {
+    fn range(&self) -> ast::Range;
+}
+
+impl Ranged for ast::Range {
+    fn range(&self) -> ast::Range {
+        *self
+    }
+}
+
+impl<T: Ranged + ?Sized> Ranged for &T {
+    fn range(&self) -> ast::Range {
+        (**self).range()
+    }
+}
+
+macro_rules! impl_ranged {
+    ($($node:ty,)*) => {
+        $(
+            impl Ranged for $node {
+                fn range(&self) -> ast::Range {
+                    self.range
+                }
+            }
+        )*
+    };
+}
+
+impl_ranged! {
+    ast::Program,
+    ast::DebuggerStatement,
+    ast::ExpressionStatement,
+    ast::Identifier,
+    ast::Literal,
+    ast::CallExpression,
+    ast::SpreadElement,
+    ast::MemberExpression,
+    ast::TokenValue,
+}
+
+impl Ranged for ast::Statement {
+    fn range(&self) -> ast::Range {
+        match self {
+            ast::Statement::DebuggerStatement(stmt) => stmt.range,
+            ast::Statement::ExpressionStatement(stmt) => stmt.range,
+        }
+    }
+}
+
+impl Ranged for ast::Expression {
+    fn range(&self) -> ast::Range {
+        match self {
+            ast::Expression::Literal(expr) => expr.range,
+            ast::Expression::Identifier(expr) => expr.range,
+            ast::Expression::CallExpression(expr) => expr.range,
+            ast::Expression::MemberExpression(expr) => expr.range,
+        }
+    }
+}
+
+impl Ranged for ast::ExprOrSpread {
+    fn range(&self) -> ast::Range {
+        match self {
+            ast::ExprOrSpread::Expr(expr) => expr.range(),
+            ast::ExprOrSpread::Spread(spread) => spread.range,
+        }
+    }
+}
+
+impl Ranged for ast::Prop {
+    fn range(&self) -> ast::Range {
+        match self {
+            ast::Prop::Identifier(ident) => ident.range,
+        }
+    }
+}
+
+impl Ranged for ast::Token {
+    fn range(&self) -> ast::Range {
+        self.value().range
+    }
+}
+
+impl Ranged for ast::Comment {
+    fn range(&self) -> ast::Range {
+        self.value().range
+    }
+}
+
+// What ESLint's token methods return when `includeComments` is set.
+#[derive(Debug, Clone, Copy)]
+pub enum TokenOrComment<'a> {
+    Token(&'a ast::Token),
+    Comment(&'a ast::Comment),
+}
+
+impl<'a> TokenOrComment<'a> {
+    pub fn value(&self) -> &'a ast::TokenValue {
+        match self {
+            TokenOrComment::Token(token) => token.value(),
+            TokenOrComment::Comment(comment) => comment.value(),
+        }
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self, TokenOrComment::Comment(_))
+    }
+}
+
+impl Ranged for TokenOrComment<'_> {
+    fn range(&self) -> ast::Range {
+        self.value().range
+    }
+}
+
+impl Serialize for TokenOrComment<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TokenOrComment::Token(token) => token.serialize(serializer),
+            TokenOrComment::Comment(comment) => comment.serialize(serializer),
+        }
+    }
+}
+
+// ESLint's `{ includeComments, skip }` cursor options.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CursorOptions {
+    pub include_comments: bool,
+    // Number of tokens to skip over.
+    pub skip: usize,
+}
+
+#[derive(Debug)]
+pub struct SourceCode<'a> {
+    index: SourceIndex<'a>,
+    tokens: Vec<TokenOrComment<'a>>,
+    tokens_and_comments: Vec<TokenOrComment<'a>>,
+    // UTF-16 offset of the start of each line.
+    line_starts: Vec<u32>,
+}
+
+impl<'a> SourceCode<'a> {
+    // `program` must have been parsed from `text`.
+    pub fn new(text: &'a str, program: &'a ast::Program) -> Self {
+        let tokens: Vec<_> = program.tokens.iter().map(TokenOrComment::Token).collect();
+
+        let mut tokens_and_comments = Vec::with_capacity(tokens.len() + program.comments.len());
+        let mut comments = program.comments.iter().peekable();
+        for token in &tokens {
+            while let Some(comment) = comments.next_if(|c| c.range().0 < token.range().0) {
+                tokens_and_comments.push(TokenOrComment::Comment(comment));
+            }
+            tokens_and_comments.push(*token);
+        }
+        tokens_and_comments.extend(comments.map(TokenOrComment::Comment));
+
+        let index = SourceIndex::new(text);
+        let line_starts = index
+            .line_starts()
+            .iter()
+            .map(|(_, start)| *start)
+            .collect();
+
+        SourceCode {
+            index,
+            tokens,
+            tokens_and_comments,
+            line_starts,
+        }
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.index.code()
+    }
+
+    // The source text of `node`.
+    pub fn get_text(&self, node: &impl Ranged) -> &'a str {
+        self.get_text_around(node, 0, 0)
+    }
+
+    // The source text of `node` plus `before` and `after` code units on
+    // either side of it, like `getText(node, beforeCount, afterCount)`.
+    pub fn get_text_around(&self, node: &impl Ranged, before: u32, after: u32) -> &'a str {
+        let (start, end) = node.range();
+        let start = self.index.byte_offset(start.saturating_sub(before));
+        let end = self.index.byte_offset(end.saturating_add(after)).max(start);
+        &self.text()[start..end]
+    }
+
+    // Lines without their line breaks.
+    pub fn get_lines(&self) -> Vec<&'a str> {
+        let text = self.text();
+        let line_starts = self.index.line_starts();
+        let mut lines = Vec::with_capacity(line_starts.len());
+        for (i, (start, _)) in line_starts.iter().enumerate() {
+            let line = match line_starts.get(i + 1) {
+                Some((next, _)) => text[*start..*next].trim_end_matches(is_line_break),
+                None => &text[*start..],
+            };
+            lines.push(line);
+        }
+        lines
+    }
+
+    // UTF-16 offset of the start of each line, like `lineStartIndices`.
+    pub fn line_start_indices(&self) -> &[u32] {
+        &self.line_starts
+    }
+
+    pub fn get_loc_from_index(&self, index: u32) -> ast::Position {
+        self.index.position_at(index)
+    }
+
+    pub fn tokens_and_comments(&self) -> &[TokenOrComment<'a>] {
+        &self.tokens_and_comments
+    }
+
+    pub fn get_token_before(
+        &self,
+        node: &impl Ranged,
+        options: CursorOptions,
+    ) -> Option<TokenOrComment<'a>> {
+        let list = self.list(options);
+        let index = list.partition_point(|token| token.range().0 < node.range().0);
+        index.checked_sub(1 + options.skip).map(|index| list[index])
+    }
+
+    pub fn get_token_after(
+        &self,
+        node: &impl Ranged,
+        options: CursorOptions,
+    ) -> Option<TokenOrComment<'a>> {
+        let list = self.list(options);
+        let index = list.partition_point(|token| token.range().0 < node.range().1);
+        list.get(index + options.skip).copied()
+    }
+
+    // The first token inside of `node`.
+    pub fn get_first_token(
+        &self,
+        node: &impl Ranged,
+        options: CursorOptions,
+    ) -> Option<TokenOrComment<'a>> {
+        self.get_tokens(node, options).get(options.skip).copied()
+    }
+
+    // The last token inside of `node`.
+    pub fn get_last_token(
+        &self,
+        node: &impl Ranged,
+        options: CursorOptions,
+    ) -> Option<TokenOrComment<'a>> {
+        let tokens = self.get_tokens(node, options);
+        tokens
+            .len()
+            .checked_sub(1 + options.skip)
+            .map(|index| tokens[index])
+    }
+
+    // Tokens inside of `node`, `options.skip` is ignored.
+    pub fn get_tokens(&self, node: &impl Ranged, options: CursorOptions) -> &[TokenOrComment<'a>] {
+        let (start, end) = node.range();
+        self.slice(self.list(options), start, end)
+    }
+
+    // Tokens after `left` and before `right`, `options.skip` is ignored.
+    pub fn get_tokens_between(
+        &self,
+        left: &impl Ranged,
+        right: &impl Ranged,
+        options: CursorOptions,
+    ) -> &[TokenOrComment<'a>] {
+        self.slice(self.list(options), left.range().1, right.range().0)
+    }
+
+    // Comments directly before `node`, with no tokens in between.
+    pub fn get_comments_before(&self, node: &impl Ranged) -> Vec<&'a ast::Comment> {
+        let all = &self.tokens_and_comments;
+        let end = all.partition_point(|token| token.range().0 < node.range().0);
+        let start = all[..end]
+            .iter()
+            .rposition(|token| !token.is_comment())
+            .map_or(0, |index| index + 1);
+        all[start..end].iter().map(as_comment).collect()
+    }
+
+    // Comments directly after `node`, with no tokens in between.
+    pub fn get_comments_after(&self, node: &impl Ranged) -> Vec<&'a ast::Comment> {
+        let all = &self.tokens_and_comments;
+        let start = all.partition_point(|token| token.range().0 < node.range().1);
+        all[start..]
+            .iter()
+            .take_while(|token| token.is_comment())
+            .map(as_comment)
+            .collect()
+    }
+
+    // Comments inside of `node`.
+    pub fn get_comments_inside(&self, node: &impl Ranged) -> Vec<&'a ast::Comment> {
+        let (start, end) = node.range();
+        self.slice(&self.tokens_and_comments, start, end)
+            .iter()
+            .filter(|token| token.is_comment())
+            .map(as_comment)
+            .collect()
+    }
+
+    // Whether there's any whitespace between the tokens of `first` and
+    // `second`, comments aren't whitespace.  `false` if they overlap.
+    pub fn is_space_between(&self, first: &impl Ranged, second: &impl Ranged) -> bool {
+        let (first, second) = (first.range(), second.range());
+        if first.0 < second.1 && second.0 < first.1 {
+            return false;
+        }
+        let (start, end) = if first.1 <= second.0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        // The last token of `start` through the first token of `end`.
+        let all = &self.tokens_and_comments;
+        let from = all.partition_point(|token| token.range().1 <= start.1);
+        let to = all.partition_point(|token| token.range().0 < end.0);
+        let mut previous_end = match from.checked_sub(1) {
+            Some(index) if all[index].range().0 >= start.0 => all[index].range().1,
+            _ => start.1,
+        };
+        for token in &all[from..to] {
+            if token.range().0 != previous_end {
+                return true;
+            }
+            previous_end = token.range().1;
+        }
+        let end_start = all
+            .get(to)
+            .filter(|token| token.range().1 <= end.1)
+            .map_or(end.0, |token| token.range().0);
+        end_start != previous_end
+    }
+
+    fn list(&self, options: CursorOptions) -> &[TokenOrComment<'a>] {
+        if options.include_comments {
+            &self.tokens_and_comments
+        } else {
+            &self.tokens
+        }
+    }
+
+    // Tokens that are entirely within `start..end`.
+    fn slice<'s>(
+        &self,
+        list: &'s [TokenOrComment<'a>],
+        start: u32,
+        end: u32,
+    ) -> &'s [TokenOrComment<'a>] {
+        let from = list.partition_point(|token| token.range().0 < start);
+        let to = list.partition_point(|token| token.range().1 <= end);
+        &list[from..to.max(from)]
+    }
+}
+
+// Same fields as ESLint's `SourceCode`, other than the AST.
+impl Serialize for SourceCode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("text", self.text())?;
+        map.serialize_entry("lines", &self.get_lines())?;
+        map.serialize_entry("lineStartIndices", &self.line_starts)?;
+        map.serialize_entry("tokensAndComments", &self.tokens_and_comments)?;
+        map.end()
+    }
+}
+
+pub fn to_v8<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    source_code: &SourceCode,
+) -> Result<v8::Local<'s, v8::Value>, serde_v8::Error> {
+    serde_v8::to_v8(scope, source_code)
+}
+
+fn is_line_break(c: char) -> bool {
+    matches!(c, '\r' | '\n' | '\u{2028}' | '\u{2029}')
+}
+
+fn as_comment<'a>(token: &TokenOrComment<'a>) -> &'a ast::Comment {
+    match token {
+        TokenOrComment::Comment(comment) => comment,
+        TokenOrComment::Token(_) => unreachable!("not a comment"),
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use std::{error, fmt, io, process};
+
+use sha2::{Digest, Sha256};
+
+use crate::{ast, binary, parser, schema};
+
+// Bump this when `parser::parse` returns something different for the same
+// source, e.g. after fixing a range, so that programs cached before that
+// aren't returned.  Changes to the shape of `ast::Program` are picked up from
+// its JSON Schema, and changes to the encoding from `binary::VERSION`.
+const SCHEMA_VERSION: u32 = 2;
+
+const EXTENSION: &str = "estb";
+const TMP_EXTENSION: &str = "tmp";
+
+// Temporary files older than this were left behind by writers that died
+// before renaming them.
+const TMP_MAX_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(parser::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "AST store: {error}"),
+            Error::Parse(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<parser::Error> for Error {
+    fn from(error: parser::Error) -> Self {
+        Error::Parse(error)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GcOptions {
+    // Remove programs that haven't been used for longer than this.
+    pub max_age: Option<Duration>,
+    // Remove the least recently used programs until the store is no larger
+    // than this many bytes.
+    pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GcStats {
+    pub removed: usize,
+    // Temporary files left behind by interrupted inserts, their sizes are
+    // included in `removed_bytes`.
+    pub removed_tmp_files: usize,
+    pub removed_bytes: u64,
+    pub kept: usize,
+    pub kept_bytes: u64,
+}
+
+// Caches parsed programs on disk in the binary format, keyed by a hash of
+// everything that affects the result of `parser::parse`.
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Store { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Hex encoded SHA-256 of the schema version, the parse options and the
+    // source.  Each part is length prefixed so that they can't run together.
+    pub fn key(source: &str) -> String {
+        let (syntax, target) = parser::options();
+        let options = format!("{syntax:?} {target:?}");
+        let schema = format!("{SCHEMA_VERSION}/{}/{}", binary::VERSION, schema_hash());
+
+        let mut hasher = Sha256::new();
+        for part in [schema.as_str(), options.as_str(), source] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    // Returns the cached program for `source` if there is one.  Entries that
+    // can't be read or decoded are treated as missing.
+    pub fn get(&self, source: &str) -> Option<ast::Program> {
+        self.get_by_key(&Self::key(source))
+    }
+
+    pub fn get_by_key(&self, key: &str) -> Option<ast::Program> {
+        // Keys come from clients of `server` so they're checked before being
+        // used in a path.
+        if key.len() != 64 || !key.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let path = self.path(key);
+        let bytes = fs::read(&path).ok()?;
+        let program = binary::decode(&bytes).ok()?;
+
+        // The modification time doubles as the last use time for `gc`.
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(program)
+    }
+
+    pub fn insert(&self, source: &str, program: &ast::Program) -> io::Result<()> {
+        let path = self.path(&Self::key(source));
+        fs::create_dir_all(path.parent().unwrap())?;
+
+        // Writes to a temporary file first so that concurrent readers never
+        // see a partially written entry.  The name is unique to this call so
+        // that concurrent writers, e.g. threads of `server`, don't share one.
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = path.with_extension(format!("{}.{count}.{TMP_EXTENSION}", process::id()));
+        fs::write(&tmp, binary::encode(program))?;
+        fs::rename(&tmp, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+
+    pub fn get_or_parse(&self, source: &str) -> Result<ast::Program, Error> {
+        if let Some(program) = self.get(source) {
+            return Ok(program);
+        }
+
+        let program = parser::parse(source.to_string())?;
+        self.insert(source, &program)?;
+        Ok(program)
+    }
+
+    pub fn gc(&self, options: &GcOptions) -> io::Result<GcStats> {
+        let now = SystemTime::now();
+        let mut stats = GcStats::default();
+
+        for (path, modified, len) in self.files(TMP_EXTENSION)? {
+            if now.duration_since(modified).unwrap_or_default() > TMP_MAX_AGE {
+                remove_file(&path)?;
+                stats.removed_tmp_files += 1;
+                stats.removed_bytes += len;
+            }
+        }
+
+        let mut entries = self.files(EXTENSION)?;
+        // Least recently used first.
+        entries.sort_by_key(|entry| entry.1);
+        let mut total: u64 = entries.iter().map(|entry| entry.2).sum();
+
+        for (path, modified, len) in entries {
+            let too_old = options
+                .max_age
+                .is_some_and(|max_age| now.duration_since(modified).unwrap_or_default() > max_age);
+            let too_big = options.max_size.is_some_and(|max_size| total > max_size);
+
+            if too_old || too_big {
+                remove_file(&path)?;
+                total -= len;
+                stats.removed += 1;
+                stats.removed_bytes += len;
+            } else {
+                stats.kept += 1;
+                stats.kept_bytes += len;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Spreads entries over 256 directories so that none get too large.
+        let (prefix, rest) = key.split_at(2);
+        self.dir.join(prefix).join(rest).with_extension(EXTENSION)
+    }
+
+    // The path, modification time and size of the files with `extension`.
+    fn files(&self, extension: &str) -> io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+        let mut entries = vec![];
+        let dirs = match fs::read_dir(&self.dir) {
+            Ok(dirs) => dirs,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(error) => return Err(error),
+        };
+
+        for dir in dirs {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(dir.path())? {
+                let file = file?;
+                let path = file.path();
+                if path.extension().is_some_and(|ext| ext == extension) {
+                    let metadata = file.metadata()?;
+                    entries.push((path, metadata.modified()?, metadata.len()));
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        // Removed by another process in the meantime.
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+// Hash of the JSON Schema of `ast::Program`, which changes along with its
+// shape.  Generating the schema is slow so it's only done once.
+fn schema_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        Sha256::digest(schema::json_schema())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    })
+}
@@ -0,0 +1,148 @@
+use swc_atoms::{Atom, JsWord};
+use swc_common::{BytePos, Span, SyntaxContext};
+use swc_ecma_ast::*;
+
+use crate::ast;
+use crate::parser::SourceIndex;
+
+// Reads an ESTree program, e.g. one produced by a JS tool or edited by a
+// codemod, so that it can be passed to `convert_program` along with its
+// source.
+pub fn from_json(json: &str) -> serde_json::Result<ast::Program> {
+    serde_json::from_str(json)
+}
+
+// `source` is the text the program was parsed from, which is needed to turn
+// its ranges into swc's spans.
+pub fn convert_program(program: &ast::Program, source: &str) -> Module {
+    let converter = Converter {
+        index: SourceIndex::new(source),
+    };
+    Module {
+        span: converter.convert_range(&program.range),
+        body: program
+            .body
+            .iter()
+            .map(|stmt| converter.convert_statement(stmt))
+            .collect(),
+        shebang: None,
+    }
+}
+
+struct Converter<'a> {
+    index: SourceIndex<'a>,
+}
+
+impl Converter<'_> {
+    fn convert_statement(&self, stmt: &ast::Statement) -> ModuleItem {
+        let stmt = match stmt {
+            ast::Statement::DebuggerStatement(ast::DebuggerStatement { range, .. }) => {
+                Stmt::Debugger(DebuggerStmt {
+                    span: self.convert_range(range),
+                })
+            }
+            ast::Statement::ExpressionStatement(ast::ExpressionStatement {
+                range,
+                expression,
+                ..
+            }) => Stmt::Expr(ExprStmt {
+                span: self.convert_range(range),
+                expr: Box::new(self.convert_expression(expression)),
+            }),
+        };
+
+        ModuleItem::Stmt(stmt)
+    }
+
+    fn convert_expression(&self, expr: &ast::Expression) -> Expr {
+        match expr {
+            ast::Expression::Literal(ast::Literal {
+                range, value, raw, ..
+            }) => {
+                let span = self.convert_range(range);
+                let raw = raw.as_ref().map(|raw| Atom::from(raw.as_str()));
+                match value {
+                    ast::Value::String(value) => Expr::Lit(Lit::Str(Str {
+                        span,
+                        value: JsWord::from(value.as_str()),
+                        raw,
+                    })),
+                    ast::Value::Number(value) => Expr::Lit(Lit::Num(Number {
+                        span,
+                        value: *value,
+                        raw,
+                    })),
+                }
+            }
+            ast::Expression::Identifier(ident) => Expr::Ident(self.convert_ident(ident)),
+            ast::Expression::CallExpression(ast::CallExpression {
+                range,
+                callee,
+                arguments,
+                ..
+            }) => Expr::Call(CallExpr {
+                span: self.convert_range(range),
+                callee: Callee::Expr(Box::new(self.convert_expression(callee))),
+                args: arguments
+                    .iter()
+                    .map(|arg| match arg {
+                        ast::ExprOrSpread::Expr(expr) => ExprOrSpread {
+                            spread: None,
+                            expr: Box::new(self.convert_expression(expr)),
+                        },
+                        ast::ExprOrSpread::Spread(ast::SpreadElement {
+                            range, argument, ..
+                        }) => ExprOrSpread {
+                            // swc's span of a spread only covers the `...`.
+                            spread: Some(self.convert_range(&(range.0, range.0 + 3))),
+                            expr: Box::new(self.convert_expression(argument)),
+                        },
+                    })
+                    .collect(),
+                type_args: None,
+            }),
+            ast::Expression::MemberExpression(ast::MemberExpression {
+                range,
+                object,
+                property,
+                computed,
+                ..
+            }) => Expr::Member(MemberExpr {
+                span: self.convert_range(range),
+                obj: Box::new(self.convert_expression(object)),
+                prop: match (property.as_ref(), computed) {
+                    (ast::Prop::Identifier(ident), false) => {
+                        MemberProp::Ident(self.convert_ident(ident))
+                    }
+                    (ast::Prop::Identifier(ident), true) => {
+                        MemberProp::Computed(ComputedPropName {
+                            span: self.convert_range(&ident.range),
+                            expr: Box::new(Expr::Ident(self.convert_ident(ident))),
+                        })
+                    }
+                },
+            }),
+        }
+    }
+
+    fn convert_ident(&self, ident: &ast::Identifier) -> Ident {
+        Ident::new(
+            JsWord::from(ident.name.as_str()),
+            self.convert_range(&ident.range),
+        )
+    }
+
+    // swc's spans are byte positions starting at 1, ESTree ranges are counted in
+    // UTF-16 code units.
+    fn convert_range(&self, range: &ast::Range) -> Span {
+        Span::new(
+            self.byte_pos(range.0),
+            self.byte_pos(range.1),
+            SyntaxContext::empty(),
+        )
+    }
+
+    fn byte_pos(&self, offset: u32) -> BytePos {
+        BytePos(self.index.byte_offset(offset) as u32 + 1)
+    }
+}
@@ -0,0 +1,166 @@
+// Strips the types from TypeScript modules with swc so that V8 can run them.
+// The output has an inline source map, but V8 doesn't use source maps for
+// stack traces so `remap_stack` maps their locations back to the TypeScript.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use base64::Engine;
+use swc_common::comments::SingleThreadedComments;
+use swc_common::source_map::SourceMapGenConfig;
+use swc_common::sync::Lrc;
+use swc_common::{FileName, Globals, Mark, SourceMap, Spanned, GLOBALS};
+use swc_ecma_ast::EsVersion;
+use swc_ecma_codegen::text_writer::JsWriter;
+use swc_ecma_codegen::Emitter;
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+use swc_ecma_transforms_base::{fixer::fixer, resolver};
+use swc_ecma_transforms_typescript::strip;
+use swc_ecma_visit::FoldWith;
+
+use crate::parser::{self, SourceIndex};
+
+pub struct Output {
+    // Ends with a `sourceMappingURL` comment with `source_map` inlined.
+    pub code: String,
+    pub source_map: sourcemap::SourceMap,
+}
+
+pub fn is_typescript(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("ts" | "mts" | "cts")
+    )
+}
+
+pub fn transpile(path: &Path, source: String) -> Result<Output, parser::Error> {
+    GLOBALS.set(&Globals::new(), || {
+        let cm = Lrc::<SourceMap>::default();
+        let fm = cm.new_source_file(FileName::Real(path.to_path_buf()), source);
+        let comments = SingleThreadedComments::default();
+
+        let syntax = Syntax::Typescript(TsConfig::default());
+        let lexer = Lexer::new(
+            syntax,
+            EsVersion::latest(),
+            StringInput::from(&*fm),
+            Some(&comments),
+        );
+        let mut parser = Parser::new_from(lexer);
+
+        let index = SourceIndex::new(&fm.src);
+        let result = parser.parse_program();
+        let mut errors: Vec<_> = parser
+            .take_errors()
+            .iter()
+            .map(|error| index.diagnostic(error.kind().msg().to_string(), &error.span()))
+            .collect();
+        let program = match result {
+            Ok(program) if errors.is_empty() => program,
+            Ok(_) => return Err(parser::Error::Syntax(errors)),
+            Err(error) => {
+                errors.push(index.diagnostic(error.kind().msg().to_string(), &error.span()));
+                return Err(parser::Error::Syntax(errors));
+            }
+        };
+
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        let program = program
+            .fold_with(&mut resolver(unresolved_mark, top_level_mark, true))
+            .fold_with(&mut strip(top_level_mark))
+            .fold_with(&mut fixer(Some(&comments)));
+
+        let mut code = vec![];
+        let mut mappings = vec![];
+        {
+            let mut emitter = Emitter {
+                cfg: Default::default(),
+                cm: cm.clone(),
+                comments: Some(&comments),
+                wr: JsWriter::new(cm.clone(), "\n", &mut code, Some(&mut mappings)),
+            };
+            emitter
+                .emit_program(&program)
+                .expect("writing to a Vec can't fail");
+        }
+
+        let source_map = cm.build_source_map_with_config(&mappings, None, SourceMapConfig);
+        let mut json = vec![];
+        source_map
+            .to_writer(&mut json)
+            .expect("writing to a Vec can't fail");
+
+        let mut code = String::from_utf8(code).expect("swc only emits UTF-8");
+        code.push_str("\n//# sourceMappingURL=data:application/json;base64,");
+        code.push_str(&base64::engine::general_purpose::STANDARD.encode(json));
+        Ok(Output { code, source_map })
+    })
+}
+
+struct SourceMapConfig;
+
+impl SourceMapGenConfig for SourceMapConfig {
+    fn file_name_to_source(&self, f: &FileName) -> String {
+        f.to_string()
+    }
+
+    // So that the map can be used without reading the TypeScript file.
+    fn inline_sources_content(&self, _: &FileName) -> bool {
+        true
+    }
+}
+
+// Replaces each `path:line:column` in `stack` with the line and column in
+// the TypeScript source that `source_map` maps it to.  Both are 1-based, like
+// in V8's stack traces.
+pub fn remap_stack(stack: &str, path: &Path, source_map: &sourcemap::SourceMap) -> String {
+    let location = format!("{}:", path.display());
+    let mut remapped = String::with_capacity(stack.len());
+    let mut rest = stack;
+    while let Some(i) = rest.find(&location) {
+        let end = i + location.len();
+        // Don't match the end of a longer path.
+        let starts_location = rest[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || c == '(');
+        remapped.push_str(&rest[..end]);
+        rest = &rest[end..];
+        if !starts_location {
+            continue;
+        }
+
+        let Some((line, column, len)) = line_and_column(rest) else {
+            continue;
+        };
+        let token = line
+            .checked_sub(1)
+            .zip(column.checked_sub(1))
+            .and_then(|(line, column)| source_map.lookup_token(line, column));
+        if let Some(token) = token {
+            write!(
+                remapped,
+                "{}:{}",
+                token.get_src_line() + 1,
+                token.get_src_col() + 1
+            )
+            .unwrap();
+            rest = &rest[len..];
+        }
+    }
+    remapped.push_str(rest);
+    remapped
+}
+
+// Parses the `line:column` at the start of `s`, returning them along with
+// its length.
+fn line_and_column(s: &str) -> Option<(u32, u32, usize)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let line_len = digits(s);
+    let rest = s[line_len..].strip_prefix(':')?;
+    let column_len = digits(rest);
+    let line = s[..line_len].parse().ok()?;
+    let column = rest[..column_len].parse().ok()?;
+    Some((line, column, line_len + 1 + column_len))
+}
@@ -0,0 +1,77 @@
+// Walks `ast::Program` in the pre-order that follows `VISITOR_KEYS`, numbering
+// the nodes as it goes.  Those numbers are the `NodeId`s that `scope` and
+// `code_path` refer to nodes by, and that `linter/scope_manager.js` maps back
+// to the AST's JS objects.
+
+use crate::ast;
+
+pub type NodeId = usize;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Program(&'a ast::Program),
+    Statement(&'a ast::Statement),
+    Expression(&'a ast::Expression),
+    SpreadElement(&'a ast::SpreadElement),
+    // The property of `member`.
+    Property(&'a ast::MemberExpression),
+}
+
+pub trait Visitor<'a> {
+    // Called before the children of `node` are visited.
+    fn enter(&mut self, _id: NodeId, _node: Node<'a>) {}
+    // Called after the children of `node` are visited.
+    fn leave(&mut self, _id: NodeId, _node: Node<'a>) {}
+}
+
+pub fn traverse<'a>(program: &'a ast::Program, visitor: &mut impl Visitor<'a>) {
+    let mut traversal = Traversal {
+        visitor,
+        next_node: 0,
+    };
+    traversal.visit(Node::Program(program));
+}
+
+struct Traversal<'v, V> {
+    visitor: &'v mut V,
+    next_node: NodeId,
+}
+
+impl<'a, V: Visitor<'a>> Traversal<'_, V> {
+    fn visit(&mut self, node: Node<'a>) {
+        let id = self.next_node;
+        self.next_node += 1;
+
+        self.visitor.enter(id, node);
+        match node {
+            Node::Program(program) => {
+                for stmt in &program.body {
+                    self.visit(Node::Statement(stmt));
+                }
+            }
+            Node::Statement(ast::Statement::DebuggerStatement(_)) => {}
+            Node::Statement(ast::Statement::ExpressionStatement(stmt)) => {
+                self.visit(Node::Expression(&stmt.expression));
+            }
+            Node::Expression(ast::Expression::Literal(_) | ast::Expression::Identifier(_)) => {}
+            Node::Expression(ast::Expression::CallExpression(call)) => {
+                self.visit(Node::Expression(&call.callee));
+                for arg in &call.arguments {
+                    match arg {
+                        ast::ExprOrSpread::Expr(expr) => self.visit(Node::Expression(expr)),
+                        ast::ExprOrSpread::Spread(spread) => {
+                            self.visit(Node::SpreadElement(spread))
+                        }
+                    }
+                }
+            }
+            Node::Expression(ast::Expression::MemberExpression(member)) => {
+                self.visit(Node::Expression(&member.object));
+                self.visit(Node::Property(member));
+            }
+            Node::SpreadElement(spread) => self.visit(Node::Expression(&spread.argument)),
+            Node::Property(_) => {}
+        }
+        self.visitor.leave(id, node);
+    }
+}
@@ -0,0 +1,34 @@
+use std::collections::BTreeMap;
+
+use crate::ast::{self, Node};
+
+// Same shape as `eslint-visitor-keys`' `KEYS`, but only contains the nodes
+// that `parser::parse` can produce.
+pub const VISITOR_KEYS: &[(&str, &[&str])] = &[
+    entry::<ast::Program>(),
+    entry::<ast::DebuggerStatement>(),
+    entry::<ast::ExpressionStatement>(),
+    entry::<ast::Identifier>(),
+    entry::<ast::Literal>(),
+    entry::<ast::CallExpression>(),
+    entry::<ast::SpreadElement>(),
+    entry::<ast::MemberExpression>(),
+];
+
+const fn entry<T: Node>() -> (&'static str, &'static [&'static str]) {
+    (T::TYPE, T::VISITOR_KEYS)
+}
+
+pub fn get_keys(node_type: &str) -> Option<&'static [&'static str]> {
+    VISITOR_KEYS
+        .iter()
+        .find(|(name, _)| *name == node_type)
+        .map(|(_, keys)| *keys)
+}
+
+pub fn to_v8<'a>(
+    scope: &mut v8::HandleScope<'a>,
+) -> Result<v8::Local<'a, v8::Value>, serde_v8::Error> {
+    let keys: BTreeMap<_, _> = VISITOR_KEYS.iter().copied().collect();
+    serde_v8::to_v8(scope, keys)
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::Path;
+
+use ast_store::{arena, parser};
+
+#[test]
+fn serializes_like_ast() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/espree");
+    let mut sources: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "js") && !path.ends_with("generate.js")
+        })
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect();
+    sources.push("foo(bar, \"b\").baz(\"qu\\\"x\");\n".to_string());
+    sources.push(String::new());
+
+    for source in sources {
+        let expected = serde_json::to_string(&parser::parse(source.clone()).unwrap()).unwrap();
+        let actual = serde_json::to_string(&arena::parse(source.clone()).unwrap()).unwrap();
+        assert_eq!(actual, expected, "{source:?}");
+    }
+}
+
+#[test]
+fn reports_the_same_errors() {
+    for source in ["foo(", "if (a) {}", "a.#b;", "x = 1;"] {
+        let expected = parser::parse(source.to_string()).unwrap_err().to_string();
+        let actual = arena::parse(source.to_string()).unwrap_err().to_string();
+        assert_eq!(actual, expected, "{source:?}");
+    }
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::path::Path;
+
+use ast_store::{binary, loader, parser};
+use common::{eval, eval_json, set_global, with_context};
+
+const CODE: &str = "// leading comment\nconsole.log(/* inline */ \"héllo 🌍\", foo.bar(baz));\n\n\n/* block\n   comment */\ndebugger; // trailing\n";
+
+#[test]
+fn round_trips_parsed_programs() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let bytes = binary::encode(&program);
+    let decoded = binary::decode(&bytes).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&decoded).unwrap(),
+        serde_json::to_value(&program).unwrap()
+    );
+
+    let json = serde_json::to_string(&program).unwrap();
+    assert!(
+        bytes.len() * 4 < json.len(),
+        "{} bytes vs {} bytes of JSON",
+        bytes.len(),
+        json.len()
+    );
+}
+
+#[test]
+fn rejects_invalid_input() {
+    let bytes = binary::encode(&parser::parse(CODE.to_string()).unwrap());
+
+    assert!(matches!(
+        binary::decode(b"{\"type\":\"Program\"}"),
+        Err(binary::Error::NotBinaryAst)
+    ));
+
+    let mut other_version = bytes.clone();
+    other_version[binary::MAGIC.len()] = binary::VERSION as u8 + 1;
+    assert!(matches!(
+        binary::decode(&other_version),
+        Err(binary::Error::UnsupportedVersion(_))
+    ));
+
+    for len in 0..bytes.len() {
+        assert!(
+            binary::decode(&bytes[..len]).is_err(),
+            "decoded {len} bytes"
+        );
+    }
+}
+
+// Makes `js/decode_ast.js`'s `decode` a global.
+fn load_js_decoder(scope: &mut v8::HandleScope) {
+    let js = Path::new(env!("CARGO_MANIFEST_DIR")).join("js");
+    loader::init(scope, &js);
+    let module = loader::import(scope, &js.join("decode_ast.js")).unwrap();
+    let decode = module.get_export_value(scope, "decode").unwrap();
+    set_global(scope, "decode", decode);
+}
+
+// Decodes `bytes` with the JS decoder, returning the program as JSON or the
+// message of the error it threw.
+fn decode_in_js(scope: &mut v8::HandleScope, bytes: Vec<u8>) -> Result<serde_json::Value, String> {
+    let bytes = binary::to_v8(scope, bytes);
+    set_global(scope, "bytes", bytes.into());
+    eval(
+        scope,
+        "try { globalThis.result = { ok: decode(bytes) } } \
+         catch (e) { globalThis.result = { error: e.message } }",
+    );
+    let result = eval_json(scope, "result");
+    match result.get("ok") {
+        Some(program) => Ok(program.clone()),
+        None => Err(result["error"].as_str().unwrap().to_string()),
+    }
+}
+
+#[test]
+fn js_decoder_matches_rust() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let bytes = binary::encode(&program);
+    with_context(|scope| {
+        load_js_decoder(scope);
+        assert_eq!(
+            decode_in_js(scope, bytes.clone()),
+            Ok(serde_json::to_value(&program).unwrap())
+        );
+        for len in 0..bytes.len() {
+            assert!(
+                decode_in_js(scope, bytes[..len].to_vec()).is_err(),
+                "decoded {len} bytes"
+            );
+        }
+    });
+}
+
+#[test]
+fn js_decoder_rejects_invalid_strings() {
+    // A program with one string and a literal whose raw value is string `raw`.
+    let program = |string: &[u8], raw: u8| {
+        let mut bytes = binary::MAGIC.to_vec();
+        bytes.extend([binary::VERSION as u8, 1, string.len() as u8]);
+        bytes.extend(string);
+        bytes.extend([1, 0, 1, 0, 1, 1, 1, 0, 1, 3, 0, 1, 0, 0, raw, 0, 0]);
+        bytes
+    };
+    assert!(binary::decode(&program(b"a", 1)).is_ok());
+
+    with_context(|scope| {
+        load_js_decoder(scope);
+        assert!(decode_in_js(scope, program(b"a", 1)).is_ok());
+        for (string, raw) in [
+            (&b"a"[..], 2),
+            (&[0xc3], 1),
+            (&[0xc0, 0x80], 1),
+            (&[0xed, 0xa0, 0x80], 1),
+        ] {
+            assert!(binary::decode(&program(string, raw)).is_err());
+            assert!(decode_in_js(scope, program(string, raw)).is_err());
+        }
+    });
+}
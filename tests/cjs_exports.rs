@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use ast_store::cjs_exports::{analyze, Exports};
+
+fn exports(names: &[&str], reexports: &[&str]) -> Exports {
+    Exports {
+        names: names.iter().map(|name| name.to_string()).collect(),
+        reexports: reexports.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+#[test]
+fn assignments_to_exports() {
+    let source = r#"
+        exports.a = 1;
+        module.exports.b = function () {};
+        exports["c-d"] = 2;
+        module["exports"].e = 3;
+        if (x) {
+            exports.f = exports.a = 4;
+        }
+        other.g = 5;
+        exports[name] = 6;
+    "#;
+    assert_eq!(
+        analyze(Path::new("a.cjs"), source),
+        exports(&["a", "b", "c-d", "e", "f"], &[])
+    );
+}
+
+#[test]
+fn define_property() {
+    let source = r#"
+        Object.defineProperty(exports, "__esModule", { value: true });
+        Object.defineProperty(module.exports, "a", { get() { return 1; } });
+        Object.defineProperty(other, "b", { value: 2 });
+    "#;
+    assert_eq!(
+        analyze(Path::new("a.cjs"), source),
+        exports(&["__esModule", "a"], &[])
+    );
+}
+
+#[test]
+fn object_literals() {
+    let source = r#"
+        const a = 1;
+        module.exports = {
+            a,
+            b: 2,
+            "c": 3,
+            d() {},
+            get e() { return 5; },
+            [f]: 6,
+            ...require("./more"),
+            ...other,
+        };
+    "#;
+    assert_eq!(
+        analyze(Path::new("a.cjs"), source),
+        exports(&["a", "b", "c", "d", "e"], &["./more"])
+    );
+}
+
+#[test]
+fn reexports() {
+    assert_eq!(
+        analyze(Path::new("a.cjs"), r#"module.exports = require("./b");"#),
+        exports(&[], &["./b"])
+    );
+    assert_eq!(
+        analyze(Path::new("a.cjs"), "module.exports = require(name);"),
+        exports(&[], &[])
+    );
+}
+
+#[test]
+fn typescript() {
+    let source = "type N = number;\nexports.a = (value: N): N => value;";
+    assert_eq!(analyze(Path::new("a.cts"), source), exports(&["a"], &[]));
+}
+
+#[test]
+fn syntax_errors() {
+    assert_eq!(
+        analyze(Path::new("a.cjs"), "exports.a = ;"),
+        exports(&[], &[])
+    );
+}
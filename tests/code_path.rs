@@ -0,0 +1,69 @@
+use ast_store::code_path::{self, Event, EventKind, Origin, Phase};
+use ast_store::parser;
+
+#[test]
+fn program_has_a_single_code_path() {
+    let program = parser::parse("foo();\ndebugger;\nbar.baz(qux);".to_string()).unwrap();
+    let analysis = code_path::analyze(&program);
+
+    assert_eq!(analysis.code_paths.len(), 1);
+    let code_path = &analysis.code_paths[0];
+    assert_eq!(code_path.id, "s1");
+    assert_eq!(code_path.origin, Origin::Program);
+    assert_eq!(code_path.upper, None);
+    assert_eq!(code_path.initial_segment, 0);
+    assert_eq!(code_path.returned_segments, vec![0]);
+    assert_eq!(code_path.final_segments, vec![0]);
+    assert!(code_path.thrown_segments.is_empty());
+
+    assert_eq!(analysis.segments.len(), 1);
+    assert_eq!(analysis.segments[0].id, "s1_1");
+    assert!(analysis.segments[0].reachable);
+    assert_eq!(analysis.traverse_segments(0), vec![0]);
+}
+
+#[test]
+fn events_are_emitted_around_the_program() {
+    let program = parser::parse("foo();".to_string()).unwrap();
+    let analysis = code_path::analyze(&program);
+
+    let event = |phase, kind| Event {
+        node: 0,
+        phase,
+        kind,
+    };
+    assert_eq!(
+        analysis.events,
+        vec![
+            event(Phase::Enter, EventKind::CodePathStart { code_path: 0 }),
+            event(Phase::Enter, EventKind::SegmentStart { segment: 0 }),
+            event(Phase::Leave, EventKind::SegmentEnd { segment: 0 }),
+            event(Phase::Leave, EventKind::CodePathEnd { code_path: 0 }),
+        ]
+    );
+}
+
+#[test]
+fn events_serialize_with_eslint_handler_names() {
+    let program = parser::parse("foo();".to_string()).unwrap();
+    let analysis = code_path::analyze(&program);
+    let json = serde_json::to_value(&analysis.events[0]).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "node": 0,
+            "phase": "enter",
+            "kind": { "type": "onCodePathStart", "codePath": 0 },
+        })
+    );
+}
+
+#[test]
+fn analysis_serializes_with_the_names_code_path_js_reads() {
+    let program = parser::parse("foo();".to_string()).unwrap();
+    let json = serde_json::to_value(code_path::analyze(&program)).unwrap();
+    let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, vec!["codePaths", "events", "segments"]);
+    assert_eq!(json["codePaths"][0]["initialSegment"], 0);
+    assert_eq!(json["segments"][0]["nextSegments"], serde_json::json!([]));
+}
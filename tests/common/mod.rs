@@ -0,0 +1,89 @@
+// Helpers shared by the integration tests, each test file that uses them has
+// its own `mod common;` so not all of them are used everywhere.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+// V8 can only be initialized once per process, and its flags have to be set
+// before that.  `--expose-gc` allows `request_garbage_collection_for_testing`.
+pub fn init_v8() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        v8::V8::set_flags_from_string("--harmony-import-attributes --expose-gc");
+        let platform = v8::new_default_platform(0, false).make_shared();
+        v8::V8::initialize_platform(platform);
+        v8::V8::initialize();
+    });
+}
+
+// Runs `f` in a new context in a new isolate.
+pub fn with_context<R>(f: impl FnOnce(&mut v8::HandleScope) -> R) -> R {
+    init_v8();
+    let isolate = &mut v8::Isolate::new(Default::default());
+    let scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(scope);
+    let scope = &mut v8::ContextScope::new(scope, context);
+    f(scope)
+}
+
+// Runs `code` as a script and returns its result, panicking if it throws.
+pub fn eval<'s>(scope: &mut v8::HandleScope<'s>, code: &str) -> v8::Local<'s, v8::Value> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let source = v8::String::new(scope, code).unwrap();
+    let result = v8::Script::compile(scope, source, None).and_then(|script| script.run(scope));
+    match result {
+        Some(result) => result,
+        None => {
+            let exception = scope.exception().unwrap();
+            panic!("{code}: {}", exception.to_rust_string_lossy(scope));
+        }
+    }
+}
+
+// Runs `code` and converts its result with `JSON.stringify`.
+pub fn eval_json(scope: &mut v8::HandleScope, code: &str) -> serde_json::Value {
+    let result = eval(scope, &format!("JSON.stringify({code})"));
+    serde_json::from_str(&result.to_rust_string_lossy(scope)).unwrap()
+}
+
+pub fn set_global(scope: &mut v8::HandleScope, name: &str, value: v8::Local<v8::Value>) {
+    let context = scope.get_current_context();
+    let key = v8::String::new(scope, name).unwrap();
+    context.global(scope).set(scope, key.into(), value);
+}
+
+// A fresh directory under the system temp dir, removed when dropped.  `name`
+// only has to be unique within a test file.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let test = std::env::current_exe().unwrap();
+        let test = test.file_stem().unwrap().to_string_lossy();
+        let dir = std::env::temp_dir().join(format!("{test}-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir.canonicalize().unwrap())
+    }
+
+    // Writes `contents` to `path`, relative to the directory, creating the
+    // directories on the way.
+    pub fn write(&self, path: &str, contents: &str) -> &Self {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        self
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
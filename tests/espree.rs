@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+
+use ast_store::parser;
+use serde_json::Value;
+
+// acorn's `start`/`end` duplicate `range` so we don't emit them.
+const IGNORED_KEYS: &[&str] = &["start", "end"];
+
+fn diff(path: &str, expected: &Value, actual: &Value, diffs: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                if IGNORED_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                let child_path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual_value) => diff(&child_path, expected_value, actual_value, diffs),
+                    None => diffs.push(format!("{child_path}: missing, expected {expected_value}")),
+                }
+            }
+            for (key, actual_value) in actual {
+                if !expected.contains_key(key) {
+                    diffs.push(format!("{path}.{key}: unexpected {actual_value}"));
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                diff(&format!("{path}[{i}]"), expected, actual, diffs);
+            }
+            for (i, expected) in expected.iter().enumerate().skip(actual.len()) {
+                diffs.push(format!(
+                    "{path}[{i}]: missing, expected {}",
+                    summary(expected)
+                ));
+            }
+            for (i, actual) in actual.iter().enumerate().skip(expected.len()) {
+                diffs.push(format!("{path}[{i}]: unexpected {}", summary(actual)));
+            }
+        }
+        (Value::Number(expected), Value::Number(actual))
+            if expected.as_f64() == actual.as_f64() => {}
+        _ => {
+            if expected != actual {
+                diffs.push(format!(
+                    "{path}: expected {}, found {}",
+                    summary(expected),
+                    summary(actual)
+                ));
+            }
+        }
+    }
+}
+
+// Nodes are too big to print in full so we only include their type.
+fn summary(value: &Value) -> String {
+    match value.get("type") {
+        Some(Value::String(node_type)) => format!("{node_type} node"),
+        _ => value.to_string(),
+    }
+}
+
+#[test]
+fn matches_espree_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/espree");
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "js")
+                && path.file_name().is_some_and(|name| name != "generate.js")
+        })
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    let mut failures = vec![];
+    for fixture in fixtures {
+        let code = fs::read_to_string(&fixture).unwrap();
+        let expected: Value =
+            serde_json::from_str(&fs::read_to_string(fixture.with_extension("json")).unwrap())
+                .unwrap();
+        let actual = serde_json::to_value(parser::parse(code).unwrap()).unwrap();
+
+        let mut diffs = vec![];
+        diff("Program", &expected, &actual, &mut diffs);
+        if !diffs.is_empty() {
+            failures.push(format!("{}:\n  {}", fixture.display(), diffs.join("\n  ")));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
@@ -0,0 +1,5 @@
+// leading comment
+console.log(/* inline */ "a", b);
+/* block
+   comment */
+debugger; // trailing
@@ -0,0 +1,22 @@
+// Regenerates the expected espree output for every fixture in this directory.
+// Run `node tests/fixtures/espree/generate.js` after adding a new `.js` file.
+const fs = require("fs");
+const path = require("path");
+const espree = require("espree");
+
+for (const name of fs.readdirSync(__dirname)) {
+  if (!name.endsWith(".js") || name === path.basename(__filename)) {
+    continue;
+  }
+  const code = fs.readFileSync(path.join(__dirname, name), "utf8");
+  const ast = espree.parse(code, {
+    ecmaVersion: "latest",
+    sourceType: "module",
+    range: true,
+    loc: true,
+    tokens: true,
+    comment: true,
+  });
+  const output = path.join(__dirname, name.replace(/\.js$/, ".json"));
+  fs.writeFileSync(output, JSON.stringify(ast, null, 2) + "\n");
+}
@@ -0,0 +1,3 @@
+console.log("hello, ");
+debugger;
+console.log("world!");
@@ -0,0 +1,2 @@
+console.log("héllo 🌍");
+foo.bar(baz);
@@ -0,0 +1,4 @@
+exports.done = false;
+const b = require("./b.cjs");
+exports.bDone = b.done;
+exports.done = true;
@@ -0,0 +1,4 @@
+exports.done = false;
+const a = require("./a.cjs");
+exports.aDoneWhileLoading = a.done;
+exports.done = true;
@@ -0,0 +1,4 @@
+globalThis.counterRuns = (globalThis.counterRuns || 0) + 1;
+
+let count = 0;
+exports.increment = () => ++count;
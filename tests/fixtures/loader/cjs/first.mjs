@@ -0,0 +1 @@
+globalThis.order = ["first.mjs"];
@@ -0,0 +1,7 @@
+exports.answer = 42;
+module.exports.greet = (name) => `hello, ${name}`;
+Object.defineProperty(exports, "lazy", {
+  enumerable: true,
+  get: () => "lazy",
+});
+exports["not an identifier"] = true;
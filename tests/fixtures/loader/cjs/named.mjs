@@ -0,0 +1,10 @@
+import named, { answer, greet, lazy } from "./named.cjs";
+import { answer as reexported } from "./reexport.cjs";
+
+export default {
+  answer,
+  greeting: greet("world"),
+  lazy,
+  reexported,
+  keys: Object.keys(named),
+};
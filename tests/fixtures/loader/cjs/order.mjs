@@ -0,0 +1,4 @@
+import "./first.mjs";
+import "./second.cjs";
+
+globalThis.order.push("order.mjs");
@@ -0,0 +1,6 @@
+module.exports = {
+  dirname: __dirname,
+  filename: __filename,
+  id: module.id,
+  loaded: module.loaded,
+};
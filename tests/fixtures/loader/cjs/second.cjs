@@ -0,0 +1 @@
+globalThis.order.push("second.cjs");
@@ -0,0 +1,2 @@
+// Resolved relative to this module rather than the script that imported it.
+export const load = () => import("./lazy.mjs").then(({ value }) => value);
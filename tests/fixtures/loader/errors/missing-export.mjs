@@ -0,0 +1,3 @@
+import { missing } from "../dynamic/lazy.mjs";
+
+export default missing;
@@ -0,0 +1,3 @@
+import { value } from "./missing.mjs";
+
+export default value;
@@ -0,0 +1,12 @@
+export const config = { rules: ["no-debugger"], level: 2 };
+export const name = "module";
+export const answer = () => 42;
+export const add = (a, b) => a + b;
+export const later = async (value) => {
+  await null;
+  return value * 2;
+};
+export const fails = () => {
+  throw new Error("failed");
+};
+export const never = () => new Promise(() => {});
@@ -0,0 +1,3 @@
+import invalid from "./invalid.json" with { type: "json" };
+
+export default invalid;
@@ -0,0 +1 @@
+globalThis.sideEffect = true;
@@ -0,0 +1,3 @@
+import code from "./side-effect.cjs" with { type: "json" };
+
+export default code;
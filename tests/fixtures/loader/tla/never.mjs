@@ -0,0 +1 @@
+await new Promise(() => {});
@@ -0,0 +1 @@
+await Promise.reject(new Error("rejected"));
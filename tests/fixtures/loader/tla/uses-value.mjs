@@ -0,0 +1,3 @@
+import { value } from "./value.mjs";
+
+export default value + 1;
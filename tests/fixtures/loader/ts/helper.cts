@@ -0,0 +1 @@
+exports.triple = (n: number): number => n * 3;
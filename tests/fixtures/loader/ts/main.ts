@@ -0,0 +1,3 @@
+import { double } from "./util.js";
+
+export default double(21);
@@ -0,0 +1,3 @@
+const { triple } = require("./helper");
+
+module.exports = triple(3);
@@ -0,0 +1,3 @@
+"use strict";
+'other';
+foo("use strict");
@@ -0,0 +1,63 @@
+// Regenerates the expected eslint-scope output for every fixture in this
+// directory, for both source types.  Run `node tests/fixtures/scope/generate.js`
+// after adding a new `.js` file.
+//
+// The output is a summary of the `ScopeManager` that refers to identifiers by
+// `name@offset` so that it can be compared with `scope::analyze`, see
+// `tests/scope.rs`.
+const fs = require("fs");
+const path = require("path");
+const espree = require("espree");
+const eslintScope = require("eslint-scope");
+
+const identifier = (node) => `${node.name}@${node.range[0]}`;
+
+const summarize = (scopeManager) => {
+  const scopes = scopeManager.scopes;
+  const index = (scope) => (scope === null ? null : scopes.indexOf(scope));
+  return scopes.map((scope) => ({
+    type: scope.type,
+    isStrict: scope.isStrict,
+    block: `${scope.block.type}@${scope.block.range[0]}`,
+    upper: index(scope.upper),
+    variableScope: index(scope.variableScope),
+    variables: scope.variables.map((variable) => ({
+      name: variable.name,
+      defs: variable.defs.map((def) => def.type),
+      references: variable.references.map((ref) => identifier(ref.identifier)),
+    })),
+    references: scope.references.map((ref) => ({
+      identifier: identifier(ref.identifier),
+      flag: ref.flag,
+      resolved: ref.resolved && `${ref.resolved.name}@${index(ref.resolved.scope)}`,
+    })),
+    through: scope.through.map((ref) => identifier(ref.identifier)),
+    implicitLeft: scope.implicit
+      ? scope.implicit.left.map((ref) => identifier(ref.identifier))
+      : null,
+  }));
+};
+
+for (const name of fs.readdirSync(__dirname)) {
+  if (!name.endsWith(".js") || name === path.basename(__filename)) {
+    continue;
+  }
+  const code = fs.readFileSync(path.join(__dirname, name), "utf8");
+  const expected = {};
+  for (const sourceType of ["script", "module"]) {
+    const ast = espree.parse(code, {
+      ecmaVersion: "latest",
+      sourceType,
+      range: true,
+    });
+    // Same options as `analyzeScope` in `linter/linter.js`.
+    const scopeManager = eslintScope.analyze(ast, {
+      ignoreEval: true,
+      ecmaVersion: 13,
+      sourceType,
+    });
+    expected[sourceType] = summarize(scopeManager);
+  }
+  const output = path.join(__dirname, name.replace(/\.js$/, ".json"));
+  fs.writeFileSync(output, JSON.stringify(expected, null, 2) + "\n");
+}
@@ -0,0 +1,3 @@
+console.log(foo, bar.baz);
+debugger;
+foo(foo);
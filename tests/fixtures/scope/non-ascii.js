@@ -0,0 +1,2 @@
+é("🌍", ŝ);
+/* 🌍 */ ŝ.é(ŝ);
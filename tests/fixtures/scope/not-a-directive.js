@@ -0,0 +1,3 @@
+debugger;
+'use strict';
+foo();
@@ -0,0 +1 @@
+foo(...bar, ...baz.qux(quux));
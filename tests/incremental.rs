@@ -0,0 +1,153 @@
+use ast_store::incremental::{self, Edit, Strategy};
+use ast_store::parser;
+
+const CODE: &str = "// greeting\nconsole.log(\"héllo 🌍\", name);\n\nfoo.bar(/* none */);\r\ndebugger; // done\nbaz(qux)\n";
+
+// Checks that reparsing gives the same result as a full parse and returns how
+// it got there.
+fn check(source: &str, edit: Edit) -> Option<Strategy> {
+    let program = parser::parse(source.to_string()).unwrap();
+    let new_source = edit.apply(source);
+
+    let expected =
+        parser::parse(new_source.clone()).map(|program| serde_json::to_value(program).unwrap());
+    let actual = incremental::reparse_with_strategy(program, source, &edit)
+        .map(|(program, strategy)| (serde_json::to_value(program).unwrap(), strategy));
+
+    match (expected, actual) {
+        (Ok(expected), Ok((actual, strategy))) => {
+            assert_eq!(
+                actual, expected,
+                "{edit:?} applied to {source:?} = {new_source:?}"
+            );
+            Some(strategy)
+        }
+        (Err(expected), Err(actual)) => {
+            assert_eq!(actual.to_string(), expected.to_string());
+            None
+        }
+        (expected, actual) => {
+            panic!("{edit:?} on {source:?}: expected {expected:?}, got {actual:?}")
+        }
+    }
+}
+
+fn char_boundaries(source: &str) -> Vec<u32> {
+    let mut offsets = vec![0];
+    let mut offset = 0;
+    for c in source.chars() {
+        offset += c.len_utf16() as u32;
+        offsets.push(offset);
+    }
+    offsets
+}
+
+#[test]
+fn matches_full_parse_for_insertions() {
+    let mut reparsed_statements = 0;
+    for offset in char_boundaries(CODE) {
+        for text in ["x", "\n", ";", "🌍", "(a)", "/* c */"] {
+            let strategy = check(
+                CODE,
+                Edit {
+                    range: (offset, offset),
+                    text: text.to_string(),
+                },
+            );
+            if let Some(Strategy::Statement(_)) = strategy {
+                reparsed_statements += 1;
+            }
+        }
+    }
+    // Most insertions are inside of a statement ending with a `;`.
+    assert!(reparsed_statements > 100, "{reparsed_statements}");
+}
+
+#[test]
+fn matches_full_parse_for_deletions_and_replacements() {
+    let offsets = char_boundaries(CODE);
+    for (i, start) in offsets.iter().enumerate() {
+        for end in offsets.iter().skip(i + 1).take(4) {
+            for text in ["", "y", "\r\n"] {
+                check(
+                    CODE,
+                    Edit {
+                        range: (*start, *end),
+                        text: text.to_string(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn reparses_only_the_edited_statement() {
+    let edit = |range, text: &str| Edit {
+        range,
+        text: text.to_string(),
+    };
+    // Renames `name`.
+    assert_eq!(
+        check(CODE, edit((36, 40), "other")),
+        Some(Strategy::Statement(0))
+    );
+    // Inserts an argument before the comment.
+    assert_eq!(
+        check(CODE, edit((52, 52), "a")),
+        Some(Strategy::Statement(1))
+    );
+    // Replaces `debugger;` with an expression.
+    assert_eq!(
+        check(CODE, edit((66, 75), "qux();")),
+        Some(Strategy::Statement(2))
+    );
+}
+
+#[test]
+fn falls_back_to_full_parse() {
+    // Spans two statements.
+    assert_eq!(
+        check(
+            CODE,
+            Edit {
+                range: (36, 66),
+                text: "a);\n".to_string(),
+            },
+        ),
+        Some(Strategy::Full)
+    );
+    // Spans two statements and doesn't parse.
+    assert_eq!(
+        check(
+            CODE,
+            Edit {
+                range: (20, 60),
+                text: "a".to_string(),
+            },
+        ),
+        None
+    );
+    // Past the end of the source.
+    assert_eq!(
+        check(
+            CODE,
+            Edit {
+                range: (1000, 1001),
+                text: "a".to_string(),
+            },
+        ),
+        Some(Strategy::Full)
+    );
+    // The last statement relies on automatic semicolon insertion.
+    assert_eq!(
+        check(
+            CODE,
+            Edit {
+                range: (84, 87),
+                text: "a".to_string(),
+            },
+        ),
+        Some(Strategy::Full)
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use ast_store::{lazy_ast, parser};
+use common::{eval, eval_json, set_global, with_context};
+
+fn sources() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/espree");
+    let mut sources: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "js") && !path.ends_with("generate.js")
+        })
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect();
+    sources.push(String::new());
+    sources
+}
+
+fn collect_garbage(scope: &mut v8::HandleScope) {
+    scope.request_garbage_collection_for_testing(v8::GarbageCollectionType::Full);
+}
+
+#[test]
+fn matches_serde_v8() {
+    with_context(|scope| {
+        for source in sources() {
+            let program = parser::parse(source.clone()).unwrap();
+            let eager = serde_v8::to_v8(scope, &program).unwrap();
+            set_global(scope, "eager", eager);
+            let lazy = lazy_ast::to_v8(scope, program);
+            set_global(scope, "lazy", lazy);
+
+            assert_eq!(
+                eval_json(scope, "lazy"),
+                eval_json(scope, "eager"),
+                "{source:?}"
+            );
+            // Reading a field a second time gets the cached value.
+            assert!(eval(scope, "lazy.body === lazy.body").is_true());
+        }
+    });
+}
+
+#[test]
+fn frees_programs_once_unreachable() {
+    with_context(|scope| {
+        {
+            let scope = &mut v8::HandleScope::new(scope);
+            let program = parser::parse("foo.bar(baz);".to_string()).unwrap();
+            let ast = lazy_ast::to_v8(scope, program);
+            set_global(scope, "ast", ast);
+        }
+        assert_eq!(lazy_ast::retained_programs(scope), 1);
+
+        // Nodes keep the program alive after the root is gone.
+        eval(
+            scope,
+            "globalThis.node = ast.body[0].expression; delete globalThis.ast;",
+        );
+        collect_garbage(scope);
+        assert_eq!(lazy_ast::retained_programs(scope), 1);
+        assert_eq!(
+            eval_json(scope, "[node.callee.object.name, node.arguments[0].name]"),
+            serde_json::json!(["foo", "baz"])
+        );
+
+        eval(scope, "delete globalThis.node;");
+        collect_garbage(scope);
+        assert_eq!(lazy_ast::retained_programs(scope), 0);
+    });
+}
+
+#[test]
+fn reads_fields_through_other_receivers() {
+    with_context(|scope| {
+        let program = parser::parse("foo.bar(baz);".to_string()).unwrap();
+        let ast = lazy_ast::to_v8(scope, program);
+        set_global(scope, "ast", ast);
+
+        assert_eq!(
+            eval_json(
+                scope,
+                r#"[
+                    Object.create(ast).body[0].type,
+                    Reflect.get(ast, "sourceType", {}),
+                    Object.create(Object.create(ast.body[0])).expression.callee.property.name,
+                ]"#
+            ),
+            serde_json::json!(["ExpressionStatement", "module", "bar"])
+        );
+        // The value is cached on the node, not on the receiver.
+        eval(
+            scope,
+            "globalThis.derived = Object.create(ast); derived.tokens;",
+        );
+        assert_eq!(
+            eval_json(
+                scope,
+                r#"[Object.hasOwn(ast, "tokens"), Object.hasOwn(derived, "tokens")]"#
+            ),
+            serde_json::json!([true, false])
+        );
+    });
+}
@@ -0,0 +1,281 @@
+// Runs `linter/scope_manager.js`, `linter/code_path.js` and
+// `linter/traverse.js` on the data the Rust analyses produce, loading them
+// through the loader like the linter does.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ast_store::traverse::{self, NodeId, Visitor};
+use ast_store::{ast, code_path, loader, parser, scope, visitor_keys};
+use serde_json::{json, Value};
+
+mod common;
+use common::{eval, eval_json, set_global, with_context};
+
+// Same summary as `tests/fixtures/scope/generate.js`.
+const SUMMARIZE: &str = r#"
+const identifier = (node) => `${node.name}@${node.range[0]}`;
+
+globalThis.summarize = (scopeManager) => {
+  const scopes = scopeManager.scopes;
+  const index = (scope) => (scope === null ? null : scopes.indexOf(scope));
+  return scopes.map((scope) => ({
+    type: scope.type,
+    isStrict: scope.isStrict,
+    block: `${scope.block.type}@${scope.block.range[0]}`,
+    upper: index(scope.upper),
+    variableScope: index(scope.variableScope),
+    variables: scope.variables.map((variable) => ({
+      name: variable.name,
+      defs: variable.defs.map((def) => def.type),
+      references: variable.references.map((ref) => identifier(ref.identifier)),
+    })),
+    references: scope.references.map((ref) => ({
+      identifier: identifier(ref.identifier),
+      flag: ref.flag,
+      resolved: ref.resolved && `${ref.resolved.name}@${index(ref.resolved.scope)}`,
+    })),
+    through: scope.through.map((ref) => identifier(ref.identifier)),
+    implicitLeft: scope.implicit
+      ? scope.implicit.left.map((ref) => identifier(ref.identifier))
+      : null,
+  }));
+};
+"#;
+
+fn source_type_name(source_type: &ast::SourceType) -> &'static str {
+    match source_type {
+        ast::SourceType::Script => "script",
+        ast::SourceType::Module => "module",
+    }
+}
+
+// Parses `code` and sets the globals that the tests' scripts use: `ast`,
+// `visitorKeys`, `scopeData` and `codePathData`, which `main.rs` passes to
+// `lint` in `linter/linter.js`.
+fn set_linter_globals(scope: &mut v8::HandleScope, code: &str, source_type: ast::SourceType) {
+    let linter = Path::new(env!("CARGO_MANIFEST_DIR")).join("linter");
+    loader::init(scope, &linter);
+
+    let program = parser::parse(code.to_string()).unwrap();
+    let options = scope::Options {
+        source_type,
+        implied_strict: false,
+    };
+    let value = serde_v8::to_v8(scope, &program).unwrap();
+    set_global(scope, "ast", value);
+    let value = visitor_keys::to_v8(scope).unwrap();
+    set_global(scope, "visitorKeys", value);
+    let value = scope::to_v8(scope, &scope::analyze(&program, &options)).unwrap();
+    set_global(scope, "scopeData", value);
+    let value = code_path::to_v8(scope, &code_path::analyze(&program)).unwrap();
+    set_global(scope, "codePathData", value);
+}
+
+fn scope_fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scope");
+    let mut fixtures: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "js")
+                && path.file_name().is_some_and(|name| name != "generate.js")
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+#[test]
+fn scope_manager_matches_eslint_scope_output() {
+    let mut failures = vec![];
+    for fixture in scope_fixtures() {
+        let code = fs::read_to_string(&fixture).unwrap();
+        let expected: Value =
+            serde_json::from_str(&fs::read_to_string(fixture.with_extension("json")).unwrap())
+                .unwrap();
+
+        for source_type in [ast::SourceType::Script, ast::SourceType::Module] {
+            let name = source_type_name(&source_type);
+            let actual = with_context(|scope| {
+                set_linter_globals(scope, &code, source_type);
+                eval(scope, SUMMARIZE);
+                eval_json(
+                    scope,
+                    &format!(
+                        "summarize(require('./scope_manager.js').createScopeManager(\
+                         ast, scopeData, visitorKeys, {{ sourceType: '{name}' }}))"
+                    ),
+                )
+            });
+            if actual != expected[name] {
+                failures.push(format!(
+                    "{} ({name}):\nexpected {}\nfound    {}",
+                    fixture.display(),
+                    expected[name],
+                    actual
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn scope_manager_methods() {
+    let code = "foo(...bar, ...baz.qux(quux));";
+    let actual = with_context(|scope| {
+        set_linter_globals(scope, code, ast::SourceType::Module);
+        eval_json(
+            scope,
+            r#"(() => {
+              const { createScopeManager } = require("./scope_manager.js");
+              const manager = createScopeManager(ast, scopeData, visitorKeys, {
+                sourceType: "module",
+              });
+              const [global, module] = manager.scopes;
+              const foo = ast.body[0].expression.callee;
+              return {
+                isModule: manager.isModule(),
+                acquire: [manager.acquire(ast) === global, manager.acquire(ast, true) === module],
+                release: manager.release(ast),
+                declaredVariables: manager.getDeclaredVariables(ast),
+                upper: [module.upper === global, global.childScopes[0] === module],
+                variableScope: module.variableScope === module,
+                identifier: module.references[0].identifier === foo,
+                from: module.references.every((ref) => ref.from === module),
+                flags: module.references.map((ref) => [
+                  ref.isRead(),
+                  ref.isReadOnly(),
+                  ref.isWrite(),
+                  ref.isStatic(),
+                ]),
+                resolve: module.resolve(foo),
+                isUsedName: [module.isUsedName("foo"), module.isUsedName("qux")],
+                implicit: global.implicit.left.map((ref) => ref.identifier.name),
+              };
+            })()"#,
+        )
+    });
+
+    let read = json!([true, true, false, false]);
+    assert_eq!(
+        actual,
+        json!({
+            "isModule": true,
+            "acquire": [true, true],
+            "release": null,
+            "declaredVariables": [],
+            "upper": [true, true],
+            "variableScope": true,
+            "identifier": true,
+            "from": true,
+            "flags": [read, read, read, read],
+            "resolve": null,
+            "isUsedName": [true, false],
+            "implicit": ["foo", "bar", "baz", "quux"],
+        })
+    );
+}
+
+#[test]
+fn code_paths_are_rebuilt_with_their_events() {
+    let actual = with_context(|scope| {
+        set_linter_globals(scope, "foo();\ndebugger;", ast::SourceType::Script);
+        eval_json(
+            scope,
+            r#"(() => {
+              const { createCodePaths } = require("./code_path.js");
+              const { codePaths, enter, leave } = createCodePaths(ast, codePathData, visitorKeys);
+              const [codePath] = codePaths;
+              const segments = [];
+              codePath.traverseSegments((segment) => segments.push(segment.id));
+              // [handler name, id of the code path or segment, whether the
+              // node is the program]
+              const events = (events) =>
+                events.map(([name, ...args]) => [name, args[0].id, args[args.length - 1] === ast]);
+              return {
+                codePaths: codePaths.map((codePath) => [codePath.id, codePath.origin, codePath.upper]),
+                initialSegment: codePath.initialSegment.id,
+                finalSegments: codePath.finalSegments.map((segment) => segment.id),
+                returnedSegments: codePath.returnedSegments.map((segment) => segment.id),
+                thrownSegments: codePath.thrownSegments.length,
+                segments,
+                enter: events(enter(ast)),
+                leave: events(leave(ast)),
+                statement: [enter(ast.body[0]), leave(ast.body[0])],
+              };
+            })()"#,
+        )
+    });
+
+    assert_eq!(
+        actual,
+        json!({
+            "codePaths": [["s1", "program", null]],
+            "initialSegment": "s1_1",
+            "finalSegments": ["s1_1"],
+            "returnedSegments": ["s1_1"],
+            "thrownSegments": 0,
+            "segments": ["s1_1"],
+            "enter": [
+                ["onCodePathStart", "s1", true],
+                ["onCodePathSegmentStart", "s1_1", true],
+            ],
+            "leave": [
+                ["onCodePathSegmentEnd", "s1_1", true],
+                ["onCodePathEnd", "s1", true],
+            ],
+            "statement": [[], []],
+        })
+    );
+}
+
+// Records the order nodes are entered and left in by id.
+#[derive(Default)]
+struct Events(Vec<String>);
+
+impl Visitor<'_> for Events {
+    fn enter(&mut self, id: NodeId, _node: traverse::Node) {
+        self.0.push(format!("enter {id}"));
+    }
+
+    fn leave(&mut self, id: NodeId, _node: traverse::Node) {
+        self.0.push(format!("leave {id}"));
+    }
+}
+
+#[test]
+fn traversal_matches_node_ids() {
+    let code = "foo(...a.b, c[d], 'e');\ndebugger;";
+    let mut expected = Events::default();
+    traverse::traverse(&parser::parse(code.to_string()).unwrap(), &mut expected);
+
+    let actual = with_context(|scope| {
+        set_linter_globals(scope, code, ast::SourceType::Module);
+        eval_json(
+            scope,
+            r#"(() => {
+              const { traverse } = require("./traverse.js");
+              const ids = new Map();
+              const events = [];
+              traverse(ast, visitorKeys, {
+                enter(node, parent) {
+                  if (parent !== null && !ids.has(parent)) {
+                    throw new Error(`${node.type} entered before its parent`);
+                  }
+                  ids.set(node, ids.size);
+                  events.push(`enter ${ids.get(node)}`);
+                },
+                leave(node) {
+                  events.push(`leave ${ids.get(node)}`);
+                },
+              });
+              return events;
+            })()"#,
+        )
+    });
+
+    assert_eq!(actual, json!(expected.0));
+}
@@ -0,0 +1,556 @@
+use std::path::{Path, PathBuf};
+
+use ast_store::loader::{self, resolve, LoaderError};
+use serde_json::{json, Value};
+
+mod common;
+use common::{eval, eval_json, set_global, with_context, TempDir};
+
+fn js(path: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("js")
+        .join(path)
+        .canonicalize()
+        .unwrap()
+}
+
+#[test]
+fn resolves_relative_to_the_referrer() {
+    assert_eq!(resolve("./foo.js", &js("index.js")).unwrap(), js("foo.js"));
+    assert_eq!(
+        resolve("./rules/foobar.js", &js("index.js")).unwrap(),
+        js("rules/foobar.js")
+    );
+    assert_eq!(
+        resolve("../foo.js", &js("rules/foobar.js")).unwrap(),
+        js("foo.js")
+    );
+}
+
+#[test]
+fn same_module_from_different_directories() {
+    assert_eq!(
+        resolve("./foo.js", &js("index.js")).unwrap(),
+        resolve("../foo.js", &js("rules/foobar.js")).unwrap()
+    );
+}
+
+#[test]
+fn missing_module() {
+    assert!(resolve("./missing.js", &js("index.js")).is_err());
+}
+
+#[test]
+fn typescript_imports_use_javascript_names() {
+    assert_eq!(
+        resolve("./util.js", &fixture("ts/main.ts")).unwrap(),
+        fixture("ts/util.ts")
+    );
+    assert!(resolve("./util.js", &fixture("json/import.mjs")).is_err());
+}
+
+#[test]
+fn error_messages() {
+    let error = LoaderError::NotFound {
+        specifier: "./missing.js".to_string(),
+        referrer: Some(js("index.js")),
+        reason: "not found".to_string(),
+    };
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "cannot find module \"./missing.js\" imported from {}: not found",
+            js("index.js").display()
+        )
+    );
+
+    let error = LoaderError::SyntaxError {
+        path: "rules/broken.js".into(),
+        line: 3,
+        column: 7,
+        message: "SyntaxError: Unexpected token ')'".to_string(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "rules/broken.js:3:7: SyntaxError: Unexpected token ')'"
+    );
+
+    let error = LoaderError::EvaluationError {
+        path: "rules/throws.js".into(),
+        message: "Error: oops".to_string(),
+        stack: Some("Error: oops\n    at rules/throws.js:1:7".to_string()),
+    };
+    assert_eq!(
+        error.to_string(),
+        "error evaluating rules/throws.js: Error: oops\n    at rules/throws.js:1:7"
+    );
+
+    let error = LoaderError::ExportError {
+        path: "index.js".into(),
+        name: "lint".to_string(),
+        message: "not a function".to_string(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "export \"lint\" of index.js: not a function"
+    );
+}
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/loader")
+        .canonicalize()
+        .unwrap()
+        .join(path)
+}
+
+// Runs `f` with `require` and `import()` in scripts resolving relative to the
+// fixtures.
+fn with_loader<R>(f: impl FnOnce(&mut v8::HandleScope) -> R) -> R {
+    with_context(|scope| {
+        loader::init(scope, &fixture(""));
+        f(scope)
+    })
+}
+
+// Imports the fixture at `path` and returns its default export as JSON.
+fn default_export(scope: &mut v8::HandleScope, path: &str) -> Value {
+    let module = loader::import(scope, &fixture(path)).unwrap();
+    let value = module.get_export_value(scope, "default").unwrap();
+    set_global(scope, "result", value);
+    eval_json(scope, "result")
+}
+
+// Imports the fixture at `path`, which has to fail.
+fn import_error(scope: &mut v8::HandleScope, path: &str) -> LoaderError {
+    match loader::import(scope, &fixture(path)) {
+        Ok(_) => panic!("imported {path}"),
+        Err(e) => e,
+    }
+}
+
+// Runs `code`, which evaluates to a promise, and returns what it settles with
+// as `{ "ok": value }` or `{ "error": message }`.
+fn settle(scope: &mut v8::HandleScope, code: &str) -> Value {
+    eval(
+        scope,
+        &format!(
+            "Promise.resolve({code}).then(\
+               (ok) => {{ globalThis.settled = {{ ok }}; }},\
+               (e) => {{ globalThis.settled = {{ error: String(e) }}; }})"
+        ),
+    );
+    scope.perform_microtask_checkpoint();
+    eval_json(scope, "settled")
+}
+
+#[test]
+fn require_caches_modules() {
+    let actual = with_loader(|scope| {
+        eval_json(
+            scope,
+            r#"(() => {
+              const counter = require("./cjs/counter.cjs");
+              return [
+                counter === require("./cjs/counter.cjs"),
+                counter.increment(),
+                require("./cjs/counter.cjs").increment(),
+                counterRuns,
+              ];
+            })()"#,
+        )
+    });
+    assert_eq!(actual, json!([true, 1, 2, 1]));
+}
+
+#[test]
+fn circular_requires_get_the_exports_so_far() {
+    let actual = with_loader(|scope| {
+        eval_json(scope, r#"[require("./cjs/a.cjs"), require("./cjs/b.cjs")]"#)
+    });
+    assert_eq!(
+        actual,
+        json!([
+            { "done": true, "bDone": true },
+            { "done": true, "aDoneWhileLoading": false },
+        ])
+    );
+}
+
+#[test]
+fn module_paths() {
+    let actual = with_loader(|scope| eval_json(scope, r#"require("./cjs/paths.cjs")"#));
+    let filename = fixture("cjs/paths.cjs").display().to_string();
+    assert_eq!(
+        actual,
+        json!({
+            "dirname": fixture("cjs").display().to_string(),
+            "filename": filename,
+            "id": filename,
+            "loaded": false,
+        })
+    );
+}
+
+#[test]
+fn esm_imports_commonjs_by_name() {
+    let actual = with_loader(|scope| default_export(scope, "cjs/named.mjs"));
+    assert_eq!(
+        actual,
+        json!({
+            "answer": 42,
+            "greeting": "hello, world",
+            "lazy": "lazy",
+            "reexported": 42,
+            "keys": ["answer", "greet", "lazy", "not an identifier"],
+        })
+    );
+}
+
+#[test]
+fn commonjs_runs_in_evaluation_order() {
+    let actual = with_loader(|scope| {
+        loader::import(scope, &fixture("cjs/order.mjs")).unwrap();
+        eval_json(scope, "order")
+    });
+    assert_eq!(actual, json!(["first.mjs", "second.cjs", "order.mjs"]));
+}
+
+#[test]
+fn json_modules() {
+    let data = json!({ "name": "data", "list": [1, 2] });
+    with_loader(|scope| {
+        assert_eq!(default_export(scope, "json/import.mjs"), data);
+        assert_eq!(eval_json(scope, r#"require("./json/require.cjs")"#), data);
+        assert_eq!(
+            eval_json(
+                scope,
+                r#"require("./json/data.json") === require("./json/require.cjs")"#
+            ),
+            json!(true)
+        );
+    });
+}
+
+#[test]
+fn json_needs_an_import_attribute() {
+    let error = with_loader(|scope| import_error(scope, "json/missing-attribute.mjs"));
+    let LoaderError::LinkError { path, message } = error else {
+        panic!("{error}");
+    };
+    assert_eq!(path, fixture("json/missing-attribute.mjs"));
+    assert_eq!(
+        message,
+        format!(
+            "TypeError: module {} needs an import attribute of type \"json\"",
+            fixture("json/data.json").display()
+        )
+    );
+}
+
+#[test]
+fn only_json_can_be_imported_as_json() {
+    let (error, side_effect) = with_loader(|scope| {
+        let error = import_error(scope, "json/wrong-type.mjs");
+        (error, eval_json(scope, "globalThis.sideEffect ?? null"))
+    });
+    let LoaderError::LinkError { message, .. } = error else {
+        panic!("{error}");
+    };
+    assert_eq!(
+        message,
+        format!(
+            "TypeError: module {} is not a JSON module",
+            fixture("json/side-effect.cjs").display()
+        )
+    );
+    // The module wasn't run.
+    assert_eq!(side_effect, Value::Null);
+}
+
+#[test]
+fn invalid_json() {
+    let error = with_loader(|scope| import_error(scope, "json/invalid.mjs"));
+    let LoaderError::SyntaxError {
+        path,
+        line,
+        column,
+        message,
+    } = error
+    else {
+        panic!("{error}");
+    };
+    assert_eq!(path, fixture("json/invalid.json"));
+    assert_eq!((line, column), (3, 1));
+    assert!(message.contains("at position 12"), "{message}");
+}
+
+#[test]
+fn dynamic_import() {
+    with_loader(|scope| {
+        assert_eq!(
+            settle(
+                scope,
+                r#"import("./dynamic/lazy.mjs").then((ns) => ns.value)"#
+            ),
+            json!({ "ok": "lazy" })
+        );
+        assert_eq!(
+            settle(
+                scope,
+                r#"import("./dynamic/importer.mjs").then((ns) => ns.load())"#
+            ),
+            json!({ "ok": "lazy" })
+        );
+        assert_eq!(
+            settle(
+                scope,
+                r#"Promise.all([import("./dynamic/lazy.mjs"), import("./dynamic/lazy.mjs")])
+                     .then(([a, b]) => a === b)"#
+            ),
+            json!({ "ok": true })
+        );
+        assert_eq!(
+            settle(
+                scope,
+                r#"import("./cjs/named.cjs").then((ns) => ns.answer)"#
+            ),
+            json!({ "ok": 42 })
+        );
+        assert_eq!(
+            settle(
+                scope,
+                r#"import("./json/data.json", { with: { type: "json" } })
+                     .then((ns) => ns.default.name)"#
+            ),
+            json!({ "ok": "data" })
+        );
+    });
+}
+
+#[test]
+fn dynamic_import_rejects() {
+    with_loader(|scope| {
+        assert_eq!(
+            settle(scope, r#"import("./dynamic/missing.mjs")"#),
+            json!({
+                "error": format!(
+                    "Error: cannot find module \"./dynamic/missing.mjs\" imported from {}",
+                    fixture("[eval]").display()
+                )
+            })
+        );
+        assert_eq!(
+            settle(scope, r#"import("./json/data.json")"#),
+            json!({
+                "error": format!(
+                    "TypeError: module {} needs an import attribute of type \"json\"",
+                    fixture("json/data.json").display()
+                )
+            })
+        );
+    });
+}
+
+#[test]
+fn top_level_await() {
+    let actual = with_loader(|scope| default_export(scope, "tla/uses-value.mjs"));
+    assert_eq!(actual, json!(43));
+}
+
+#[test]
+fn evaluation_errors() {
+    for (fixture_path, expected_message, position) in [
+        ("tla/rejects.mjs", "Error: rejected", ":1:22"),
+        ("tla/throws.mjs", "Error: thrown", ":1:7"),
+    ] {
+        let error = with_loader(|scope| import_error(scope, fixture_path));
+        let LoaderError::EvaluationError {
+            path,
+            message,
+            stack,
+        } = error
+        else {
+            panic!("{error}");
+        };
+        assert_eq!(path, fixture(fixture_path));
+        assert_eq!(message, expected_message);
+        let stack = stack.unwrap();
+        let location = format!("{}{position}", fixture(fixture_path).display());
+        assert!(stack.contains(&location), "{stack}");
+    }
+}
+
+#[test]
+fn top_level_await_that_never_settles() {
+    let error = with_loader(|scope| import_error(scope, "tla/never.mjs"));
+    let LoaderError::EvaluationError { message, .. } = error else {
+        panic!("{error}");
+    };
+    assert_eq!(message, "top-level await never settled");
+}
+
+#[test]
+fn loader_errors() {
+    with_loader(|scope| {
+        let error = import_error(scope, "errors/syntax.mjs");
+        let LoaderError::SyntaxError {
+            path,
+            line,
+            column,
+            message,
+        } = error
+        else {
+            panic!("{error}");
+        };
+        assert_eq!(path, fixture("errors/syntax.mjs"));
+        assert_eq!((line, column), (1, 18));
+        assert!(
+            message.ends_with("SyntaxError: Unexpected token ';'"),
+            "{message}"
+        );
+
+        let error = import_error(scope, "errors/missing-import.mjs");
+        let LoaderError::NotFound {
+            specifier,
+            referrer,
+            ..
+        } = error
+        else {
+            panic!("{error}");
+        };
+        assert_eq!(specifier, "./missing.mjs");
+        assert_eq!(referrer, Some(fixture("errors/missing-import.mjs")));
+
+        let error = import_error(scope, "errors/missing-export.mjs");
+        let LoaderError::LinkError { path, message } = error else {
+            panic!("{error}");
+        };
+        assert_eq!(path, fixture("errors/missing-export.mjs"));
+        assert_eq!(
+            message,
+            "SyntaxError: The requested module '../dynamic/lazy.mjs' \
+             does not provide an export named 'missing'"
+        );
+    });
+}
+
+#[test]
+fn failed_imports_are_retried() {
+    let dir = TempDir::new("retry");
+    dir.write(
+        "a.mjs",
+        "import { b } from \"./b.mjs\";\nexport default b;\n",
+    );
+    with_context(|scope| {
+        loader::init(scope, &dir.0);
+        let error = match loader::import(scope, &dir.path("a.mjs")) {
+            Ok(_) => panic!("imported a.mjs without b.mjs"),
+            Err(e) => e,
+        };
+        assert!(matches!(error, LoaderError::NotFound { .. }), "{error}");
+
+        dir.write("b.mjs", "export const b = \"b\";\n");
+        let module = loader::import(scope, &dir.path("a.mjs")).unwrap();
+        let b: String = module.get_export(scope, "default").unwrap();
+        assert_eq!(b, "b");
+    });
+}
+
+#[test]
+fn failed_cycles_are_retried() {
+    let dir = TempDir::new("cycle");
+    dir.write(
+        "a.mjs",
+        "import \"./b.mjs\";\nimport { c } from \"./c.mjs\";\nexport default c;\n",
+    )
+    .write("b.mjs", "export { default } from \"./a.mjs\";\n");
+    with_context(|scope| {
+        loader::init(scope, &dir.0);
+        for path in ["a.mjs", "b.mjs"] {
+            let error = match loader::import(scope, &dir.path(path)) {
+                Ok(_) => panic!("imported {path} without c.mjs"),
+                Err(e) => e,
+            };
+            // Rather than linking `b.mjs` without `a.mjs`.
+            assert!(matches!(error, LoaderError::NotFound { .. }), "{error}");
+        }
+
+        dir.write("c.mjs", "export const c = \"c\";\n");
+        let module = loader::import(scope, &dir.path("b.mjs")).unwrap();
+        let c: String = module.get_export(scope, "default").unwrap();
+        assert_eq!(c, "c");
+    });
+}
+
+#[test]
+fn module_exports() {
+    with_loader(|scope| {
+        let module = loader::import(scope, &fixture("exports/module.mjs")).unwrap();
+        assert_eq!(module.path(), fixture("exports/module.mjs"));
+        assert_eq!(
+            module.export_names(scope),
+            ["add", "answer", "config", "fails", "later", "name", "never"]
+        );
+
+        let config: Value = module.get_export(scope, "config").unwrap();
+        assert_eq!(config, json!({ "rules": ["no-debugger"], "level": 2 }));
+        let answer: i32 = module.call_export(scope, "answer", ()).unwrap();
+        assert_eq!(answer, 42);
+        let sum: i32 = module.call_export(scope, "add", (1, 2)).unwrap();
+        assert_eq!(sum, 3);
+        let doubled: i32 = module.call_export(scope, "later", (21,)).unwrap();
+        assert_eq!(doubled, 42);
+    });
+}
+
+#[test]
+fn module_export_errors() {
+    with_loader(|scope| {
+        let module = loader::import(scope, &fixture("exports/module.mjs")).unwrap();
+        let export_error = |error: LoaderError| match error {
+            LoaderError::ExportError { name, message, .. } => (name, message),
+            error => panic!("{error}"),
+        };
+
+        let error = module.get_export::<Value>(scope, "missing").unwrap_err();
+        assert_eq!(
+            export_error(error),
+            ("missing".to_string(), "no such export".to_string())
+        );
+        let error = module.call_export::<_, ()>(scope, "name", ()).unwrap_err();
+        assert_eq!(
+            export_error(error),
+            ("name".to_string(), "not a function".to_string())
+        );
+        let error = module.call_export::<_, ()>(scope, "never", ()).unwrap_err();
+        assert_eq!(
+            export_error(error),
+            (
+                "never".to_string(),
+                "returned a promise that never settled".to_string()
+            )
+        );
+        let error = module.get_export::<u32>(scope, "name").unwrap_err();
+        assert_eq!(export_error(error).0, "name");
+
+        let error = module.call_export::<_, ()>(scope, "fails", ()).unwrap_err();
+        let LoaderError::EvaluationError { path, message, .. } = error else {
+            panic!("{error}");
+        };
+        assert_eq!(path, fixture("exports/module.mjs"));
+        assert_eq!(message, "Error: failed");
+    });
+}
+
+#[test]
+fn typescript_modules() {
+    with_loader(|scope| {
+        assert_eq!(default_export(scope, "ts/main.ts"), json!(42));
+        assert_eq!(default_export(scope, "ts/require.cts"), json!(9));
+        assert_eq!(
+            eval_json(scope, r#"require("./ts/helper").triple(2)"#),
+            json!(6)
+        );
+    });
+}
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ast_store::bundle;
+use ast_store::module_source::{Disk, Layered, MemorySource, ModuleSource};
+use ast_store::resolver::{format_in, resolve, Format, Options};
+
+fn memory() -> MemorySource {
+    let mut source = MemorySource::new();
+    source.insert("/project/package.json", r#"{ "type": "module" }"#);
+    source.insert("/project/index.js", "import './lib/a.js';");
+    source.insert("/project/lib/a.js", "export const a = 1;");
+    source.insert(
+        "/project/node_modules/dep/package.json",
+        r#"{ "exports": { "require": "./main.cjs", "default": "./main.js" } }"#,
+    );
+    source.insert("/project/node_modules/dep/main.js", "");
+    source.insert("/project/node_modules/dep/main.cjs", "");
+    source
+}
+
+#[test]
+fn memory_files_and_directories() {
+    let source = memory();
+    assert!(source.is_file(Path::new("/project/lib/a.js")));
+    assert!(!source.is_dir(Path::new("/project/lib/a.js")));
+    assert!(source.is_dir(Path::new("/project/lib")));
+    assert!(source.is_dir(Path::new("/")));
+    assert!(!source.exists(Path::new("/project/li")));
+    assert!(!source.exists(Path::new("project/lib/a.js")));
+    assert_eq!(
+        source
+            .read_to_string(Path::new("/project/lib/../index.js"))
+            .unwrap(),
+        "import './lib/a.js';"
+    );
+    assert_eq!(
+        source
+            .canonicalize(Path::new("/project/./lib/../lib/a.js"))
+            .unwrap(),
+        PathBuf::from("/project/lib/a.js")
+    );
+    assert!(source.canonicalize(Path::new("/project/b.js")).is_err());
+}
+
+#[test]
+fn resolves_in_memory() {
+    let source: Rc<dyn ModuleSource> = Rc::new(memory());
+    let esm = Options {
+        source: source.clone(),
+        ..Options::esm()
+    };
+    let require = Options {
+        source: source.clone(),
+        ..Options::require()
+    };
+    let referrer = Path::new("/project/index.js");
+
+    assert_eq!(
+        resolve("./lib/a.js", referrer, &esm).unwrap(),
+        PathBuf::from("/project/lib/a.js")
+    );
+    assert_eq!(
+        resolve("./lib/a", referrer, &require).unwrap(),
+        PathBuf::from("/project/lib/a.js")
+    );
+    assert_eq!(
+        resolve("dep", referrer, &esm).unwrap(),
+        PathBuf::from("/project/node_modules/dep/main.js")
+    );
+    assert_eq!(
+        resolve("dep", referrer, &require).unwrap(),
+        PathBuf::from("/project/node_modules/dep/main.cjs")
+    );
+    assert!(resolve("./lib", referrer, &esm).is_err());
+    assert_eq!(format_in(&*source, referrer), Format::Module);
+}
+
+#[test]
+fn embedded_bundle() {
+    let bundle = bundle!("/builtin", ["js/package.json", "js/bar.js"]);
+    assert_eq!(bundle.root(), Path::new("/builtin"));
+    assert!(bundle.is_file(Path::new("/builtin/js/bar.js")));
+    assert!(bundle.is_dir(Path::new("/builtin/js")));
+    assert_eq!(
+        bundle
+            .read_to_string(Path::new("/builtin/js/bar.js"))
+            .unwrap(),
+        include_str!("../js/bar.js")
+    );
+    assert_eq!(
+        format_in(&bundle, Path::new("/builtin/js/bar.js")),
+        Format::Module
+    );
+}
+
+#[test]
+fn layers_shadow_later_layers() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut overrides = MemorySource::new();
+    overrides.insert(manifest_dir.join("js/bar.js"), "export const bar = 'baz';");
+    let source = Layered::new(vec![Rc::new(overrides), Rc::new(Disk)]);
+
+    assert_eq!(
+        source
+            .read_to_string(&manifest_dir.join("js/bar.js"))
+            .unwrap(),
+        "export const bar = 'baz';"
+    );
+    assert_eq!(
+        source
+            .read_to_string(&manifest_dir.join("js/foo.js"))
+            .unwrap(),
+        include_str!("../js/foo.js")
+    );
+    assert!(source.is_dir(&manifest_dir.join("js/rules")));
+    assert!(source
+        .read_to_string(&manifest_dir.join("js/missing.js"))
+        .is_err());
+}
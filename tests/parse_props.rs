@@ -0,0 +1,139 @@
+use ast_store::{ast, invariants, parser};
+use proptest::prelude::*;
+
+// Words that can't be used as identifiers in a module, or that the parser
+// doesn't support as property names yet.
+const RESERVED: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+fn identifier() -> impl Strategy<Value = String> {
+    "[a-z_$][a-z0-9_$]{0,6}".prop_filter("reserved word", |name| !RESERVED.contains(&name.as_str()))
+}
+
+// Only the syntax that the parser supports, so that every program parses.
+fn expression() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        identifier(),
+        "\"([a-z é🌍]|\\\\[\\\\n\"]){0,6}\"",
+        "'([a-z é🌍]|\\\\[\\\\n']){0,6}'",
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            (inner.clone(), identifier())
+                .prop_map(|(object, property)| format!("{object}.{property}")),
+            (inner.clone(), prop::collection::vec(argument(inner), 0..4))
+                .prop_map(|(callee, args)| format!("{callee}({})", args.join(", "))),
+        ]
+    })
+}
+
+fn argument(expression: BoxedStrategy<String>) -> impl Strategy<Value = String> {
+    (any::<bool>(), expression).prop_map(
+        |(spread, expr)| {
+            if spread {
+                format!("...{expr}")
+            } else {
+                expr
+            }
+        },
+    )
+}
+
+fn trivia() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::from("\n")),
+        Just(String::from(" ")),
+        Just(String::from("\r\n")),
+        "/\\*[a-z é\n]{0,8}\\*/",
+        "//[a-z é]{0,8}\n",
+    ]
+}
+
+fn program() -> impl Strategy<Value = String> {
+    let statement = prop_oneof![
+        Just(String::from("debugger;")),
+        // Without a semicolon, the next statement has to be on another line.
+        Just(String::from("debugger\n")),
+        expression().prop_map(|expr| format!("{expr};")),
+    ];
+    prop::collection::vec((trivia(), statement), 0..8).prop_map(|items| {
+        items
+            .into_iter()
+            .map(|(trivia, statement)| format!("{trivia}{statement}"))
+            .collect()
+    })
+}
+
+// Found by `arbitrary_input_never_panics` when files were parsed as
+// declaration files.
+#[test]
+fn assignments_never_panic() {
+    assert!(parser::parse("a = 1;".to_string()).is_err());
+}
+
+fn check(program: &ast::Program) -> Result<(), TestCaseError> {
+    invariants::check(program).map_err(TestCaseError::fail)
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_input_never_panics(code in any::<String>()) {
+        if let Ok(program) = parser::parse(code) {
+            check(&program)?;
+        }
+    }
+
+    #[test]
+    fn generated_programs_satisfy_invariants(code in program()) {
+        match parser::parse(code.clone()) {
+            Ok(program) => check(&program)?,
+            Err(error) => return Err(TestCaseError::fail(format!("{code:?}: {error}"))),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use ast_store::ast;
+use ast_store::parser;
+use ast_store::printer::{print, PrintOptions};
+
+fn print_with_source(program: &ast::Program, source: &str) -> String {
+    print(
+        program,
+        &PrintOptions {
+            source: Some(source),
+        },
+    )
+}
+
+fn expression(program: &mut ast::Program, i: usize) -> &mut ast::Expression {
+    match &mut program.body[i] {
+        ast::Statement::ExpressionStatement(stmt) => &mut stmt.expression,
+        stmt => panic!("expected an expression statement, found {stmt:?}"),
+    }
+}
+
+fn rename(expr: &mut ast::Expression, name: &str) {
+    match expr {
+        ast::Expression::Identifier(ident) => ident.name = name.to_string(),
+        ast::Expression::CallExpression(call) => rename(&mut call.callee, name),
+        expr => panic!("expected an identifier or call, found {expr:?}"),
+    }
+}
+
+fn number(value: f64) -> ast::Expression {
+    let position = || ast::Position { line: 1, column: 0 };
+    ast::Expression::Literal(ast::Literal {
+        loc: ast::Location {
+            start: position(),
+            end: position(),
+        },
+        range: (0, 0),
+        value: ast::Value::Number(value),
+        raw: None,
+    })
+}
+
+#[test]
+fn prints_without_source() {
+    let program = parser::parse("foo ( 'a',b.c, ... d )\ndebugger".to_string()).unwrap();
+    assert_eq!(
+        print(&program, &PrintOptions::default()),
+        "foo('a', b.c, ...d);\ndebugger;\n"
+    );
+}
+
+#[test]
+fn preserves_unmodified_statements() {
+    let code = "foo ( 'a',b.c ) ;\n\n  debugger ;\n";
+    let program = parser::parse(code.to_string()).unwrap();
+    assert_eq!(
+        print_with_source(&program, code),
+        "foo ( 'a',b.c ) ;\ndebugger ;\n"
+    );
+}
+
+#[test]
+fn reprints_statements_relying_on_asi() {
+    let code = "foo ( 'a' )\ndebugger\n";
+    let program = parser::parse(code.to_string()).unwrap();
+    assert_eq!(print_with_source(&program, code), "foo('a');\ndebugger;\n");
+}
+
+#[test]
+fn reprints_modified_statements() {
+    let code = "foo ( 'a' ) ;\nbar ( 'b' ) ;\n";
+    let mut program = parser::parse(code.to_string()).unwrap();
+    rename(expression(&mut program, 1), "baz");
+    assert_eq!(
+        print_with_source(&program, code),
+        "foo ( 'a' ) ;\nbaz('b');\n"
+    );
+}
+
+#[test]
+fn preserves_non_ascii_statements() {
+    let code = "é ( '🌍' ) ;\nfoo ( '🌍' ) ;\n";
+    let mut program = parser::parse(code.to_string()).unwrap();
+    rename(expression(&mut program, 0), "bar");
+    assert_eq!(
+        print_with_source(&program, code),
+        "bar('🌍');\nfoo ( '🌍' ) ;\n"
+    );
+}
+
+#[test]
+fn places_comments() {
+    let code = "// a\nfoo(/* b */ x); // c\n/* d */ bar(/* e */ y);\n// f\n";
+    let mut program = parser::parse(code.to_string()).unwrap();
+    assert_eq!(
+        print_with_source(&program, code),
+        "// a\nfoo(/* b */ x);\n// c\n/* d */\nbar(/* e */ y);\n// f\n"
+    );
+
+    // Comments inside of a modified statement move before it.
+    rename(expression(&mut program, 1), "baz");
+    assert_eq!(
+        print_with_source(&program, code),
+        "// a\nfoo(/* b */ x);\n// c\n/* d */\n/* e */\nbaz(y);\n// f\n"
+    );
+}
+
+#[test]
+fn prints_numbers() {
+    let mut program = parser::parse("a.b;\nc;".to_string()).unwrap();
+    let cases = [
+        (1.0, "1"),
+        (0.5, "0.5"),
+        (-2.0, "-2"),
+        (f64::NAN, "NaN"),
+        (f64::INFINITY, "Infinity"),
+        (f64::NEG_INFINITY, "-Infinity"),
+    ];
+    for (value, expected) in cases {
+        *expression(&mut program, 1) = number(value);
+        match expression(&mut program, 0) {
+            ast::Expression::MemberExpression(member) => *member.object = number(value),
+            expr => panic!("expected a member expression, found {expr:?}"),
+        }
+        assert_eq!(
+            print(&program, &PrintOptions::default()),
+            format!("({expected}).b;\n{expected};\n")
+        );
+    }
+}
+
+#[test]
+fn reprints_edited_literals() {
+    let code = "foo ( 'a' ) ;\nbar ( 'b' ) ;\n";
+    let mut program = parser::parse(code.to_string()).unwrap();
+    match expression(&mut program, 1) {
+        ast::Expression::CallExpression(call) => match &mut call.arguments[0] {
+            ast::ExprOrSpread::Expr(ast::Expression::Literal(literal)) => {
+                literal.value = ast::Value::String("c\n".to_string());
+            }
+            arg => panic!("expected a literal, found {arg:?}"),
+        },
+        expr => panic!("expected a call, found {expr:?}"),
+    }
+    assert_eq!(
+        print_with_source(&program, code),
+        "foo ( 'a' ) ;\nbar(\"c\\n\");\n"
+    );
+    assert_eq!(
+        print(&program, &PrintOptions::default()),
+        "foo('a');\nbar(\"c\\n\");\n"
+    );
+}
+
+#[test]
+fn keeps_raw_literals_that_match() {
+    let code = r"foo('a', 'b\
+c');";
+    let program = parser::parse(code.to_string()).unwrap();
+    assert_eq!(
+        print(&program, &PrintOptions::default()),
+        format!("{code}\n")
+    );
+}
@@ -0,0 +1,267 @@
+use std::path::{Path, PathBuf};
+
+use ast_store::resolver::{format, resolve, Error, Format, Options};
+
+mod common;
+use common::TempDir;
+
+// A project with a few packages in node_modules, one level up from `src`.
+fn project(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+    dir.write(
+        "package.json",
+        r##"{
+            "name": "app",
+            "exports": { ".": "./src/index.js", "./utils": "./src/utils.js" },
+            "imports": {
+                "#internal/*": "./src/internal/*.js",
+                "#dep": { "node": "plain", "default": "./src/missing.js" }
+            }
+        }"##,
+    )
+    .write("src/index.js", "")
+    .write("src/utils.js", "")
+    .write("src/internal/a.js", "")
+    .write("src/dir/index.js", "")
+    .write("src/lib.js", "")
+    .write(
+        "node_modules/conditional/package.json",
+        r#"{
+            "exports": {
+                ".": { "require": "./main.cjs", "import": "./main.mjs", "default": "./main.js" },
+                "./feature": [{ "worker": "./worker.js" }, "./feature.js"],
+                "./lib/*": "./lib/*.js",
+                "./lib/private/*": null
+            }
+        }"#,
+    )
+    .write("node_modules/conditional/main.cjs", "")
+    .write("node_modules/conditional/main.mjs", "")
+    .write("node_modules/conditional/main.js", "")
+    .write("node_modules/conditional/feature.js", "")
+    .write("node_modules/conditional/lib/a.js", "")
+    .write("node_modules/conditional/lib/private/b.js", "")
+    .write(
+        "node_modules/plain/package.json",
+        r#"{ "main": "lib/entry" }"#,
+    )
+    .write("node_modules/plain/lib/entry.js", "")
+    .write("node_modules/plain/other.js", "")
+    .write("node_modules/no-main/index.js", "")
+    .write(
+        "node_modules/@scope/pkg/package.json",
+        r#"{ "exports": "./pkg.js" }"#,
+    )
+    .write("node_modules/@scope/pkg/pkg.js", "");
+    dir
+}
+
+fn esm(specifier: &str, referrer: &Path) -> Result<PathBuf, Error> {
+    resolve(specifier, referrer, &Options::esm())
+}
+
+#[test]
+fn relative_paths() {
+    let dir = project("relative");
+    let referrer = dir.path("src/index.js");
+
+    assert_eq!(esm("./lib.js", &referrer).unwrap(), dir.path("src/lib.js"));
+    assert_eq!(
+        esm("../node_modules/plain/other.js", &referrer).unwrap(),
+        dir.path("node_modules/plain/other.js")
+    );
+    // ESM doesn't add extensions or look for index files.
+    assert!(matches!(
+        esm("./lib", &referrer),
+        Err(Error::NotFound { .. })
+    ));
+    assert!(matches!(
+        esm("./dir", &referrer),
+        Err(Error::UnsupportedDirectoryImport(_))
+    ));
+}
+
+#[test]
+fn exports_conditions() {
+    let dir = project("conditions");
+    let referrer = dir.path("src/index.js");
+
+    assert_eq!(
+        esm("conditional", &referrer).unwrap(),
+        dir.path("node_modules/conditional/main.mjs")
+    );
+    assert_eq!(
+        resolve("conditional", &referrer, &Options::require()).unwrap(),
+        dir.path("node_modules/conditional/main.cjs")
+    );
+    let options = Options {
+        conditions: vec![],
+        extensions: vec![],
+        ..Options::esm()
+    };
+    assert_eq!(
+        resolve("conditional", &referrer, &options).unwrap(),
+        dir.path("node_modules/conditional/main.js")
+    );
+    assert_eq!(
+        esm("conditional/feature", &referrer).unwrap(),
+        dir.path("node_modules/conditional/feature.js")
+    );
+    assert_eq!(
+        esm("@scope/pkg", &referrer).unwrap(),
+        dir.path("node_modules/@scope/pkg/pkg.js")
+    );
+}
+
+#[test]
+fn exports_patterns() {
+    let dir = project("patterns");
+    let referrer = dir.path("src/index.js");
+
+    assert_eq!(
+        esm("conditional/lib/a", &referrer).unwrap(),
+        dir.path("node_modules/conditional/lib/a.js")
+    );
+    assert!(matches!(
+        esm("conditional/lib/private/b", &referrer),
+        Err(Error::PackagePathNotExported { .. })
+    ));
+    assert!(matches!(
+        esm("conditional/main.js", &referrer),
+        Err(Error::PackagePathNotExported { .. })
+    ));
+    assert!(matches!(
+        esm("@scope/pkg/pkg.js", &referrer),
+        Err(Error::PackagePathNotExported { .. })
+    ));
+}
+
+#[test]
+fn main_fallback() {
+    let dir = project("main");
+    let referrer = dir.path("src/nested/deeper/file.js");
+
+    assert_eq!(
+        esm("plain", &referrer).unwrap(),
+        dir.path("node_modules/plain/lib/entry.js")
+    );
+    assert_eq!(
+        esm("no-main", &referrer).unwrap(),
+        dir.path("node_modules/no-main/index.js")
+    );
+    // Without `exports` any file can be imported, but only by its full path.
+    assert_eq!(
+        esm("plain/other.js", &referrer).unwrap(),
+        dir.path("node_modules/plain/other.js")
+    );
+    assert!(esm("plain/other", &referrer).is_err());
+    assert_eq!(
+        resolve("plain/other", &referrer, &Options::require()).unwrap(),
+        dir.path("node_modules/plain/other.js")
+    );
+    assert!(matches!(
+        esm("missing", &referrer),
+        Err(Error::NotFound { .. })
+    ));
+}
+
+#[test]
+fn self_reference_and_imports() {
+    let dir = project("imports");
+    let referrer = dir.path("src/index.js");
+
+    assert_eq!(esm("app", &referrer).unwrap(), dir.path("src/index.js"));
+    assert_eq!(
+        esm("app/utils", &referrer).unwrap(),
+        dir.path("src/utils.js")
+    );
+    assert_eq!(
+        esm("#internal/a", &referrer).unwrap(),
+        dir.path("src/internal/a.js")
+    );
+    assert_eq!(
+        esm("#dep", &referrer).unwrap(),
+        dir.path("node_modules/plain/lib/entry.js")
+    );
+    assert!(matches!(
+        esm("#unknown", &referrer),
+        Err(Error::PackageImportNotDefined { .. })
+    ));
+}
+
+#[test]
+fn builtins() {
+    let dir = project("builtins");
+    assert!(matches!(
+        esm("node:fs", &dir.path("src/index.js")),
+        Err(Error::UnsupportedBuiltin(name)) if name == "fs"
+    ));
+}
+
+#[test]
+fn formats() {
+    let dir = project("formats");
+    dir.write("esm/package.json", r#"{ "type": "module" }"#)
+        .write("esm/a.js", "")
+        .write("esm/b.cjs", "")
+        .write("esm/c.json", "{}");
+
+    assert_eq!(format(&dir.path("src/index.js")), Format::CommonJs);
+    assert_eq!(format(&dir.path("src/index.mjs")), Format::Module);
+    assert_eq!(format(&dir.path("esm/a.js")), Format::Module);
+    assert_eq!(format(&dir.path("esm/b.cjs")), Format::CommonJs);
+    assert_eq!(format(&dir.path("esm/c.json")), Format::Json);
+    assert_eq!(format(&dir.path("src/rule.mts")), Format::Module);
+    assert_eq!(format(&dir.path("esm/rule.cts")), Format::CommonJs);
+    assert_eq!(
+        format(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/js/index.js"
+        ))),
+        Format::Module
+    );
+    assert_eq!(
+        format(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/linter/linter.js"
+        ))),
+        Format::CommonJs
+    );
+}
+
+#[test]
+fn typescript_sources() {
+    let dir = project("typescript");
+    dir.write("src/util.ts", "")
+        .write("src/esm.mts", "")
+        .write("src/cjs.cts", "")
+        .write("src/both.ts", "")
+        .write("src/both.js", "");
+    let referrer = dir.path("src/index.ts");
+    let options = Options {
+        typescript: true,
+        ..Options::esm()
+    };
+
+    assert_eq!(
+        resolve("./util.js", &referrer, &options).unwrap(),
+        dir.path("src/util.ts")
+    );
+    assert_eq!(
+        resolve("./esm.mjs", &referrer, &options).unwrap(),
+        dir.path("src/esm.mts")
+    );
+    assert_eq!(
+        resolve("./cjs.cjs", &referrer, &options).unwrap(),
+        dir.path("src/cjs.cts")
+    );
+    // A JavaScript file that exists wins over the TypeScript one.
+    assert_eq!(
+        resolve("./both.js", &referrer, &options).unwrap(),
+        dir.path("src/both.js")
+    );
+    assert!(matches!(
+        esm("./util.js", &referrer),
+        Err(Error::NotFound { .. })
+    ));
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::Path;
+
+use ast_store::{parser, schema, visitor_keys};
+
+fn read_types_file(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("types")
+        .join(name);
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn generated_types_are_up_to_date() {
+    assert_eq!(
+        read_types_file("ast.schema.json"),
+        schema::json_schema(),
+        "run `cargo run --bin generate-types`"
+    );
+    assert_eq!(
+        read_types_file("ast.d.ts"),
+        schema::typescript_declarations(),
+        "run `cargo run --bin generate-types`"
+    );
+}
+
+#[test]
+fn parsed_program_matches_schema() {
+    let schema: serde_json::Value = serde_json::from_str(&schema::json_schema()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+
+    let code = "// comment\nconsole.log(\"hello, \", name);\ndebugger;\n";
+    let program = serde_json::to_value(parser::parse(code.to_string()).unwrap()).unwrap();
+
+    let errors: Vec<_> = match schema.validate(&program) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .map(|error| format!("{} at {}", error, error.instance_path))
+            .collect(),
+    };
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn schema_rejects_wrong_node_types() {
+    let schema: serde_json::Value = serde_json::from_str(&schema::json_schema()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+
+    let code = "foo(bar);\n";
+    let mut program = serde_json::to_value(parser::parse(code.to_string()).unwrap()).unwrap();
+    assert!(schema.is_valid(&program));
+    program["type"] = "Script".into();
+    assert!(!schema.is_valid(&program));
+
+    let mut program = serde_json::to_value(parser::parse(code.to_string()).unwrap()).unwrap();
+    let argument = &mut program["body"][0]["expression"]["arguments"][0];
+    *argument = serde_json::json!({
+        "type": "RestElement",
+        "loc": argument["loc"],
+        "range": argument["range"],
+        "argument": argument.clone(),
+    });
+    assert!(!schema.is_valid(&program));
+    program["body"][0]["expression"]["arguments"][0]["type"] = "SpreadElement".into();
+    assert!(schema.is_valid(&program));
+}
+
+#[test]
+fn declarations_include_node_types() {
+    let declarations = schema::typescript_declarations();
+    for (node_type, _) in visitor_keys::VISITOR_KEYS {
+        let start = format!("export interface {node_type} {{ type: \"{node_type}\", ");
+        assert!(
+            declarations.lines().any(|line| line.starts_with(&start)),
+            "{node_type} should have a `type` field"
+        );
+    }
+}
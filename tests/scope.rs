@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+
+use ast_store::scope::{self, ScopeManager};
+use ast_store::{ast, parser, visitor_keys};
+use serde_json::{json, Value};
+
+// Nodes in the same pre-order as `scope::NodeId`s, as `Type@offset` and
+// `name@offset` for identifiers.
+fn nodes(program: &ast::Program) -> Vec<(String, String)> {
+    fn visit(node: &Value, nodes: &mut Vec<(String, String)>) {
+        let node_type = node["type"].as_str().unwrap();
+        let start = &node["range"][0];
+        let identifier = match node.get("name") {
+            Some(Value::String(name)) => format!("{name}@{start}"),
+            _ => String::new(),
+        };
+        nodes.push((format!("{node_type}@{start}"), identifier));
+        for key in visitor_keys::get_keys(node_type).unwrap() {
+            match &node[key] {
+                Value::Array(children) => children.iter().for_each(|child| visit(child, nodes)),
+                Value::Null => {}
+                child => visit(child, nodes),
+            }
+        }
+    }
+
+    let mut nodes = vec![];
+    visit(&serde_json::to_value(program).unwrap(), &mut nodes);
+    nodes
+}
+
+// Same summary as `tests/fixtures/scope/generate.js`.
+fn summarize(manager: &ScopeManager, program: &ast::Program) -> Value {
+    let nodes = nodes(program);
+    let identifier = |reference: usize| &nodes[manager.references[reference].identifier].1;
+
+    let scopes: Vec<_> = manager
+        .scopes
+        .iter()
+        .map(|scope| {
+            let variables: Vec<_> = scope
+                .variables
+                .iter()
+                .map(|id| {
+                    let variable = &manager.variables[*id];
+                    json!({
+                        "name": variable.name,
+                        "defs": variable.defs.iter().map(|def| def.r#type).collect::<Vec<_>>(),
+                        "references": variable.references.iter().map(|id| identifier(*id)).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            let references: Vec<_> = scope
+                .references
+                .iter()
+                .map(|id| {
+                    let reference = &manager.references[*id];
+                    let resolved = reference.resolved.map(|id| {
+                        let variable = &manager.variables[id];
+                        format!("{}@{}", variable.name, variable.scope)
+                    });
+                    json!({
+                        "identifier": identifier(*id),
+                        "flag": reference.flag,
+                        "resolved": resolved,
+                    })
+                })
+                .collect();
+            json!({
+                "type": scope.r#type,
+                "isStrict": scope.is_strict,
+                "block": nodes[scope.block].0,
+                "upper": scope.upper,
+                "variableScope": scope.variable_scope,
+                "variables": variables,
+                "references": references,
+                "through": scope.through.iter().map(|id| identifier(*id)).collect::<Vec<_>>(),
+                "implicitLeft": scope.implicit.as_ref().map(|implicit| {
+                    implicit.left.iter().map(|id| identifier(*id)).collect::<Vec<_>>()
+                }),
+            })
+        })
+        .collect();
+    Value::Array(scopes)
+}
+
+#[test]
+fn matches_eslint_scope_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scope");
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "js")
+                && path.file_name().is_some_and(|name| name != "generate.js")
+        })
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    let mut failures = vec![];
+    for fixture in fixtures {
+        let code = fs::read_to_string(&fixture).unwrap();
+        let expected: Value =
+            serde_json::from_str(&fs::read_to_string(fixture.with_extension("json")).unwrap())
+                .unwrap();
+        let program = parser::parse(code).unwrap();
+
+        for (name, source_type) in [
+            ("script", ast::SourceType::Script),
+            ("module", ast::SourceType::Module),
+        ] {
+            let options = scope::Options {
+                source_type,
+                implied_strict: false,
+            };
+            let actual = summarize(&scope::analyze(&program, &options), &program);
+            if actual != expected[name] {
+                failures.push(format!(
+                    "{} ({name}):\nexpected {}\nfound    {}",
+                    fixture.display(),
+                    expected[name],
+                    actual
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn implied_strict() {
+    let program = parser::parse("foo();".to_string()).unwrap();
+    let options = scope::Options {
+        source_type: ast::SourceType::Script,
+        implied_strict: true,
+    };
+    let manager = scope::analyze(&program, &options);
+    assert!(manager.scopes[0].is_strict);
+}
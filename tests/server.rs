@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use ast_store::server::{Request, Server, ServerOptions};
+use ast_store::store::Store;
+use ast_store::{binary, parser};
+
+mod common;
+use common::TempDir;
+
+const CODE: &str = "// greeting\nconsole.log(\"hello\");\n";
+
+fn get(server: &Server, target: &str) -> ast_store::server::Response {
+    let request = Request::parse(&format!("GET {target} HTTP/1.1")).unwrap();
+    server.handle(&request)
+}
+
+#[test]
+fn parses_request_lines() {
+    assert_eq!(
+        Request::parse("GET /ast?path=%2Ftmp%2Fa%20b.js&tokens HTTP/1.1\r\n"),
+        Some(Request {
+            method: "GET".to_string(),
+            path: "/ast".to_string(),
+            query: vec![
+                ("path".to_string(), "/tmp/a b.js".to_string()),
+                ("tokens".to_string(), String::new()),
+            ],
+        })
+    );
+    assert_eq!(Request::parse("GET /ast"), None);
+    assert_eq!(Request::parse("GET /ast?path=%zz HTTP/1.1"), None);
+}
+
+#[test]
+fn serves_programs_by_path_and_hash() {
+    let dir = TempDir::new("formats");
+    let file = dir.0.join("hello.js");
+    fs::write(&file, CODE).unwrap();
+    let server = Server::new(Store::new(dir.0.join("cache")));
+    let path = file.to_str().unwrap();
+
+    let response = get(&server, &format!("/ast?path={path}"));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.content_type, "application/json");
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json["type"], "Program");
+    assert_eq!(json["tokens"], serde_json::json!([]));
+    assert_eq!(json["comments"], serde_json::json!([]));
+
+    let key = response.key.unwrap();
+    assert_eq!(key, Store::key(CODE));
+
+    let response = get(&server, &format!("/ast?hash={key}&tokens&comments=true"));
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    let expected = serde_json::to_value(parser::parse(CODE.to_string()).unwrap()).unwrap();
+    assert_eq!(json, expected);
+
+    let response = get(&server, &format!("/ast?path={path}&format=swc"));
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json["type"], "Module");
+    let response = get(&server, &format!("/ast?hash={key}&format=swc"));
+    assert_eq!(response.status, 400);
+
+    let response = get(&server, &format!("/ast?path={path}&format=binary"));
+    assert_eq!(response.content_type, "application/octet-stream");
+    let program = binary::decode(&response.body).unwrap();
+    assert_eq!(program.body.len(), 1);
+    assert!(program.tokens.is_empty());
+}
+
+#[test]
+fn reports_errors() {
+    let dir = TempDir::new("errors");
+    let file = dir.0.join("invalid.js");
+    fs::write(&file, "console.log(").unwrap();
+    let server = Server::new(Store::new(dir.0.join("cache")));
+    let path = file.to_str().unwrap();
+
+    assert_eq!(get(&server, &format!("/ast?path={path}")).status, 422);
+    assert_eq!(get(&server, "/ast?path=/does/not/exist.js").status, 404);
+    assert_eq!(
+        get(&server, &format!("/ast?hash={}", "0".repeat(64))).status,
+        404
+    );
+    assert_eq!(get(&server, "/ast?hash=../../etc/passwd").status, 404);
+    assert_eq!(
+        get(&server, &format!("/ast?path={path}&format=xml")).status,
+        400
+    );
+    assert_eq!(get(&server, "/ast").status, 400);
+    assert_eq!(get(&server, "/other").status, 404);
+
+    let request = Request::parse("POST /ast HTTP/1.1").unwrap();
+    assert_eq!(server.handle(&request).status, 405);
+}
+
+#[test]
+fn serves_over_tcp() {
+    let dir = TempDir::new("tcp");
+    let file = dir.0.join("hello.js");
+    fs::write(&file, CODE).unwrap();
+    let cache = dir.0.join("cache");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || Server::new(Store::new(cache)).serve_tcp(listener));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET /ast?path={}&format=binary HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        file.to_str().unwrap()
+    )
+    .unwrap();
+    let mut response = vec![];
+    stream.read_to_end(&mut response).unwrap();
+
+    let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let headers = String::from_utf8_lossy(&response[..header_end]);
+    assert!(headers.starts_with("HTTP/1.1 200 OK\r\n"), "{headers}");
+    assert!(headers.contains(&format!("X-Ast-Key: {}", Store::key(CODE))));
+
+    let program = binary::decode(&response[header_end + 4..]).unwrap();
+    assert_eq!(program.body.len(), 1);
+}
+
+fn read_response(stream: &mut TcpStream) -> io::Result<String> {
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+#[test]
+fn closes_stalled_connections() {
+    let dir = TempDir::new("timeout");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let options = ServerOptions {
+        timeout: Some(Duration::from_millis(100)),
+        ..ServerOptions::default()
+    };
+    let server = Server::with_options(Store::new(dir.0.join("cache")), options);
+    thread::spawn(move || server.serve_tcp(listener));
+
+    // Never sends a request, the server gives up on it.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert_eq!(read_response(&mut stream).unwrap(), "");
+}
+
+#[test]
+fn limits_concurrent_connections() {
+    let dir = TempDir::new("limit");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let options = ServerOptions {
+        timeout: None,
+        max_connections: 1,
+    };
+    let server = Server::with_options(Store::new(dir.0.join("cache")), options);
+    thread::spawn(move || server.serve_tcp(listener));
+
+    let idle = TcpStream::connect(addr).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET /other HTTP/1.1\r\n\r\n").unwrap();
+
+    // The idle connection takes up the only slot...
+    stream
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    let error = read_response(&mut stream).unwrap_err();
+    assert!(
+        matches!(
+            error.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ),
+        "{error}"
+    );
+
+    // ...until it goes away.
+    drop(idle);
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let response = read_response(&mut stream).unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{response}"
+    );
+}
+
+#[test]
+fn rejects_oversized_requests() {
+    let dir = TempDir::new("oversized");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || Server::new(Store::new(dir.0.join("cache"))).serve_tcp(listener));
+
+    // A request line that fills the limit without ever ending.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let line = format!("GET /{}", "a".repeat(8 * 1024 - 5));
+    stream.write_all(line.as_bytes()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let response = read_response(&mut stream).unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+        "{response}"
+    );
+    assert!(
+        response.ends_with("request header too large\n"),
+        "{response}"
+    );
+
+    // Same for headers.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = "GET /other HTTP/1.1\r\n";
+    let header = format!("X: {}", "a".repeat(8 * 1024 - request.len() - 3));
+    write!(stream, "{request}{header}").unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let response = read_response(&mut stream).unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+        "{response}"
+    );
+}
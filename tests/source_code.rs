@@ -0,0 +1,158 @@
+use ast_store::ast;
+use ast_store::parser;
+use ast_store::source_code::{CursorOptions, SourceCode, TokenOrComment};
+
+const CODE: &str = "// leading\nfoo(/* a */ \"🌍\", bar.baz);\r\ndebugger; // trailing\n";
+
+fn value<'a>(token: Option<TokenOrComment<'a>>) -> Option<&'a str> {
+    token.map(|token| token.value().value.as_str())
+}
+
+fn statement(program: &ast::Program, index: usize) -> &ast::Statement {
+    &program.body[index]
+}
+
+const WITH_COMMENTS: CursorOptions = CursorOptions {
+    include_comments: true,
+    skip: 0,
+};
+
+#[test]
+fn get_text() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let source = SourceCode::new(CODE, &program);
+
+    assert_eq!(
+        source.get_text(statement(&program, 0)),
+        "foo(/* a */ \"🌍\", bar.baz);"
+    );
+    assert_eq!(source.get_text(statement(&program, 1)), "debugger;");
+    assert_eq!(
+        source.get_text_around(statement(&program, 1), 2, 1),
+        "\r\ndebugger; "
+    );
+    assert_eq!(source.get_text(&program), &CODE[11..CODE.len() - 13]);
+}
+
+#[test]
+fn get_lines() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let source = SourceCode::new(CODE, &program);
+
+    assert_eq!(
+        source.get_lines(),
+        vec![
+            "// leading",
+            "foo(/* a */ \"🌍\", bar.baz);",
+            "debugger; // trailing",
+            ""
+        ]
+    );
+    assert_eq!(source.line_start_indices(), &[0, 11, 40, 62]);
+    let position = source.get_loc_from_index(41);
+    assert_eq!((position.line, position.column), (3, 1));
+}
+
+#[test]
+fn token_cursors() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let source = SourceCode::new(CODE, &program);
+    let first = statement(&program, 0);
+    let second = statement(&program, 1);
+
+    assert_eq!(
+        value(source.get_first_token(first, Default::default())),
+        Some("foo")
+    );
+    assert_eq!(
+        value(source.get_last_token(first, Default::default())),
+        Some(";")
+    );
+    assert_eq!(
+        value(source.get_last_token(
+            first,
+            CursorOptions {
+                skip: 1,
+                ..Default::default()
+            }
+        )),
+        Some(")")
+    );
+    assert_eq!(
+        value(source.get_token_before(second, Default::default())),
+        Some(";")
+    );
+    assert_eq!(
+        value(source.get_token_before(first, Default::default())),
+        None
+    );
+    assert_eq!(
+        value(source.get_token_before(first, WITH_COMMENTS)),
+        Some(" leading")
+    );
+    assert_eq!(
+        value(source.get_token_after(first, Default::default())),
+        Some("debugger")
+    );
+    assert_eq!(
+        value(source.get_token_after(second, Default::default())),
+        None
+    );
+    assert_eq!(
+        value(source.get_token_after(second, WITH_COMMENTS)),
+        Some(" trailing")
+    );
+
+    let tokens: Vec<_> = source
+        .get_tokens(first, WITH_COMMENTS)
+        .iter()
+        .take(3)
+        .map(|token| token.value().value.as_str())
+        .collect();
+    assert_eq!(tokens, vec!["foo", "(", " a "]);
+    assert_eq!(source.tokens_and_comments().len(), program.tokens.len() + 3);
+}
+
+#[test]
+fn comments() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let source = SourceCode::new(CODE, &program);
+    let first = statement(&program, 0);
+    let second = statement(&program, 1);
+
+    let values = |comments: Vec<&ast::Comment>| -> Vec<String> {
+        comments
+            .into_iter()
+            .map(|comment| comment.value().value.clone())
+            .collect()
+    };
+    assert_eq!(values(source.get_comments_before(first)), vec![" leading"]);
+    assert!(source.get_comments_before(second).is_empty());
+    assert_eq!(values(source.get_comments_after(second)), vec![" trailing"]);
+    assert_eq!(values(source.get_comments_inside(first)), vec![" a "]);
+}
+
+#[test]
+fn is_space_between() {
+    let program = parser::parse(CODE.to_string()).unwrap();
+    let source = SourceCode::new(CODE, &program);
+
+    let ast::Statement::ExpressionStatement(stmt) = statement(&program, 0) else {
+        panic!("expected an expression statement");
+    };
+    let ast::Expression::CallExpression(call) = stmt.expression.as_ref() else {
+        panic!("expected a call expression");
+    };
+    let [first, second] = &call.arguments[..] else {
+        panic!("expected two arguments");
+    };
+
+    // `foo(` and `"🌍"` are only separated by a comment and a space.
+    assert!(source.is_space_between(call.callee.as_ref(), first));
+    // `"🌍",` then a space.
+    assert!(source.is_space_between(first, second));
+    assert!(!source.is_space_between(second, &program.tokens[program.tokens.len() - 3]));
+    assert!(source.is_space_between(statement(&program, 0), statement(&program, 1)));
+    // Overlapping.
+    assert!(!source.is_space_between(stmt, call));
+}
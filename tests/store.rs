@@ -0,0 +1,147 @@
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+use ast_store::parser;
+use ast_store::store::{GcOptions, Store};
+
+mod common;
+use common::TempDir;
+
+fn body_len(program: &ast_store::ast::Program) -> usize {
+    program.body.len()
+}
+
+#[test]
+fn serves_hits_without_parsing() {
+    let dir = TempDir::new("hits");
+    let store = Store::new(&dir.0);
+    let source = "console.log(\"hello\");\n";
+
+    assert!(store.get(source).is_none());
+    let program = store.get_or_parse(source).unwrap();
+    assert_eq!(
+        serde_json::to_value(store.get(source).unwrap()).unwrap(),
+        serde_json::to_value(&program).unwrap()
+    );
+
+    // Replacing the entry shows that hits come from the store rather than
+    // from parsing `source` again.
+    let other = parser::parse("debugger;\ndebugger;\n".to_string()).unwrap();
+    store.insert(source, &other).unwrap();
+    assert_eq!(body_len(&store.get_or_parse(source).unwrap()), 2);
+
+    assert!(store.get("debugger;\n").is_none());
+    assert!(store.get_or_parse("console.log(").is_err());
+}
+
+#[test]
+fn ignores_corrupt_entries() {
+    let dir = TempDir::new("corrupt");
+    let store = Store::new(&dir.0);
+    let source = "debugger;\n";
+
+    store.get_or_parse(source).unwrap();
+    for file in fs::read_dir(&dir.0).unwrap() {
+        for entry in fs::read_dir(file.unwrap().path()).unwrap() {
+            fs::write(entry.unwrap().path(), b"garbage").unwrap();
+        }
+    }
+
+    assert!(store.get(source).is_none());
+    assert_eq!(body_len(&store.get_or_parse(source).unwrap()), 1);
+    assert!(store.get(source).is_some());
+}
+
+#[test]
+fn collects_garbage_by_age_and_size() {
+    let dir = TempDir::new("gc");
+    let store = Store::new(&dir.0);
+    let sources: Vec<_> = (0..4).map(|i| format!("foo{i}.bar();\n")).collect();
+
+    // Each entry was last used an hour earlier than the next one.
+    let now = SystemTime::now();
+    for (i, source) in sources.iter().enumerate() {
+        store.get_or_parse(source).unwrap();
+
+        let key = Store::key(source);
+        let path = dir.0.join(&key[..2]).join(format!("{}.estb", &key[2..]));
+        let file = File::options().write(true).open(path).unwrap();
+        let age = Duration::from_secs(3600 * (sources.len() - i) as u64);
+        file.set_modified(now - age).unwrap();
+    }
+
+    let stats = store
+        .gc(&GcOptions {
+            max_age: Some(Duration::from_secs(3600 * 3 + 60)),
+            max_size: None,
+        })
+        .unwrap();
+    assert_eq!((stats.removed, stats.kept), (1, 3));
+    assert!(store.get(&sources[0]).is_none());
+
+    let entry_size = stats.kept_bytes / 3;
+    let stats = store
+        .gc(&GcOptions {
+            max_age: None,
+            max_size: Some(entry_size * 2),
+        })
+        .unwrap();
+    assert_eq!((stats.removed, stats.kept), (1, 2));
+    assert!(store.get(&sources[1]).is_none());
+    assert!(store.get(&sources[2]).is_some());
+    assert!(store.get(&sources[3]).is_some());
+}
+
+#[test]
+fn concurrent_inserts_dont_collide() {
+    let dir = TempDir::new("concurrent");
+    let store = Store::new(&dir.0);
+    let source = "foo.bar(baz);\n";
+    let program = parser::parse(source.to_string()).unwrap();
+
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..20 {
+                    store.insert(source, &program).unwrap();
+                }
+            });
+        }
+    });
+
+    assert!(store.get(source).is_some());
+    let files: Vec<_> = fs::read_dir(&dir.0)
+        .unwrap()
+        .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+        .map(|file| file.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1, "{files:?}");
+}
+
+#[test]
+fn collects_leftover_temporary_files() {
+    let dir = TempDir::new("tmp");
+    let store = Store::new(&dir.0);
+    store.get_or_parse("debugger;\n").unwrap();
+
+    let now = SystemTime::now();
+    let subdir = dir.0.join("00");
+    fs::create_dir_all(&subdir).unwrap();
+    let old = subdir.join("old.1.0.tmp");
+    let new = subdir.join("new.1.1.tmp");
+    for (path, age) in [(&old, 2 * 3600), (&new, 60)] {
+        fs::write(path, b"partial").unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(now - Duration::from_secs(age)).unwrap();
+    }
+
+    let stats = store.gc(&GcOptions::default()).unwrap();
+    assert_eq!(
+        (stats.removed, stats.removed_tmp_files, stats.kept),
+        (0, 1, 1)
+    );
+    assert_eq!(stats.removed_bytes, 7);
+    assert!(!old.exists());
+    // It might still be being written.
+    assert!(new.exists());
+}
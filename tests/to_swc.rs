@@ -0,0 +1,75 @@
+use ast_store::{parser, to_swc};
+use swc_common::sync::Lrc;
+use swc_common::{FileName, SourceMap};
+use swc_ecma_ast::{EsVersion, Module};
+use swc_ecma_parser::{parse_file_as_module, Syntax, TsConfig};
+
+fn swc_parse(code: &str) -> Module {
+    let cm = Lrc::<SourceMap>::default();
+    let fm = cm.new_source_file(FileName::Anon, code.to_string());
+    parse_file_as_module(
+        &fm,
+        Syntax::Typescript(TsConfig::default()),
+        EsVersion::default(),
+        None,
+        &mut vec![],
+    )
+    .unwrap()
+}
+
+// Going through JSON like a program edited by a JS tool would should give
+// back the module swc parses from the same code, spans included.
+fn assert_round_trips(code: &str) {
+    let program = parser::parse(code.to_string()).unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    let program = to_swc::from_json(&json).unwrap();
+    assert_eq!(
+        to_swc::convert_program(&program, code),
+        swc_parse(code),
+        "{code}"
+    );
+}
+
+#[test]
+fn round_trips_through_json() {
+    assert_round_trips("");
+    assert_round_trips("debugger;");
+    assert_round_trips("foo;\n  bar.baz;\n");
+    assert_round_trips("foo.bar(\"baz\", 'qux')(quux);");
+    assert_round_trips("// comment\na(b.c, /* d */ e);");
+    assert_round_trips("foo(...bar, baz, ... qux.quux('a'));");
+}
+
+#[test]
+fn round_trips_non_ascii_sources() {
+    assert_round_trips("é;\n  ünïcode.naïve('🌍', 'ü');");
+    assert_round_trips("/* 🌍 */ foo(...𝒳, 'é');\n\u{2028}bar;");
+}
+
+#[test]
+fn reads_programs_without_tokens_or_comments() {
+    let json = r#"{
+        "type": "Program",
+        "loc": {"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 4}},
+        "range": [0, 4],
+        "sourceType": "module",
+        "body": [{
+            "type": "ExpressionStatement",
+            "loc": {"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 4}},
+            "range": [0, 4],
+            "expression": {
+                "type": "Identifier",
+                "loc": {"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 3}},
+                "range": [0, 3],
+                "name": "foo"
+            }
+        }]
+    }"#;
+    let program = to_swc::from_json(json).unwrap();
+    assert_eq!(to_swc::convert_program(&program, "foo;"), swc_parse("foo;"));
+}
+
+#[test]
+fn rejects_invalid_json() {
+    assert!(to_swc::from_json(r#"{"type": "Program"}"#).is_err());
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use ast_store::parser;
+use ast_store::transpile::{is_typescript, remap_stack, transpile};
+
+const RULE: &str = "\
+interface Options {
+  allow: string[];
+}
+
+export const create = (options: Options): number => {
+  throw new Error(options.allow.join());
+};
+";
+
+#[test]
+fn typescript_extensions() {
+    assert!(is_typescript(Path::new("rules/a.ts")));
+    assert!(is_typescript(Path::new("rules/a.mts")));
+    assert!(is_typescript(Path::new("rules/a.cts")));
+    assert!(!is_typescript(Path::new("rules/a.js")));
+    assert!(!is_typescript(Path::new("rules/a.d")));
+}
+
+#[test]
+fn strips_types() {
+    let output = transpile(Path::new("/rules/a.ts"), RULE.to_string()).unwrap();
+    let (code, source_map_url) = output.code.rsplit_once('\n').unwrap();
+
+    assert_eq!(
+        code,
+        "export const create = (options)=>{\n    throw new Error(options.allow.join());\n};\n"
+    );
+    assert!(source_map_url.starts_with("//# sourceMappingURL=data:application/json;base64,"));
+    assert_eq!(output.source_map.get_source_contents(0), Some(RULE));
+}
+
+#[test]
+fn syntax_errors() {
+    let result = transpile(Path::new("/rules/a.ts"), "let a: = 1;".to_string());
+    let Err(parser::Error::Syntax(diagnostics)) = result else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(diagnostics[0].loc.start.line, 1);
+    assert_eq!(diagnostics[0].loc.start.column, 7);
+}
+
+#[test]
+fn remaps_stack_traces() {
+    let path = Path::new("/rules/a.ts");
+    let output = transpile(path, RULE.to_string()).unwrap();
+
+    // `throw` is on line 2 of the output and line 6 of the source.
+    let stack = "Error: a\n    at create (/rules/a.ts:2:11)\n    at /other/rules/a.ts:2:11\n    at /rules/b.js:2:11";
+    assert_eq!(
+        remap_stack(stack, path, &output.source_map),
+        "Error: a\n    at create (/rules/a.ts:6:9)\n    at /other/rules/a.ts:2:11\n    at /rules/b.js:2:11"
+    );
+}
@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ast_store::{parser, visitor_keys};
+use serde_json::Value;
+
+// ESLint doesn't traverse into these even though they contain typed objects.
+const NOT_VISITED: &[(&str, &str)] = &[("Program", "tokens"), ("Program", "comments")];
+
+fn is_node(value: &Value) -> bool {
+    match value {
+        Value::Object(object) => object.contains_key("type"),
+        Value::Array(values) => !values.is_empty() && values.iter().all(is_node),
+        _ => false,
+    }
+}
+
+// The fields of each node type that hold nodes or lists of nodes, as
+// serialized, along with the children of each node in visitor key order.
+fn node_fields(
+    node: &Value,
+    fields: &mut BTreeMap<String, BTreeSet<String>>,
+    order: &mut Vec<(String, Vec<u64>)>,
+) {
+    let node_type = node["type"].as_str().unwrap();
+    let entry = fields.entry(node_type.to_string()).or_default();
+    for (key, value) in node.as_object().unwrap() {
+        if is_node(value) && !NOT_VISITED.contains(&(node_type, key.as_str())) {
+            entry.insert(key.clone());
+        }
+    }
+
+    let mut starts = vec![];
+    for key in visitor_keys::get_keys(node_type).unwrap_or_default() {
+        let children = match &node[key] {
+            Value::Array(children) => children.iter().collect(),
+            Value::Null => vec![],
+            child => vec![child],
+        };
+        for child in children {
+            starts.push(child["range"][0].as_u64().unwrap());
+            node_fields(child, fields, order);
+        }
+    }
+    order.push((node_type.to_string(), starts));
+}
+
+// Every node type the parser can produce.
+fn sample() -> Value {
+    let program = parser::parse("a.b(...c, 'd');\ndebugger;".to_string()).unwrap();
+    serde_json::to_value(program).unwrap()
+}
+
+#[test]
+fn match_serialized_node_fields() {
+    let mut fields = BTreeMap::new();
+    let mut order = vec![];
+    node_fields(&sample(), &mut fields, &mut order);
+
+    let sampled: BTreeSet<_> = fields.keys().map(String::as_str).collect();
+    let all: BTreeSet<_> = visitor_keys::VISITOR_KEYS
+        .iter()
+        .map(|(node_type, _)| *node_type)
+        .collect();
+    assert_eq!(sampled, all, "the sample should contain every node type");
+
+    for (node_type, fields) in &fields {
+        let keys: BTreeSet<_> = visitor_keys::get_keys(node_type)
+            .unwrap()
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert_eq!(&keys, fields, "visitor keys of {node_type}");
+    }
+}
+
+#[test]
+fn follow_source_order() {
+    let mut order = vec![];
+    node_fields(&sample(), &mut BTreeMap::new(), &mut order);
+    for (node_type, starts) in order {
+        assert!(
+            starts.windows(2).all(|pair| pair[0] <= pair[1]),
+            "children of {node_type} aren't visited in source order: {starts:?}"
+        );
+    }
+}
@@ -0,0 +1,21 @@
+// Generated by `cargo run --bin generate-types`, do not edit.
+
+export interface Position { line: number, column: number, }
+export interface Location { start: Position, end: Position, }
+export type Comment = { "type": "Line" } & TokenValue | { "type": "Block" } & TokenValue;
+export interface Program { type: "Program", loc: Location, range: [number, number], sourceType: SourceType, body: Array<Statement>, tokens: Array<Token>, comments: Array<Comment>, }
+export type SourceType = "script" | "module";
+export type Statement = { "type": "DebuggerStatement" } & DebuggerStatement | { "type": "ExpressionStatement" } & ExpressionStatement;
+export interface DebuggerStatement { type: "DebuggerStatement", loc: Location, range: [number, number], }
+export interface ExpressionStatement { type: "ExpressionStatement", loc: Location, range: [number, number], expression: Expression, }
+export type Expression = { "type": "Literal" } & Literal | { "type": "Identifier" } & Identifier | { "type": "CallExpression" } & CallExpression | { "type": "MemberExpression" } & MemberExpression;
+export interface Identifier { type: "Identifier", loc: Location, range: [number, number], name: string, }
+export interface Literal { type: "Literal", loc: Location, range: [number, number], value: Value, raw: string | null, }
+export type Value = string | number;
+export interface CallExpression { type: "CallExpression", loc: Location, range: [number, number], callee: Expression, arguments: Array<ExprOrSpread>, optional: boolean, }
+export type ExprOrSpread = Expression | SpreadElement;
+export interface SpreadElement { type: "SpreadElement", loc: Location, range: [number, number], argument: Expression, }
+export interface MemberExpression { type: "MemberExpression", loc: Location, range: [number, number], object: Expression, property: Prop, computed: boolean, optional: boolean, }
+export type Prop = { "type": "Identifier" } & Identifier;
+export type Token = { "type": "Identifier" } & TokenValue | { "type": "Keyword" } & TokenValue | { "type": "String" } & TokenValue | { "type": "Punctuator" } & TokenValue;
+export interface TokenValue { loc: Location, range: [number, number], value: string, }